@@ -38,6 +38,10 @@ impl CliOverridableConfig<FullNodePreset> for CliFullNodeConfig {
             self.full_node.verifying_key_str = verifying_key_str.clone();
         }
 
+        if let Some(allowed_operations) = &args.allowed_operations {
+            self.full_node.allowed_operations = Some(allowed_operations.iter().cloned().collect());
+        }
+
         Ok(())
     }
 }
@@ -62,6 +66,10 @@ impl CliOverridableConfig<ProverPreset> for CliProverConfig {
             self.prover.recursive_proofs = recursive_proofs;
         }
 
+        if let Some(allowed_operations) = &args.allowed_operations {
+            self.prover.allowed_operations = Some(allowed_operations.iter().cloned().collect());
+        }
+
         Ok(())
     }
 }
@@ -35,6 +35,7 @@ mod tests {
             enabled: false,
             host: "0.0.0.0".to_string(),
             port: 8080,
+            ..WebServerConfig::default()
         };
 
         let web_args = CliWebserverArgs {
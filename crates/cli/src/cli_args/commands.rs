@@ -68,6 +68,11 @@ pub struct FullNodeCliArgs {
     /// base64-encoded SPKI DER content directly.
     pub verifying_key: Option<String>,
 
+    #[arg(long, value_delimiter = ',')]
+    /// Types of the operations accepted from clients, e.g. `create_did,set_rotation_keys`.
+    /// All operations are accepted if unset.
+    pub allowed_operations: Option<Vec<String>>,
+
     #[command(flatten)]
     pub da: CliDaLayerArgs,
 
@@ -122,6 +127,11 @@ pub struct ProverCliArgs {
     /// Enable recursive proofs for more efficient verification
     pub recursive_proofs: Option<bool>,
 
+    #[arg(long, value_delimiter = ',')]
+    /// Types of the operations accepted from clients, e.g. `create_did,set_rotation_keys`.
+    /// All operations are accepted if unset.
+    pub allowed_operations: Option<Vec<String>>,
+
     #[command(flatten)]
     pub da: CliDaLayerArgs,
 
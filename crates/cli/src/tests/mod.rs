@@ -1,5 +1,5 @@
 use anyhow::Result;
-use std::{env, fs};
+use std::{collections::HashSet, env, fs};
 use tempfile::TempDir;
 
 use crate::{
@@ -158,6 +158,7 @@ path = "/config/path"
         specter: false,
         config_path,
         verifying_key: Some("cli_key".to_string()),
+        allowed_operations: None,
         da: CliDaLayerArgs::default(),
         db: CliDatabaseArgs {
             db_type: Some(CliDatabaseType::RocksDB),
@@ -214,6 +215,7 @@ port = 8080
         specter: false,
         config_path,
         verifying_key: None, // No CLI override
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: Default::default(),
@@ -237,6 +239,7 @@ fn test_prover_config_cli_args_precedence() -> Result<()> {
 signing_key_path = "/config/key.pem"
 max_epochless_gap = 5
 recursive_proofs = false
+allowed_operations = ["create_account"]
 
 [webserver]
 enabled = false
@@ -261,6 +264,10 @@ path = "/config/db"
         signing_key: Some("/cli/key.pem".to_string()),
         max_epochless_gap: Some(15),
         recursive_proofs: Some(true),
+        allowed_operations: Some(vec![
+            "create_did".to_string(),
+            "set_rotation_keys".to_string(),
+        ]),
         da: Default::default(),
         db: CliDatabaseArgs {
             db_type: Some(CliDatabaseType::RocksDB),
@@ -281,6 +288,13 @@ path = "/config/db"
     assert!(config.prover.recursive_proofs);
     assert!(config.prover.webserver.enabled);
     assert_eq!(config.prover.webserver.port, 4000);
+    assert_eq!(
+        config.prover.allowed_operations,
+        Some(HashSet::from([
+            "create_did".to_string(),
+            "set_rotation_keys".to_string()
+        ]))
+    );
 
     if let prism_storage::DatabaseConfig::RocksDB(rocksdb_config) = &config.db {
         assert_eq!(rocksdb_config.path, "/cli/db");
@@ -316,6 +330,7 @@ recursive_proofs = false
         signing_key: None, // No CLI override
         max_epochless_gap: None,
         recursive_proofs: None,
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: Default::default(),
@@ -344,6 +359,7 @@ fn test_full_node_preset_application() -> Result<()> {
         specter: false,
         config_path,
         verifying_key: None,
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: Default::default(),
@@ -369,6 +385,7 @@ fn test_prover_preset_application() -> Result<()> {
         signing_key: None,
         max_epochless_gap: None,
         recursive_proofs: None,
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: Default::default(),
@@ -390,6 +407,7 @@ fn test_conflicting_presets_error() {
         specter: true, // This should be prevented by clap conflicts_with
         config_path: "/tmp/config.toml".to_string(),
         verifying_key: None,
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: Default::default(),
@@ -425,6 +443,7 @@ port = 8080
         signing_key: None,           // Use config value
         max_epochless_gap: Some(10), // Override config
         recursive_proofs: None,      // Use config value
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: CliWebserverArgs {
@@ -466,6 +485,7 @@ invalid toml syntax
         signing_key: Some("/cli/key.pem".to_string()),
         max_epochless_gap: Some(5),
         recursive_proofs: Some(true),
+        allowed_operations: None,
         da: Default::default(),
         db: Default::default(),
         web: Default::default(),
@@ -1,12 +1,15 @@
+use prism_common::api::DEFAULT_POLLING_INTERVAL;
 use serde::{Serialize, de::DeserializeOwned};
 use std::{
     error::Error,
     fmt::{Display, Formatter},
+    time::Duration,
 };
 
 pub struct PrismHttpClient {
     client: reqwest::Client,
     base_url: url::Url,
+    polling_interval: Duration,
 }
 
 impl PrismHttpClient {
@@ -14,26 +17,55 @@ impl PrismHttpClient {
         Ok(Self {
             client: reqwest::Client::new(),
             base_url: reqwest::Url::parse(base_url).map_err(Into::<PrismHttpClientError>::into)?,
+            polling_interval: DEFAULT_POLLING_INTERVAL,
         })
     }
 
+    /// Sets the interval at which transactions posted by this client poll for their application,
+    /// e.g. a shorter one for a local node.
+    pub fn with_polling_interval(mut self, polling_interval: Duration) -> Self {
+        self.polling_interval = polling_interval;
+        self
+    }
+
+    pub fn polling_interval(&self) -> Duration {
+        self.polling_interval
+    }
+
     pub async fn fetch<T>(&self, path: &str) -> Result<T, PrismHttpClientError>
     where
         T: DeserializeOwned,
     {
         let url = self.join_url(path)?;
-        let response = self.client.get(&url).send().await?;
-        response.json::<T>().await.map_err(Into::<PrismHttpClientError>::into)
+        let response = Self::check_status(self.client.get(&url).send().await?).await?;
+        Self::decode(response).await
     }
 
     pub async fn post<T, U>(&self, path: &str, body: &T) -> Result<U, PrismHttpClientError>
+    where
+        T: Serialize,
+        U: DeserializeOwned,
+    {
+        self.post_with_headers(path, body, &[]).await
+    }
+
+    pub async fn post_with_headers<T, U>(
+        &self,
+        path: &str,
+        body: &T,
+        headers: &[(&str, &str)],
+    ) -> Result<U, PrismHttpClientError>
     where
         T: Serialize,
         U: DeserializeOwned,
     {
         let url = self.join_url(path)?;
-        let response = self.client.post(&url).json(body).send().await?;
-        response.json::<U>().await.map_err(Into::<PrismHttpClientError>::into)
+        let request = headers.iter().fold(
+            self.client.post(&url).json(body),
+            |request, (name, value)| request.header(*name, *value),
+        );
+        let response = Self::check_status(request.send().await?).await?;
+        Self::decode(response).await
     }
 
     pub async fn post_no_response<T>(
@@ -45,10 +77,30 @@ impl PrismHttpClient {
         T: Serialize,
     {
         let url = self.join_url(path)?;
-        self.client.post(&url).json(body).send().await?;
+        Self::check_status(self.client.post(&url).json(body).send().await?).await?;
         Ok(())
     }
 
+    /// Turns non-success responses into [`PrismHttpClientError::Status`], keeping the body.
+    async fn check_status(
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, PrismHttpClientError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(PrismHttpClientError::Status(status.as_u16(), body))
+    }
+
+    /// Parses the JSON body, keeping the parser's message in [`PrismHttpClientError::Decode`].
+    async fn decode<T: DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T, PrismHttpClientError> {
+        let body = response.bytes().await?;
+        serde_json::from_slice(&body).map_err(Into::<PrismHttpClientError>::into)
+    }
+
     fn join_url(&self, path: &str) -> Result<String, PrismHttpClientError> {
         self.base_url
             .join(path)
@@ -59,9 +111,9 @@ impl PrismHttpClient {
 
 #[derive(Debug)]
 pub enum PrismHttpClientError {
-    Decode,
+    Decode(String),
     Request,
-    Status(u16),
+    Status(u16, String),
     Url(String),
     Unknown,
 }
@@ -69,10 +121,10 @@ pub enum PrismHttpClientError {
 impl Display for PrismHttpClientError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            PrismHttpClientError::Decode => write!(f, "Failed to decode response"),
+            PrismHttpClientError::Decode(err) => write!(f, "Failed to decode response: {}", err),
             PrismHttpClientError::Request => write!(f, "Request failed"),
-            PrismHttpClientError::Status(code) => {
-                write!(f, "Request failed with status code: {}", code)
+            PrismHttpClientError::Status(code, body) => {
+                write!(f, "Request failed with status code {}: {}", code, body)
             }
             PrismHttpClientError::Url(err) => write!(f, "URL parse error: {}", err),
             PrismHttpClientError::Unknown => write!(f, "Unknown error occurred"),
@@ -87,10 +139,11 @@ impl From<reqwest::Error> for PrismHttpClientError {
         if err.is_request() {
             PrismHttpClientError::Request
         } else if err.is_decode() {
-            PrismHttpClientError::Decode
+            PrismHttpClientError::Decode(err.to_string())
         } else if err.is_status() {
             PrismHttpClientError::Status(
                 err.status().expect("Status error should contain status").into(),
+                String::new(),
             )
         } else {
             PrismHttpClientError::Unknown
@@ -98,6 +151,12 @@ impl From<reqwest::Error> for PrismHttpClientError {
     }
 }
 
+impl From<serde_json::Error> for PrismHttpClientError {
+    fn from(err: serde_json::Error) -> Self {
+        PrismHttpClientError::Decode(err.to_string())
+    }
+}
+
 impl From<url::ParseError> for PrismHttpClientError {
     fn from(err: url::ParseError) -> Self {
         PrismHttpClientError::Url(err.to_string())
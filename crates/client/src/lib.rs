@@ -4,7 +4,11 @@ mod prism_api;
 pub use http_client::{PrismHttpClient, PrismHttpClientError};
 pub use prism_common::{
     account::Account,
-    api::{PendingTransaction, PrismApi, PrismApiError, types::*},
+    api::{
+        DidMethod, DidResolver, PendingTransaction, PlcDirectory, PrismApi, PrismApiError,
+        plc_directory::{DEFAULT_PLC_DIRECTORY_URL, PlcDirectoryClient},
+        types::*,
+    },
     builder,
     digest::Digest,
     operation::SignatureBundle,
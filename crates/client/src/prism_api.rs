@@ -1,23 +1,21 @@
-use std::{future::Future, time::Duration};
-
 use async_trait::async_trait;
 use prism_common::{
     api::{
-        PendingTransaction, PendingTransactionImpl, PrismApi, PrismApiError, PrismApiTimer,
-        types::{AccountRequest, AccountResponse, CommitmentResponse},
+        IDEMPOTENCY_KEY_HEADER, PendingTransaction, PendingTransactionImpl, PrismApi,
+        PrismApiError,
+        timer::TokioTimer,
+        types::{
+            AccountRequest, AccountResponse, CommitmentResponse, QueuedTransaction,
+            ResolveHandleRequest, ResolveHandleResponse, TransactionResult,
+        },
     },
+    operation::SignedPLCOp,
     transaction::Transaction,
 };
 
 use crate::{PrismHttpClient, PrismHttpClientError};
 
-pub struct PrismHttpTokioTimer;
-
-impl PrismApiTimer for PrismHttpTokioTimer {
-    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
-        tokio::time::sleep(duration)
-    }
-}
+pub type PrismHttpTokioTimer = TokioTimer;
 
 #[async_trait]
 impl PrismApi for PrismHttpClient {
@@ -37,22 +35,91 @@ impl PrismApi for PrismHttpClient {
     async fn post_transaction(
         &self,
         transaction: Transaction,
+        idempotency_key: Option<String>,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
-        self.post_no_response("/transaction", &transaction).await?;
-        Ok(PendingTransactionImpl::new(self, transaction))
+        let headers = idempotency_key
+            .as_deref()
+            .map(|key| (IDEMPOTENCY_KEY_HEADER, key))
+            .into_iter()
+            .collect::<Vec<_>>();
+        let receipt: QueuedTransaction =
+            self.post_with_headers("/transaction", &transaction, &headers).await?;
+        Ok(PendingTransactionImpl::new(self, transaction)
+            .with_polling_interval(self.polling_interval())
+            .with_receipt(receipt))
+    }
+
+    async fn get_genesis_operation(&self, did: &str) -> Result<SignedPLCOp, PrismApiError> {
+        let response = self.fetch(&format!("/{}/genesis", did)).await?;
+        Ok(response)
+    }
+
+    async fn resolve_handle(&self, handle: &str) -> Result<String, PrismApiError> {
+        let request = ResolveHandleRequest {
+            handle: handle.to_string(),
+        };
+        let response: ResolveHandleResponse = self.post("/resolve-handle", &request).await?;
+        Ok(response.did)
+    }
+
+    async fn post_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionResult>, PrismApiError> {
+        let response = self.post("/transactions", &transactions).await?;
+        Ok(response)
     }
 }
 
 impl From<PrismHttpClientError> for PrismApiError {
     fn from(err: PrismHttpClientError) -> Self {
         match err {
-            PrismHttpClientError::Decode => PrismApiError::SerdeFailed(err.to_string()),
+            PrismHttpClientError::Decode(msg) => PrismApiError::SerdeFailed(msg),
             PrismHttpClientError::Request => PrismApiError::RequestFailed("unspecific".to_string()),
-            PrismHttpClientError::Status(status) => {
-                PrismApiError::RequestFailed(format!("Status: {}", status))
-            }
+            PrismHttpClientError::Status(status, body) => PrismApiError::Http { status, body },
             PrismHttpClientError::Url(msg) => PrismApiError::InvalidTarget(msg),
             PrismHttpClientError::Unknown => PrismApiError::Unknown,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use prism_keys::SigningKey;
+
+    use super::*;
+    use prism_common::test_utils::{create_did_tx, serve_once};
+
+    #[tokio::test]
+    async fn test_post_transaction_returns_receipt() {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let receipt = QueuedTransaction {
+            tx_hash: transaction.hash().unwrap(),
+            queue_position: 3,
+            target_epoch: 7,
+        };
+        let (url, request) = serve_once("200 OK", serde_json::to_string(&receipt).unwrap()).await;
+
+        let client = PrismHttpClient::new(&url).unwrap();
+        let pending =
+            client.post_transaction(transaction, Some("retry-1".to_string())).await.unwrap();
+
+        assert_eq!(pending.receipt(), Some(&receipt));
+        let request = request.await.unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("post /transaction http/1.1"));
+        assert!(request.contains("idempotency-key: retry-1"));
+    }
+
+    #[tokio::test]
+    async fn test_post_transaction_reports_malformed_receipt() {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let (url, _) = serve_once("200 OK", r#""queued""#.to_string()).await;
+
+        let client = PrismHttpClient::new(&url).unwrap();
+        let result = client.post_transaction(transaction, None).await;
+
+        assert!(matches!(result, Err(PrismApiError::SerdeFailed(_))));
+    }
+}
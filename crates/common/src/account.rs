@@ -1,21 +1,25 @@
 use std::collections::HashMap;
 
 use anyhow::{Result, anyhow};
-use bs58;
-use prism_errors::AccountError;
-use prism_keys::VerifyingKey;
-use prism_serde::raw_or_b64;
+use prism_errors::{AccountError, DidError, OperationError, TransactionError};
+use prism_keys::{CryptoAlgorithm, Signature, VerifyingKey};
+use prism_serde::{base64::FromBase64, binary::ToBinary, raw_or_b64};
 use serde::{Deserialize, Serialize};
+use url::Url;
 use utoipa::ToSchema;
 
 use crate::{
     api::{
         PrismApi,
         noop::NoopPrismApi,
-        types::{DidDocument, DidService, VerificationMethod},
+        types::{DidDocument, DidService, VerificationMethod, VerificationMethodType},
     },
     builder::{ModifyAccountRequestBuilder, RequestBuilder},
-    operation::Operation,
+    digest::Digest,
+    operation::{
+        DidConfig, Operation, PLC_OPERATION_TYPE, SignedPLCOp, UnsignedPLCOp,
+        service_challenge_hash,
+    },
     transaction::Transaction,
 };
 
@@ -50,13 +54,48 @@ pub struct Account {
     /// The current set of valid keys for the account. Any of these keys can be
     /// used to sign transactions.
     #[serde(rename = "rotationKeys")]
-    rotation_keys: Vec<VerifyingKey>,
+    rotation_keys: RotationKeySet,
 
     #[serde(rename = "alsoKnownAs")]
     also_known_as: Vec<String>,
     /// Set of service / URL mappings. the key strings should not include a `#`
     /// prefix; that will be added when rendering the DID document.
     services: HashMap<String, Service>,
+
+    /// The CID of the most recently applied operation, see [`Operation::cid`].
+    #[serde(default, rename = "headCid")]
+    head_cid: Option<String>,
+
+    /// Whether the DID has been deactivated.
+    #[serde(default)]
+    deactivated: bool,
+
+    /// The key signing the challenges for account creation, if this account is a service. See
+    /// [`Operation::RegisterService`].
+    #[serde(default, rename = "challengeKey")]
+    challenge_key: Option<VerifyingKey>,
+
+    /// The latest operations a recovery fork may still override, oldest first. At most
+    /// [`DEFAULT_PLC_RECOVERY_WINDOW`] are kept, see
+    /// [`Account::process_transaction_with_fork_base`].
+    #[serde(default, rename = "recentOperations")]
+    recent_operations: Vec<RecentOperation>,
+}
+
+/// An operation applied by [`Account::process_transaction`], kept so that a recovery fork can
+/// override it. The account state it was applied to is only referenced by its digest and has to
+/// be provided by whoever applies the fork.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+pub struct RecentOperation {
+    /// The CID of the operation, see [`Operation::cid`].
+    pub cid: String,
+    /// The CID of the operation before it, i.e. the head of the account it was applied to.
+    pub prev: Option<String>,
+    /// The authority index of the key that signed the operation in the rotation keys it was
+    /// applied to, `None` if it was not one of them (e.g. for account creations).
+    pub signer: Option<usize>,
+    /// The [`Account::state_digest`] of the account the operation was applied to.
+    pub previous: Digest,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
@@ -67,12 +106,274 @@ pub struct Service {
 }
 
 impl Service {
-    pub fn new_pds(endpoint: String) -> Self {
+    /// Creates a service of an arbitrary type. The endpoint is taken as is, as it is part of
+    /// signed operations; see [`ensure_http_prefix`] to normalize it beforehand.
+    pub fn new(service_type: impl Into<String>, endpoint: String) -> Self {
         Self {
-            service_type: "AtprotoPersonalDataServer".to_string(),
+            service_type: service_type.into(),
             endpoint,
         }
     }
+
+    /// Creates an atproto PDS service.
+    pub fn new_pds(endpoint: String) -> Self {
+        Self::new(ATPROTO_PDS_SERVICE_TYPE, endpoint)
+    }
+
+    /// Creates an atproto labeler service.
+    pub fn new_labeler(endpoint: String) -> Self {
+        Self::new("AtprotoLabeler", endpoint)
+    }
+
+    /// Checks that the type is non-empty and the endpoint is an absolute http(s) URL.
+    pub fn validate(&self) -> Result<(), OperationError> {
+        if self.service_type.trim().is_empty() {
+            return Err(OperationError::EmptyServiceType);
+        }
+        let invalid = || OperationError::InvalidServiceEndpoint(self.endpoint.clone());
+        let url = Url::parse(&self.endpoint).map_err(|_| invalid())?;
+        if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+            return Err(invalid());
+        }
+        Ok(())
+    }
+}
+
+/// The rotation keys of an account, ordered by authority: a key with a lower index may override
+/// operations signed by keys with a higher index. Keys are unique.
+///
+/// Deserialization does not check uniqueness, as accounts created before it was enforced may
+/// hold duplicate keys.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default, ToSchema)]
+#[serde(transparent)]
+pub struct RotationKeySet(Vec<VerifyingKey>);
+
+impl RotationKeySet {
+    pub fn as_slice(&self) -> &[VerifyingKey] {
+        &self.0
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, VerifyingKey> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, key: &VerifyingKey) -> bool {
+        self.0.contains(key)
+    }
+
+    /// Returns the authority index of `key`, lower indices having higher authority.
+    pub fn authority_index(&self, key: &VerifyingKey) -> Option<usize> {
+        self.0.iter().position(|k| k == key)
+    }
+
+    /// Appends `key` with the lowest authority, failing if it is already in the set.
+    pub fn push(&mut self, key: VerifyingKey) -> Result<(), OperationError> {
+        if self.contains(&key) {
+            return Err(OperationError::DuplicateRotationKey(
+                self.len(),
+                key.to_string(),
+            ));
+        }
+        self.0.push(key);
+        Ok(())
+    }
+
+    /// Removes `key`, keeping the order of the remaining keys. Returns whether it was present.
+    pub fn remove(&mut self, key: &VerifyingKey) -> bool {
+        let len = self.len();
+        self.0.retain(|k| k != key);
+        self.len() != len
+    }
+}
+
+impl TryFrom<Vec<VerifyingKey>> for RotationKeySet {
+    type Error = OperationError;
+
+    fn try_from(keys: Vec<VerifyingKey>) -> Result<Self, Self::Error> {
+        if let Some((index, key)) =
+            keys.iter().enumerate().find(|(index, key)| keys[..*index].contains(key))
+        {
+            return Err(OperationError::DuplicateRotationKey(index, key.to_string()));
+        }
+        Ok(Self(keys))
+    }
+}
+
+impl From<RotationKeySet> for Vec<VerifyingKey> {
+    fn from(keys: RotationKeySet) -> Self {
+        keys.0
+    }
+}
+
+impl<'a> IntoIterator for &'a RotationKeySet {
+    type Item = &'a VerifyingKey;
+    type IntoIter = std::slice::Iter<'a, VerifyingKey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Prefixes endpoints without an `http://` or `https://` scheme with `https://`, like
+/// `ensureHttpPrefix` of the atproto reference implementation.
+pub fn ensure_http_prefix(endpoint: String) -> String {
+    if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+        endpoint
+    } else {
+        format!("https://{}", endpoint)
+    }
+}
+
+/// Id of the verification method holding the atproto signing key.
+pub const ATPROTO_VERIFICATION_METHOD: &str = "atproto";
+
+/// Id of the service holding the atproto PDS.
+pub const ATPROTO_PDS_SERVICE: &str = "atproto_pds";
+
+/// Type PLC requires the [`ATPROTO_PDS_SERVICE`] to have.
+pub const ATPROTO_PDS_SERVICE_TYPE: &str = "AtprotoPersonalDataServer";
+
+/// Maximum number of operations a fork signed by a higher-authority rotation key may override.
+/// plc.directory bounds this by a 72 hour window, but operation logs carry no timestamps, so the
+/// window is measured in operations instead.
+pub const DEFAULT_PLC_RECOVERY_WINDOW: u64 = 10;
+
+/// A PLC operation log reduced by [`Account::compact_log`], along with the state it results in.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CompactedAccount {
+    /// The genesis operation, which the DID is derived from
+    pub genesis: SignedPLCOp,
+    /// The latest operations of the active chain after the genesis operation, ending with the
+    /// head. These are the operations a recovery may still fork from.
+    pub recent: Vec<SignedPLCOp>,
+    /// The account state after the head
+    pub account: Account,
+}
+
+impl CompactedAccount {
+    /// Derives the DID from the retained genesis operation, which has to match the account's id.
+    pub fn did(&self) -> String {
+        self.genesis.derive_did()
+    }
+
+    /// Returns the latest operation.
+    pub fn head(&self) -> &SignedPLCOp {
+        self.recent.last().unwrap_or(&self.genesis)
+    }
+}
+
+fn parse_did_key(did: &str) -> Result<VerifyingKey, DidError> {
+    VerifyingKey::from_did(did).map_err(|e| DidError::InvalidKey(did.to_string(), e.to_string()))
+}
+
+/// Returns the index of the key in `keys` that made the operation's signature over its unsigned
+/// DAG-CBOR, if any.
+fn signer_index(op: &SignedPLCOp, keys: &[VerifyingKey]) -> Result<Option<usize>, DidError> {
+    let payload =
+        op.unsigned.encode_to_bytes().map_err(|e| DidError::EncodingFailed(e.to_string()))?;
+    let Ok(sig_bytes) = Vec::<u8>::from_base64(&op.sig) else {
+        return Ok(None);
+    };
+
+    Ok(keys.iter().position(|key| {
+        Signature::from_algorithm_and_bytes(key.algorithm(), &sig_bytes)
+            .is_ok_and(|sig| key.verify_signature(&payload, &sig).is_ok())
+    }))
+}
+
+/// An operation of a PLC log that is part of the active chain: its CID, the index of the rotation
+/// key that signed it, and the account state after it.
+type AppliedPlcOp<'a> = (String, usize, Account, &'a SignedPLCOp);
+
+/// Verifies a PLC operation log, see [`Account::from_plc_log_with_recovery_window`], and returns
+/// its active chain, from genesis to the latest operation. Nullified operations are not part of it.
+fn replay_plc_log(
+    ops: &[SignedPLCOp],
+    recovery_window: u64,
+) -> Result<Vec<AppliedPlcOp<'_>>, DidError> {
+    let genesis = ops.first().ok_or(DidError::EmptyLog)?;
+    let did = genesis.derive_did();
+    let mut applied: Vec<AppliedPlcOp> = Vec::new();
+
+    for (index, op) in ops.iter().enumerate() {
+        if op.unsigned.type_ != PLC_OPERATION_TYPE {
+            return Err(DidError::UnsupportedOperation(op.unsigned.type_.clone()));
+        }
+        op.unsigned.validate().map_err(|e| DidError::MalformedOperation(index, e.to_string()))?;
+
+        let latest_cid = applied.last().map(|(cid, ..)| cid.clone());
+        let broken_chain = || DidError::BrokenChain {
+            index,
+            expected: latest_cid.clone(),
+            actual: op.unsigned.prev.clone(),
+        };
+        // number of applied operations that are kept by this operation
+        let kept = match &op.unsigned.prev {
+            prev if *prev == latest_cid => applied.len(),
+            Some(prev) => {
+                applied.iter().position(|(cid, ..)| cid == prev).ok_or_else(broken_chain)? + 1
+            }
+            None => return Err(broken_chain()),
+        };
+
+        let rotation_keys = op
+            .unsigned
+            .rotation_keys
+            .iter()
+            .map(|k| parse_did_key(k))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rotation_keys = RotationKeySet::try_from(rotation_keys).map_err(|e| {
+            DidError::InvalidKey(op.unsigned.rotation_keys.join(", "), e.to_string())
+        })?;
+        let signers = match kept {
+            0 => &rotation_keys,
+            _ => &applied[kept - 1].2.rotation_keys,
+        };
+        let signer =
+            signer_index(op, signers.as_slice())?.ok_or(DidError::InvalidSignature(index))?;
+
+        if kept < applied.len() {
+            let (_, overridden_signer, ..) = &applied[kept];
+            if signer >= *overridden_signer {
+                return Err(DidError::InsufficientAuthority(index));
+            }
+            if (applied.len() - kept) as u64 > recovery_window {
+                return Err(DidError::RecoveryWindowExpired(index));
+            }
+            applied.truncate(kept);
+        }
+
+        let mut account = match applied.last() {
+            Some((_, _, account, _)) => account.clone(),
+            None => Account {
+                did: did.clone(),
+                ..Default::default()
+            },
+        };
+        account.verification_methods = op
+            .unsigned
+            .verification_methods
+            .iter()
+            .map(|(id, key)| Ok((id.clone(), parse_did_key(key)?)))
+            .collect::<Result<_, DidError>>()?;
+        account.rotation_keys = rotation_keys;
+        account.also_known_as = op.unsigned.also_known_as.clone();
+        account.services = op.unsigned.services.clone();
+        account.nonce += 1;
+        let cid = op.cid()?;
+        account.head_cid = Some(cid.clone());
+        applied.push((cid, signer, account, op));
+    }
+
+    Ok(applied)
 }
 
 impl Account {
@@ -84,8 +385,164 @@ impl Account {
         self.nonce
     }
 
+    /// Returns the nonce the next transaction for this account has to carry. Every processed
+    /// transaction advances it by one, starting at 0 for the genesis transaction.
+    pub fn nonce_for_next_transaction(&self) -> u64 {
+        self.nonce
+    }
+
     pub fn valid_keys(&self) -> &[VerifyingKey] {
-        &self.rotation_keys
+        self.rotation_keys.as_slice()
+    }
+
+    /// Returns the [`Account::valid_keys`] using the given algorithm, for verifiers supporting
+    /// only some curves.
+    pub fn valid_keys_with_algorithm(&self, algorithm: CryptoAlgorithm) -> Vec<&VerifyingKey> {
+        self.rotation_keys.iter().filter(|key| key.algorithm() == algorithm).collect()
+    }
+
+    /// Returns the challenge key of a service account, see [`Operation::RegisterService`].
+    pub fn challenge_key(&self) -> Option<&VerifyingKey> {
+        self.challenge_key.as_ref()
+    }
+
+    /// Verifies that an [`Operation::CreateAccount`] for this service meets its challenge, i.e.
+    /// is signed by the service's challenge key. Whether the challenge expired depends on the
+    /// clock of the sequencer and is checked there, see [`Operation::verify_challenge_expiry`].
+    pub fn verify_service_challenge(&self, operation: &Operation) -> Result<(), AccountError> {
+        let Operation::CreateAccount {
+            id,
+            service_id,
+            challenge,
+            challenge_expires_at,
+            key,
+        } = operation
+        else {
+            return Err(TransactionError::InvalidOp(
+                "Only account creation meets a service challenge".to_string(),
+            )
+            .into());
+        };
+
+        if service_id != &self.did {
+            return Err(AccountError::AccountIdError(
+                service_id.to_string(),
+                self.did.to_string(),
+            ));
+        }
+
+        let Some(challenge_key) = &self.challenge_key else {
+            return Err(AccountError::NotAService(self.did.to_string()));
+        };
+
+        challenge_key
+            .verify_signature(
+                service_challenge_hash(id, service_id, key, *challenge_expires_at),
+                challenge,
+            )
+            .map_err(|e| AccountError::InvalidChallenge(e.to_string()))
+    }
+
+    /// Rebuilds the unsigned genesis operation of an account that has not been modified since its
+    /// creation. Signed with the genesis signature, it derives the account's DID, which lets a
+    /// verifier check that the DID was derived correctly (see [`SignedPLCOp::derive_did`]).
+    pub fn to_genesis_plc_op(&self) -> Result<UnsignedPLCOp, DidError> {
+        if self.nonce != 1 {
+            return Err(DidError::NotGenesis(self.did.clone()));
+        }
+
+        let to_did = |key: &VerifyingKey| {
+            key.to_did()
+                .map_err(|e| DidError::InvalidKey(key.algorithm().to_string(), e.to_string()))
+        };
+        Ok(UnsignedPLCOp {
+            type_: PLC_OPERATION_TYPE.to_string(),
+            rotation_keys: self.rotation_keys.iter().map(to_did).collect::<Result<_, _>>()?,
+            verification_methods: self
+                .verification_methods
+                .iter()
+                .map(|(id, key)| Ok((id.clone(), to_did(key)?)))
+                .collect::<Result<_, DidError>>()?,
+            also_known_as: self.also_known_as.clone(),
+            services: self.services.clone(),
+            prev: None,
+        })
+    }
+
+    /// Checks that the account's DID is the one derived from `genesis_op`, so that a DID document
+    /// rendered from the account is bound to the operation that created it.
+    pub fn verify_did_binding(&self, genesis_op: &SignedPLCOp) -> Result<(), DidError> {
+        self.verify_did_binding_with(genesis_op, &DidConfig::DEFAULT)
+    }
+
+    /// Like [`Self::verify_did_binding`], for DIDs of the method configured in `config`.
+    pub fn verify_did_binding_with(
+        &self,
+        genesis_op: &SignedPLCOp,
+        config: &DidConfig,
+    ) -> Result<(), DidError> {
+        let derived = genesis_op.derive_did_with(config);
+        if self.did != derived {
+            return Err(DidError::UnboundDid {
+                did: self.did.clone(),
+                derived,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reconciles two observations of the same account, e.g. replayed from blobs and fetched from
+    /// an API, and returns the more recent one. Fails if they belong to different DIDs or
+    /// diverge at the same nonce.
+    pub fn merge(&self, other: &Account) -> Result<Account, DidError> {
+        if self.did != other.did {
+            return Err(DidError::DidMismatch(self.did.clone(), other.did.clone()));
+        }
+        if self.nonce == other.nonce && self != other {
+            return Err(DidError::ConflictingState {
+                did: self.did.clone(),
+                nonce: self.nonce,
+            });
+        }
+
+        let newer = if other.nonce > self.nonce {
+            other
+        } else {
+            self
+        };
+        Ok(newer.clone())
+    }
+
+    /// Returns what changed from `previous` to this account, e.g. to notify subscribers.
+    pub fn diff(&self, previous: &Account) -> AccountDiff {
+        let mut removed_verification_methods: Vec<String> = previous
+            .verification_methods
+            .keys()
+            .filter(|id| !self.verification_methods.contains_key(*id))
+            .cloned()
+            .collect();
+        removed_verification_methods.sort();
+        let mut removed_services: Vec<String> = previous
+            .services
+            .keys()
+            .filter(|id| !self.services.contains_key(*id))
+            .cloned()
+            .collect();
+        removed_services.sort();
+
+        AccountDiff {
+            added_rotation_keys: added(previous.valid_keys(), self.valid_keys()),
+            removed_rotation_keys: added(self.valid_keys(), previous.valid_keys()),
+            changed_verification_methods: changed(
+                &previous.verification_methods,
+                &self.verification_methods,
+            ),
+            removed_verification_methods,
+            changed_services: changed(&previous.services, &self.services),
+            removed_services,
+            added_also_known_as: added(&previous.also_known_as, &self.also_known_as),
+            removed_also_known_as: added(&self.also_known_as, &previous.also_known_as),
+        }
     }
 
     /// Creates a new request builder with the default NoopPrismApi implementation.
@@ -119,23 +576,138 @@ impl Account {
         RequestBuilder::new_with_prism(prism).to_modify_account(self)
     }
 
-    /// Validates and processes an incoming [`Transaction`], updating the account state.
+    /// Rebuilds an account from a PLC operation log, using the
+    /// [`DEFAULT_PLC_RECOVERY_WINDOW`]. See [`Account::from_plc_log_with_recovery_window`].
+    pub fn from_plc_log(ops: &[SignedPLCOp]) -> Result<Account, DidError> {
+        Self::from_plc_log_with_recovery_window(ops, DEFAULT_PLC_RECOVERY_WINDOW)
+    }
+
+    /// Rebuilds an account from a PLC operation log, ordered from genesis to the latest
+    /// operation. Each operation has to reference a previous operation's CID in `prev` and be
+    /// signed by one of that operation's rotation keys; the genesis operation by one of its own.
+    ///
+    /// An operation referencing an already superseded operation is a fork. It overrides
+    /// (nullifies) all operations after its `prev`, if it is signed by a rotation key with a lower
+    /// index (higher authority) than the first overridden operation, and at most
+    /// `recovery_window` operations are overridden.
+    pub fn from_plc_log_with_recovery_window(
+        ops: &[SignedPLCOp],
+        recovery_window: u64,
+    ) -> Result<Account, DidError> {
+        let mut applied = replay_plc_log(ops, recovery_window)?;
+        let (_, _, account, _) = applied.pop().ok_or(DidError::EmptyLog)?;
+        Ok(account)
+    }
+
+    /// Compacts a PLC operation log, ordered from genesis to the latest operation, using the
+    /// [`DEFAULT_PLC_RECOVERY_WINDOW`]. See [`Account::compact_log_with_recovery_window`].
+    pub fn compact_log(history: &[SignedPLCOp]) -> Result<CompactedAccount, DidError> {
+        Self::compact_log_with_recovery_window(history, DEFAULT_PLC_RECOVERY_WINDOW)
+    }
+
+    /// Verifies a PLC operation log like [`Account::from_plc_log_with_recovery_window`], and
+    /// reduces it to the genesis operation and the latest operations that a recovery could still
+    /// fork from: the head and the `recovery_window` operations before it. Nullified and older
+    /// operations are dropped.
+    pub fn compact_log_with_recovery_window(
+        history: &[SignedPLCOp],
+        recovery_window: u64,
+    ) -> Result<CompactedAccount, DidError> {
+        let mut applied = replay_plc_log(history, recovery_window)?;
+        let genesis = history.first().ok_or(DidError::EmptyLog)?.clone();
+
+        // the genesis operation is kept separately
+        let kept = usize::try_from(recovery_window)
+            .unwrap_or(usize::MAX)
+            .saturating_add(1)
+            .min(applied.len() - 1);
+        let recent = applied[applied.len() - kept..].iter().map(|(.., op)| (*op).clone()).collect();
+        let (_, _, account, _) = applied.pop().ok_or(DidError::EmptyLog)?;
+
+        Ok(CompactedAccount {
+            genesis,
+            recent,
+            account,
+        })
+    }
+
+    /// Validates and processes an incoming [`Transaction`], updating the account state. Forks are
+    /// rejected, see [`Account::process_transaction_with_fork_base`].
     pub fn process_transaction(&mut self, tx: &Transaction) -> Result<()> {
-        self.validate_transaction(tx)?;
-        self.process_operation(&tx.operation)?;
-        self.nonce += 1;
+        self.process_transaction_with_fork_base(tx, None)
+    }
+
+    /// Validates and processes an incoming [`Transaction`] like [`Account::process_transaction`],
+    /// accepting forks.
+    ///
+    /// A transaction whose `prev` references one of the [`DEFAULT_PLC_RECOVERY_WINDOW`] operations
+    /// before the head is a fork: it overrides (nullifies) all operations after its `prev`, if it
+    /// is signed by a rotation key of that state with a lower index (higher authority) than the
+    /// signer of the first overridden operation. The account only keeps the digests of former
+    /// states, so `fork_base` has to be the account state after `prev`; it is ignored if the
+    /// transaction is no fork, see [`Account::fork_prev`]. The nonce keeps counting all
+    /// transactions.
+    pub fn process_transaction_with_fork_base(
+        &mut self,
+        tx: &Transaction,
+        fork_base: Option<&Account>,
+    ) -> Result<()> {
+        let fork = self.validate_transaction(tx, fork_base)?;
+        let nonce = self
+            .nonce
+            .checked_add(1)
+            .ok_or_else(|| AccountError::NonceOverflow(self.did.clone()))?;
+        match fork {
+            Some(base) => {
+                // applied to a copy, so that a failure leaves the account untouched
+                let mut account = base.clone();
+                account.process_operation(&tx.operation, &tx.vk)?;
+                account.nonce = nonce;
+                *self = account;
+            }
+            None => {
+                self.process_operation(&tx.operation, &tx.vk)?;
+                self.nonce = nonce;
+            }
+        }
         Ok(())
     }
 
-    /// Validates a transaction against the current account state. Please note
-    /// that the operation must be validated separately.
-    fn validate_transaction(&self, tx: &Transaction) -> Result<(), AccountError> {
+    /// Returns the `prev` of `tx` if it is a fork, i.e. does not extend the head of the account.
+    pub fn fork_prev<'a>(&self, tx: &'a Transaction) -> Option<&'a str> {
+        tx.prev.as_deref().filter(|prev| self.head_cid() != Some(*prev))
+    }
+
+    /// Returns the digest of the account state as committed, which [`RecentOperation::previous`]
+    /// references.
+    pub fn state_digest(&self) -> Result<Digest> {
+        Ok(Digest::hash(self.encode_to_bytes()?))
+    }
+
+    /// Validates a transaction against the current account state, returning the state it is
+    /// applied to if it is a fork. Please note that the operation must be validated separately.
+    fn validate_transaction<'a>(
+        &self,
+        tx: &Transaction,
+        fork_base: Option<&'a Account>,
+    ) -> Result<Option<&'a Account>, AccountError> {
         if tx.nonce != self.nonce {
             return Err(AccountError::NonceError(tx.nonce, self.nonce));
         }
+        let fork = match tx.prev {
+            ref prev if *prev == self.head_cid => None,
+            Some(_) => Some(self.verify_fork(tx, fork_base)?),
+            None => {
+                return Err(AccountError::PrevError(
+                    tx.prev.clone(),
+                    self.head_cid.clone(),
+                ));
+            }
+        };
 
         match &tx.operation {
-            Operation::CreateAccount { id, key, .. } => {
+            Operation::CreateAccount { id, key, .. }
+            | Operation::RegisterService { id, key, .. } => {
                 if !self.is_empty() {
                     return Err(AccountError::NonceError(tx.nonce, self.nonce));
                 }
@@ -146,6 +718,7 @@ impl Account {
                         id.to_string(),
                     ));
                 }
+                // security-sensitive: authorizes the signer, compares in constant time
                 if &tx.vk != key {
                     return Err(AccountError::AccountKeyError(
                         tx.vk.to_string(),
@@ -168,47 +741,261 @@ impl Account {
                 }
                 tx.verify_cbor_signature()?;
             }
-            _ => {
+            // rejected by `process_operation`
+            Operation::Unknown { .. } => {}
+            Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::SetRotationKeys { .. }
+            | Operation::DeactivateDID {} => {
+                if tx.id != self.did {
+                    return Err(AccountError::TransactionIdError(
+                        tx.id.to_string(),
+                        self.did.to_string(),
+                    ));
+                }
+                // only rotation keys may update an account, a fork's authority is checked
+                // against the state it is applied to
+                if fork.is_none() && !self.rotation_keys.contains(&tx.vk) {
+                    return Err(AccountError::InvalidKey);
+                }
                 tx.verify_signature()?;
             }
         }
 
+        Ok(fork)
+    }
+
+    /// Checks that `fork_base` is the state `tx.prev` references, which the first operation a fork
+    /// overrides was applied to, and that `tx` has the authority to override it.
+    fn verify_fork<'a>(
+        &self,
+        tx: &Transaction,
+        fork_base: Option<&'a Account>,
+    ) -> Result<&'a Account, AccountError> {
+        let overridden = self
+            .recent_operations
+            .iter()
+            .find(|op| op.prev == tx.prev)
+            .ok_or_else(|| AccountError::PrevError(tx.prev.clone(), self.head_cid.clone()))?;
+        let unknown_base = || AccountError::UnknownForkBase(tx.prev.clone().unwrap_or_default());
+        let base = fork_base.ok_or_else(unknown_base)?;
+        if base.state_digest().ok() != Some(overridden.previous) {
+            return Err(unknown_base());
+        }
+        match base.rotation_keys.authority_index(&tx.vk) {
+            Some(signer) if overridden.signer.is_none_or(|overridden| signer < overridden) => {
+                Ok(base)
+            }
+            _ => Err(AccountError::InsufficientAuthority(tx.vk.to_string())),
+        }
+    }
+
+    /// Processes an operation signed by `signer`, updating the account state. Should only be run
+    /// in the context of a transaction.
+    fn process_operation(&mut self, operation: &Operation, signer: &VerifyingKey) -> Result<()> {
+        operation.validate(self)?;
+        let cid = operation.cid()?;
+        let recent_operation = RecentOperation {
+            cid: cid.clone(),
+            prev: self.head_cid.clone(),
+            signer: self.rotation_keys.authority_index(signer),
+            previous: self.state_digest()?,
+        };
+        operation.apply(self)?;
+        self.head_cid = Some(cid);
+        self.recent_operations.push(recent_operation);
+        let window = usize::try_from(DEFAULT_PLC_RECOVERY_WINDOW).unwrap_or(usize::MAX);
+        let expired = self.recent_operations.len().saturating_sub(window);
+        self.recent_operations.drain(..expired);
         Ok(())
     }
 
-    /// Validates an operation against the current account state.
-    fn validate_operation(&self, operation: &Operation) -> Result<()> {
-        match operation {
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0
+    }
+
+    pub fn verification_methods(&self) -> &HashMap<String, VerifyingKey> {
+        &self.verification_methods
+    }
+
+    /// Returns the key of the verification method with the given id (without a `#` prefix).
+    pub fn verification_method(&self, id: &str) -> Option<&VerifyingKey> {
+        self.verification_methods.get(id)
+    }
+
+    /// Returns the atproto signing key, i.e. the [`ATPROTO_VERIFICATION_METHOD`].
+    pub fn atproto_key(&self) -> Option<&VerifyingKey> {
+        self.verification_method(ATPROTO_VERIFICATION_METHOD)
+    }
+
+    pub fn also_known_as(&self) -> &[String] {
+        &self.also_known_as
+    }
+
+    /// Returns the handles listed in `alsoKnownAs` without their `at://` prefix, in order.
+    /// Entries that are not `at://` URIs are skipped.
+    pub fn handles(&self) -> impl Iterator<Item = &str> {
+        self.also_known_as.iter().filter_map(|alias| alias.strip_prefix("at://"))
+    }
+
+    /// Returns the account's primary handle, which atproto takes to be the first
+    /// [`Self::handles`] entry.
+    pub fn primary_handle(&self) -> Option<&str> {
+        self.handles().next()
+    }
+
+    pub fn services(&self) -> &HashMap<String, Service> {
+        &self.services
+    }
+
+    pub fn head_cid(&self) -> Option<&str> {
+        self.head_cid.as_deref()
+    }
+
+    /// Returns whether the DID has been deactivated. Deactivated accounts keep their last state,
+    /// which is still resolvable.
+    pub const fn is_deactivated(&self) -> bool {
+        self.deactivated
+    }
+
+    /// Renders the account's DID document, typing its verification methods as given. The
+    /// [`From`] conversion renders all of them as `Multikey`. Keys without a `did:key`
+    /// representation are left out.
+    pub fn to_did_document(&self, method_type: VerificationMethodType) -> DidDocument {
+        let mut context = vec!["https://www.w3.org/ns/did/v1".to_string()];
+        if method_type == VerificationMethodType::Multikey {
+            context.push("https://w3id.org/security/multikey/v1".to_string());
+        }
+
+        let verification_methods: Vec<VerificationMethod> = self
+            .verification_methods
+            .iter()
+            .filter_map(|(key_id, verifying_key)| {
+                let did_key = verifying_key.to_did().ok()?;
+                let public_key_multibase =
+                    did_key.strip_prefix("did:key:").unwrap_or(&did_key).to_string();
+
+                let key_context = method_type.context(verifying_key.algorithm());
+                if !context.iter().any(|c| c == key_context) {
+                    context.push(key_context.to_string());
+                }
+
+                Some(VerificationMethod {
+                    id: format!("{}#{}", self.did, key_id),
+                    method_type: method_type.method_type(verifying_key.algorithm()).to_string(),
+                    controller: self.did.clone(),
+                    public_key_multibase,
+                })
+            })
+            .collect();
+
+        let services: Vec<DidService> = self
+            .services
+            .iter()
+            .map(|(service_id, service)| DidService {
+                id: format!("#{}", service_id),
+                service_type: service.service_type.clone(),
+                service_endpoint: service.endpoint.clone(),
+            })
+            .collect();
+
+        DidDocument {
+            context,
+            id: self.did.clone(),
+            also_known_as: self.also_known_as.clone(),
+            verification_method: verification_methods,
+            service: services,
+            deactivated: self.deactivated,
+        }
+    }
+
+    /// Returns the current operation fields in the shape of plc.directory's `/{did}/data`
+    /// endpoint, with keys encoded as `did:key`s. Keys without a `did:key` representation are
+    /// left out.
+    pub fn to_plc_data(&self) -> serde_json::Value {
+        let verification_methods: HashMap<&str, String> = self
+            .verification_methods
+            .iter()
+            .filter_map(|(id, key)| Some((id.as_str(), key.to_did().ok()?)))
+            .collect();
+        let rotation_keys: Vec<String> =
+            self.rotation_keys.iter().filter_map(|k| k.to_did().ok()).collect();
+
+        serde_json::json!({
+            "did": self.did,
+            "verificationMethods": verification_methods,
+            "rotationKeys": rotation_keys,
+            "alsoKnownAs": self.also_known_as,
+            "services": self.services,
+        })
+    }
+}
+
+/// A state transition of an [`Account`]. [`Account::process_transaction`] validates an operation
+/// against the account state before applying it, after checking nonce, `prev` and signature of
+/// the surrounding transaction.
+pub trait OperationHandler {
+    /// Checks that the operation can be applied to `account`, without modifying it.
+    fn validate(&self, account: &Account) -> Result<()>;
+
+    /// Applies the operation to `account`. Only called once [`OperationHandler::validate`]
+    /// passed.
+    fn apply(&self, account: &mut Account) -> Result<()>;
+}
+
+impl OperationHandler for Operation {
+    fn validate(&self, account: &Account) -> Result<()> {
+        if account.deactivated {
+            return Err(AccountError::Deactivated(account.did.clone()).into());
+        }
+        match self {
             Operation::AddKey { key } => {
-                if self.rotation_keys.contains(key) {
+                if account.rotation_keys.contains(key) {
                     return Err(anyhow!("Key already exists"));
                 }
             }
             Operation::RevokeKey { key } => {
-                if !self.rotation_keys.contains(key) {
+                if !account.rotation_keys.contains(key) {
                     return Err(anyhow!("Key does not exist"));
                 }
             }
-            Operation::CreateDID { .. } | Operation::CreateAccount { .. } => {
-                if !self.is_empty() {
+            Operation::SetRotationKeys { .. } => self.validate_basic()?,
+            Operation::DeactivateDID {} => {
+                if account.is_empty() {
+                    return Err(anyhow!("Account does not exist"));
+                }
+            }
+            Operation::CreateDID { rotation_keys, .. } => {
+                if !account.is_empty() {
                     return Err(anyhow!("Account already exists"));
                 }
+                RotationKeySet::try_from(rotation_keys.clone())?;
+            }
+            Operation::CreateAccount { .. } | Operation::RegisterService { .. } => {
+                if !account.is_empty() {
+                    return Err(anyhow!("Account already exists"));
+                }
+            }
+            Operation::Unknown { name } => {
+                return Err(OperationError::UnknownOperation(name.clone()).into());
             }
         }
         Ok(())
     }
 
-    /// Processes an operation, updating the account state. Should only be run
-    /// in the context of a transaction.
-    fn process_operation(&mut self, operation: &Operation) -> Result<()> {
-        self.validate_operation(operation)?;
-
-        match operation {
+    fn apply(&self, account: &mut Account) -> Result<()> {
+        match self {
             Operation::AddKey { key } => {
-                self.rotation_keys.push(key.clone());
+                account.rotation_keys.push(key.clone())?;
             }
             Operation::RevokeKey { key } => {
-                self.rotation_keys.retain(|k| k != key);
+                account.rotation_keys.remove(key);
+            }
+            Operation::SetRotationKeys { keys } => {
+                account.rotation_keys = keys.clone().try_into()?;
+            }
+            Operation::DeactivateDID {} => {
+                account.deactivated = true;
             }
             Operation::CreateDID {
                 did,
@@ -218,85 +1005,125 @@ impl Account {
                 atproto_pds,
                 ..
             } => {
-                self.did = did.clone();
-                self.also_known_as = also_known_as.clone();
-                self.rotation_keys = rotation_keys.clone();
-                self.verification_methods = verification_methods.clone();
-                self.services.insert(
+                // converted first, so that a failure leaves the account untouched
+                let rotation_keys = rotation_keys.clone().try_into()?;
+                account.did = did.clone();
+                account.also_known_as = also_known_as.clone();
+                account.rotation_keys = rotation_keys;
+                account.verification_methods = verification_methods.clone();
+                account.services.insert(
                     "atproto_pds".to_string(),
                     Service::new_pds(atproto_pds.clone()),
                 );
             }
             Operation::CreateAccount { id, key, .. } => {
-                self.did = id.clone();
-                self.rotation_keys.push(key.clone());
+                account.did = id.clone();
+                account.rotation_keys.push(key.clone())?;
+            }
+            Operation::RegisterService {
+                id,
+                key,
+                challenge_key,
+            } => {
+                account.did = id.clone();
+                account.rotation_keys.push(key.clone())?;
+                account.challenge_key = Some(challenge_key.clone());
+            }
+            Operation::Unknown { name } => {
+                return Err(OperationError::UnknownOperation(name.clone()).into());
             }
         }
-
         Ok(())
     }
+}
 
-    pub fn is_empty(&self) -> bool {
-        self.nonce == 0
-    }
+/// Changes between two states of an account, see [`Account::diff`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    pub added_rotation_keys: Vec<VerifyingKey>,
+    pub removed_rotation_keys: Vec<VerifyingKey>,
+    /// Verification methods that were added or now hold a different key, by id.
+    pub changed_verification_methods: HashMap<String, VerifyingKey>,
+    pub removed_verification_methods: Vec<String>,
+    /// Services that were added or now have a different type or endpoint, by id.
+    pub changed_services: HashMap<String, Service>,
+    pub removed_services: Vec<String>,
+    /// Handles (`alsoKnownAs` entries) that were added.
+    pub added_also_known_as: Vec<String>,
+    pub removed_also_known_as: Vec<String>,
+}
 
-    pub fn verification_methods(&self) -> &HashMap<String, VerifyingKey> {
-        &self.verification_methods
+impl AccountDiff {
+    /// Returns whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
     }
+}
 
-    pub fn also_known_as(&self) -> &[String] {
-        &self.also_known_as
-    }
+/// Returns the items of `current` that are not in `previous`, in their order in `current`.
+fn added<T: Clone + PartialEq>(previous: &[T], current: &[T]) -> Vec<T> {
+    current.iter().filter(|item| !previous.contains(item)).cloned().collect()
+}
 
-    pub fn services(&self) -> &HashMap<String, Service> {
-        &self.services
-    }
+/// Returns the entries of `current` that are missing or different in `previous`.
+fn changed<T: Clone + PartialEq>(
+    previous: &HashMap<String, T>,
+    current: &HashMap<String, T>,
+) -> HashMap<String, T> {
+    current
+        .iter()
+        .filter(|(id, value)| previous.get(*id) != Some(*value))
+        .map(|(id, value)| (id.clone(), value.clone()))
+        .collect()
 }
 
 impl From<&Account> for DidDocument {
     fn from(account: &Account) -> Self {
-        let context = vec![
-            "https://www.w3.org/ns/did/v1".to_string(),
-            "https://w3id.org/security/multikey/v1".to_string(),
-        ];
+        account.to_did_document(VerificationMethodType::Multikey)
+    }
+}
 
-        let verification_methods: Vec<VerificationMethod> = account
-            .verification_methods
+/// Rebuilds the parts of an account that are published in its DID document.
+///
+/// Rotation keys and the head CID are not part of a DID document, so the resulting account has
+/// none, and starts at nonce 0.
+impl TryFrom<&DidDocument> for Account {
+    type Error = DidError;
+
+    fn try_from(document: &DidDocument) -> Result<Self, Self::Error> {
+        let verification_methods = document
+            .verification_method
             .iter()
-            .map(|(key_id, verifying_key)| {
-                let public_key_multibase = match verifying_key.to_did() {
-                    Ok(did_key) => did_key.strip_prefix("did:key:").unwrap_or(&did_key).to_string(),
-                    Err(_) => {
-                        // Fallback to raw key representation if DID conversion fails
-                        format!("z{}", bs58::encode(verifying_key.to_bytes()).into_string())
-                    }
-                };
-
-                VerificationMethod {
-                    id: format!("{}#{}", account.did, key_id),
-                    method_type: "Multikey".to_string(),
-                    controller: account.did.clone(),
-                    public_key_multibase,
-                }
+            .map(|method| {
+                let key_id = method.id.rsplit_once('#').map_or(method.id.as_str(), |(_, id)| id);
+                let key = parse_did_key(&format!("did:key:{}", method.public_key_multibase))?;
+                Ok((key_id.to_string(), key))
             })
-            .collect();
+            .collect::<Result<HashMap<_, _>, DidError>>()?;
 
-        let services: Vec<DidService> = account
-            .services
+        let services = document
+            .service
             .iter()
-            .map(|(service_id, service)| DidService {
-                id: format!("#{}", service_id),
-                service_type: service.service_type.clone(),
-                service_endpoint: service.endpoint.clone(),
+            .map(|service| {
+                let service_id = service.id.strip_prefix('#').unwrap_or(&service.id);
+                (
+                    service_id.to_string(),
+                    Service {
+                        service_type: service.service_type.clone(),
+                        endpoint: service.service_endpoint.clone(),
+                    },
+                )
             })
             .collect();
 
-        DidDocument {
-            context,
-            id: account.did.clone(),
-            also_known_as: account.also_known_as.clone(),
-            verification_method: verification_methods,
-            service: services,
-        }
+        Ok(Account {
+            did: document.id.clone(),
+            verification_methods,
+            also_known_as: document.also_known_as.clone(),
+            services,
+            deactivated: document.deactivated,
+            ..Default::default()
+        })
     }
 }
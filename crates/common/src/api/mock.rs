@@ -1,27 +1,62 @@
 use async_trait::async_trait;
 use mockall::mock;
-use std::time::Duration;
+use prism_keys::VerifyingKey;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use crate::{account::Account, api::PendingTransaction, transaction::Transaction};
+use crate::{
+    account::Account,
+    api::PendingTransaction,
+    operation::{Operation, SignedPLCOp},
+    transaction::Transaction,
+};
 
 use super::{
     PrismApi, PrismApiError, PrismApiTimer,
     types::{AccountResponse, CommitmentResponse},
 };
 
-pub struct MockPrismTimer;
+/// Timer that returns immediately, recording the requested durations. Clones share the
+/// recorded durations, so a clone can be handed to [`PendingTransactionImpl::with_timer`].
+///
+/// [`PendingTransactionImpl::with_timer`]: super::PendingTransactionImpl::with_timer
+#[derive(Clone, Default)]
+pub struct MockPrismTimer {
+    sleeps: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl MockPrismTimer {
+    /// Returns the durations slept so far.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+}
 
 impl PrismApiTimer for MockPrismTimer {
-    async fn sleep(_: Duration) {}
+    async fn sleep(&self, duration: Duration) {
+        self.sleeps.lock().unwrap().push(duration);
+    }
 }
 
 pub struct MockPrismPendingTransaction {
     result: Result<Account, PrismApiError>,
+    commitment: CommitmentResponse,
 }
 
 impl MockPrismPendingTransaction {
     pub fn with_result(result: Result<Account, PrismApiError>) -> Self {
-        Self { result }
+        Self {
+            result,
+            commitment: CommitmentResponse::default(),
+        }
+    }
+
+    /// Sets the commitment returned by [`PendingTransaction::wait_for_commitment`].
+    pub fn with_commitment(mut self, commitment: CommitmentResponse) -> Self {
+        self.commitment = commitment;
+        self
     }
 }
 
@@ -32,13 +67,22 @@ impl PendingTransaction<'_> for MockPrismPendingTransaction {
     async fn wait_with_interval(&self, _: Duration) -> Result<Account, PrismApiError> {
         self.result.clone()
     }
+
+    async fn wait_for_commitment_with_interval(
+        &self,
+        _: Duration,
+    ) -> Result<(Account, CommitmentResponse), PrismApiError> {
+        Ok((self.result.clone()?, self.commitment.clone()))
+    }
 }
 
 mock! {
     pub PrismApi {
         pub async fn get_account(&self, id: &str) -> Result<AccountResponse, PrismApiError>;
         pub async fn get_commitment(&self) -> Result<CommitmentResponse, PrismApiError>;
-        pub async fn post_transaction(&self, transaction: Transaction) -> Result<MockPrismPendingTransaction, PrismApiError>;
+        pub async fn post_transaction(&self, transaction: Transaction, idempotency_key: Option<String>) -> Result<MockPrismPendingTransaction, PrismApiError>;
+        pub async fn get_genesis_operation(&self, did: &str) -> Result<SignedPLCOp, PrismApiError>;
+        pub async fn resolve_handle(&self, handle: &str) -> Result<String, PrismApiError>;
     }
 }
 
@@ -57,7 +101,321 @@ impl PrismApi for MockPrismApi {
     async fn post_transaction(
         &self,
         transaction: Transaction,
+        idempotency_key: Option<String>,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
-        MockPrismApi::post_transaction(self, transaction).await
+        MockPrismApi::post_transaction(self, transaction, idempotency_key).await
+    }
+
+    async fn get_genesis_operation(&self, did: &str) -> Result<SignedPLCOp, PrismApiError> {
+        MockPrismApi::get_genesis_operation(self, did).await
+    }
+
+    async fn resolve_handle(&self, handle: &str) -> Result<String, PrismApiError> {
+        MockPrismApi::resolve_handle(self, handle).await
+    }
+}
+
+/// Transactions recorded by [`MockPrismApi::capture_transactions`], in the order they were posted.
+#[derive(Clone, Default)]
+pub struct CapturedTransactions(Arc<Mutex<Vec<Transaction>>>);
+
+impl CapturedTransactions {
+    pub fn all(&self) -> Vec<Transaction> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn last(&self) -> Option<Transaction> {
+        self.0.lock().unwrap().last().cloned()
+    }
+
+    /// Returns whether any captured transaction satisfies `matcher`, see [`matchers`].
+    pub fn any(&self, matcher: impl Fn(&Transaction) -> bool) -> bool {
+        self.0.lock().unwrap().iter().any(matcher)
+    }
+}
+
+impl MockPrismApi {
+    /// Accepts any number of posted transactions and records them, so that tests can assert the
+    /// operations a builder produced. Pending transactions resolve to `result`.
+    pub fn capture_transactions(
+        &mut self,
+        result: Result<Account, PrismApiError>,
+    ) -> CapturedTransactions {
+        let captured = CapturedTransactions::default();
+        let transactions = captured.0.clone();
+        self.expect_post_transaction().returning(move |transaction, _| {
+            transactions.lock().unwrap().push(transaction);
+            Ok(MockPrismPendingTransaction::with_result(result.clone()))
+        });
+        captured
+    }
+}
+
+/// Predicates on posted transactions, for [`CapturedTransactions::any`] or an expectation's
+/// `withf`.
+pub mod matchers {
+    use super::*;
+
+    /// Matches transactions whose operation has the given [`Operation::operation_type`].
+    pub fn operation_type(operation_type: &'static str) -> impl Fn(&Transaction) -> bool {
+        move |tx| tx.operation.operation_type() == operation_type
+    }
+
+    /// Matches DID creations with exactly the given rotation keys, in order.
+    pub fn create_did_with_rotation_keys(keys: Vec<VerifyingKey>) -> impl Fn(&Transaction) -> bool {
+        move |tx| matches!(&tx.operation, Operation::CreateDID { rotation_keys, .. } if *rotation_keys == keys)
+    }
+
+    /// Matches transactions adding the given key.
+    pub fn add_key(key: VerifyingKey) -> impl Fn(&Transaction) -> bool {
+        move |tx| matches!(&tx.operation, Operation::AddKey { key: added } if *added == key)
+    }
+
+    /// Matches transactions revoking the given key.
+    pub fn revoke_key(key: VerifyingKey) -> impl Fn(&Transaction) -> bool {
+        move |tx| matches!(&tx.operation, Operation::RevokeKey { key: revoked } if *revoked == key)
+    }
+
+    /// Matches transactions signed by the given key.
+    pub fn signed_by(key: VerifyingKey) -> impl Fn(&Transaction) -> bool {
+        move |tx| tx.vk == key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prism_keys::SigningKey;
+    use prism_serde::binary::ToBinary;
+    use std::collections::HashMap;
+
+    use super::{matchers::*, *};
+    use crate::{
+        api::{
+            PendingTransaction, PendingTransactionImpl, PrismApi,
+            types::{AccountLookup, DidDocument, HashedMerkleProof},
+        },
+        digest::Digest,
+        test_utils::create_did_tx,
+    };
+
+    #[tokio::test]
+    async fn test_capture_create_did_operation() {
+        let signing_key = SigningKey::new_secp256k1();
+        let rotation_keys = vec![signing_key.verifying_key()];
+
+        let mut prism = MockPrismApi::new();
+        let captured = prism.capture_transactions(Ok(Account::default()));
+
+        prism
+            .create_atproto_did(
+                SigningKey::new_secp256k1().verifying_key(),
+                rotation_keys.clone(),
+                "at://alice.example.com".to_string(),
+                "http://localhost:2583".to_string(),
+                &signing_key,
+            )
+            .await
+            .unwrap()
+            .wait()
+            .await
+            .unwrap();
+
+        assert_eq!(captured.all().len(), 1);
+        assert!(captured.any(create_did_with_rotation_keys(rotation_keys)));
+        assert!(captured.any(signed_by(signing_key.verifying_key())));
+        assert!(!captured.any(operation_type("add_key")));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_commitment_returns_commitment_of_account() {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let mut account = Account::default();
+        account.process_transaction(&transaction).unwrap();
+
+        let before = CommitmentResponse {
+            commitment: Digest::hash(b"before"),
+            ..Default::default()
+        };
+        let after = CommitmentResponse {
+            commitment: Digest::hash(b"after"),
+            epoch: 1,
+            ..Default::default()
+        };
+
+        let mut prism = MockPrismApi::new();
+        let mut account_calls = 0;
+        prism.expect_get_account().returning(move |_| {
+            account_calls += 1;
+            if account_calls == 1 {
+                return Err(PrismApiError::Http {
+                    status: 404,
+                    body: String::new(),
+                });
+            }
+            Ok(AccountResponse {
+                account: Some(account.clone()),
+                proof: HashedMerkleProof::empty(),
+            })
+        });
+        // the commitment changes while the applied account is fetched for the first time, and
+        // every read is stamped with another time
+        let mut commitment_calls = 0;
+        let commitments = (before.clone(), after.clone());
+        prism.expect_get_commitment().returning(move || {
+            commitment_calls += 1;
+            assert!(commitment_calls <= 10, "commitment never settled");
+            let commitment = if commitment_calls <= 2 {
+                &commitments.0
+            } else {
+                &commitments.1
+            };
+            Ok(CommitmentResponse {
+                timestamp: Some(commitment_calls),
+                ..commitment.clone()
+            })
+        });
+
+        let pending = PendingTransactionImpl::new(&prism, transaction.clone());
+        let (applied, commitment) = pending.wait_for_commitment().await.unwrap();
+
+        assert_eq!(applied.nonce(), 1);
+        assert_eq!(commitment.commitment, after.commitment);
+        assert_eq!(commitment.epoch, after.epoch);
+    }
+
+    #[tokio::test]
+    async fn test_wait_polls_at_configured_interval() {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let mut account = Account::default();
+        account.process_transaction(&transaction).unwrap();
+
+        let mut prism = MockPrismApi::new();
+        let mut account_calls = 0;
+        prism.expect_get_account().returning(move |_| {
+            account_calls += 1;
+            if account_calls < 3 {
+                return Err(PrismApiError::Http {
+                    status: 404,
+                    body: String::new(),
+                });
+            }
+            Ok(AccountResponse {
+                account: Some(account.clone()),
+                proof: HashedMerkleProof::empty(),
+            })
+        });
+
+        let interval = Duration::from_millis(250);
+        let timer = MockPrismTimer::default();
+        let pending = PendingTransactionImpl::new(&prism, transaction)
+            .with_polling_interval(interval)
+            .with_timer(timer.clone());
+        pending.wait().await.unwrap();
+
+        assert_eq!(timer.sleeps(), vec![interval, interval]);
+    }
+
+    #[tokio::test]
+    async fn test_create_did_with_multiple_verification_methods() {
+        let signing_key = SigningKey::new_secp256k1();
+        let verification_methods = HashMap::from([
+            (
+                "atproto".to_string(),
+                SigningKey::new_secp256k1().verifying_key(),
+            ),
+            (
+                "assertion".to_string(),
+                SigningKey::new_secp256r1().verifying_key(),
+            ),
+        ]);
+
+        let mut prism = MockPrismApi::new();
+        let captured = prism.capture_transactions(Ok(Account::default()));
+        prism
+            .create_did(
+                verification_methods.clone(),
+                vec![signing_key.verifying_key()],
+                "at://alice.example.com".to_string(),
+                "http://localhost:2583".to_string(),
+                &signing_key,
+            )
+            .await
+            .unwrap();
+
+        let mut account = Account::default();
+        account.process_transaction(&captured.last().unwrap()).unwrap();
+        assert_eq!(account.verification_methods(), &verification_methods);
+
+        let document = DidDocument::from(&account);
+        let mut ids = document.verification_method.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                format!("{}#assertion", account.id()),
+                format!("{}#atproto", account.id()),
+            ]
+        );
+
+        // validate_basic limits the number of verification methods
+        let too_many = (0..11)
+            .map(|i| {
+                (
+                    format!("key-{}", i),
+                    SigningKey::new_secp256k1().verifying_key(),
+                )
+            })
+            .collect();
+        let result = prism
+            .create_did(
+                too_many,
+                vec![signing_key.verifying_key()],
+                "at://alice.example.com".to_string(),
+                "http://localhost:2583".to_string(),
+                &signing_key,
+            )
+            .await;
+        assert!(matches!(result, Err(PrismApiError::Transaction(_))));
+        assert_eq!(captured.all().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_of_missing_account_is_absent() {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let mut other = Account::default();
+        other.process_transaction(&transaction).unwrap();
+        // the only account in the tree, where the path of the missing account ends
+        let other_key_hash = Digest::hash(other.id());
+        let other_value_hash = Digest::hash(other.encode_to_bytes().unwrap());
+        let other_leaf = HashedMerkleProof::leaf_hash_of(&other_key_hash, &other_value_hash);
+
+        let mut prism = MockPrismApi::new();
+        prism.expect_get_account().returning(move |_| {
+            Ok(AccountResponse {
+                account: None,
+                proof: HashedMerkleProof {
+                    leaf: Some(other_leaf),
+                    leaf_key_hash: Some(other_key_hash),
+                    leaf_value_hash: Some(other_value_hash),
+                    siblings: vec![],
+                },
+            })
+        });
+
+        let lookup = prism.lookup_account("did:prism:missing").await.unwrap();
+        assert!(matches!(lookup, AccountLookup::Absent(_)));
+        lookup.verify("did:prism:missing", &other_leaf).unwrap();
+        assert!(lookup.verify("did:prism:missing", &Digest::hash(b"other root")).is_err());
+
+        // the leaf of a present account does not show its absence
+        let stripped = prism.lookup_account(other.id()).await.unwrap();
+        assert!(matches!(stripped, AccountLookup::Absent(_)));
+        assert!(matches!(
+            stripped.verify(other.id(), &other_leaf),
+            Err(PrismApiError::InvalidProof(_))
+        ));
     }
 }
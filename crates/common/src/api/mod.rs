@@ -1,12 +1,18 @@
 #[cfg(feature = "mockall")]
 pub mod mock;
 pub mod noop;
+#[cfg(feature = "reqwest")]
+pub mod plc_directory;
+mod resolver;
+pub mod timer;
 pub mod types;
 
 use async_trait::async_trait;
-use prism_errors::TransactionError;
-use prism_keys::{SigningKey, VerifyingKey};
+use prism_errors::{AccountError, OperationError, TransactionError};
+use prism_keys::{CryptoAlgorithm, SigningKey, VerifyingKey};
+use prism_serde::binary::{DecodeError, EncodeError};
 use std::{
+    collections::HashMap,
     error::Error,
     fmt::{Debug, Display, Formatter},
     future::Future,
@@ -14,19 +20,43 @@ use std::{
     time::Duration,
 };
 
-use crate::{account::Account, builder::RequestBuilder, transaction::Transaction};
-use types::{AccountResponse, CommitmentResponse};
+use crate::{
+    account::{ATPROTO_VERIFICATION_METHOD, Account},
+    builder::RequestBuilder,
+    operation::SignedPLCOp,
+    transaction::Transaction,
+};
+pub use resolver::{DidMethod, DidResolver, PlcDirectory};
+use types::{
+    AccountLookup, AccountResponse, CommitmentResponse, QueuedTransaction, TransactionResult,
+};
+
+/// HTTP header carrying the idempotency key of a posted transaction, see
+/// [`PrismApi::post_transaction`].
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// HTTP header carrying the id correlating a request across services. Nodes echo it in their
+/// responses, generating one if the request has none.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
 
 #[derive(Clone, Debug)]
 pub enum PrismApiError {
     /// Error while preparing the transaction
     Transaction(TransactionError),
+    /// The transaction can not be applied to the account
+    Account(AccountError),
     /// Error trying to send a request
     RequestFailed(String),
+    /// The server answered with a non-success HTTP status
+    Http { status: u16, body: String },
     /// The target of that API request is invalid
     InvalidTarget(String),
     /// Error during (de)serialization of data
     SerdeFailed(String),
+    /// A returned Merkle proof does not verify against the commitment
+    InvalidProof(String),
+    /// The API implementation does not support that request
+    Unsupported(String),
     /// Bridge for [`anyhow::Error`]
     Any(Arc<anyhow::Error>),
     /// Unknown error
@@ -37,9 +67,13 @@ impl Display for PrismApiError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Transaction(err) => write!(f, "Transaction error {}", err),
+            Self::Account(err) => write!(f, "Account error {}", err),
             Self::RequestFailed(msg) => write!(f, "Request execution failed: {}", msg),
+            Self::Http { status, body } => write!(f, "HTTP status {}: {}", status, body),
             Self::InvalidTarget(msg) => write!(f, "Invalid target: {}", msg),
             Self::SerdeFailed(msg) => write!(f, "(De)Serialization error: {}", msg),
+            Self::InvalidProof(msg) => write!(f, "Invalid proof: {}", msg),
+            Self::Unsupported(msg) => write!(f, "Unsupported request: {}", msg),
             Self::Any(msg) => write!(f, "Unspecific error: {}", msg),
             Self::Unknown => write!(f, "Unknown error"),
         }
@@ -48,12 +82,50 @@ impl Display for PrismApiError {
 
 impl Error for PrismApiError {}
 
+impl PrismApiError {
+    /// Returns `true` if the server reported that the requested resource does not exist (yet).
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::Http { status: 404, .. })
+    }
+}
+
 impl From<TransactionError> for PrismApiError {
     fn from(err: TransactionError) -> Self {
         PrismApiError::Transaction(err)
     }
 }
 
+/// Invalid operations are reported like the builders do, as [`TransactionError::InvalidOp`].
+impl From<OperationError> for PrismApiError {
+    fn from(err: OperationError) -> Self {
+        PrismApiError::Transaction(TransactionError::InvalidOp(err.to_string()))
+    }
+}
+
+impl From<AccountError> for PrismApiError {
+    fn from(err: AccountError) -> Self {
+        PrismApiError::Account(err)
+    }
+}
+
+impl From<serde_json::Error> for PrismApiError {
+    fn from(err: serde_json::Error) -> Self {
+        PrismApiError::SerdeFailed(err.to_string())
+    }
+}
+
+impl From<EncodeError> for PrismApiError {
+    fn from(err: EncodeError) -> Self {
+        PrismApiError::SerdeFailed(err.to_string())
+    }
+}
+
+impl From<DecodeError> for PrismApiError {
+    fn from(err: DecodeError) -> Self {
+        PrismApiError::SerdeFailed(err.to_string())
+    }
+}
+
 impl From<anyhow::Error> for PrismApiError {
     fn from(err: anyhow::Error) -> Self {
         PrismApiError::Any(Arc::new(err))
@@ -69,13 +141,51 @@ where
 
     async fn get_account(&self, id: &str) -> Result<AccountResponse, PrismApiError>;
 
+    /// Looks up an account, telling its proven absence apart from its presence. See
+    /// [`AccountLookup::verify`] to check the lookup against a commitment.
+    async fn lookup_account(&self, id: &str) -> Result<AccountLookup, PrismApiError> {
+        Ok(self.get_account(id).await?.into())
+    }
+
     async fn get_commitment(&self) -> Result<CommitmentResponse, PrismApiError>;
 
+    /// Posts a transaction for inclusion in the next epoch. If an `idempotency_key` is given, a
+    /// repeated post with the same key (e.g. a retry after a network error) returns the outcome
+    /// of the first one instead of queuing the transaction again.
     async fn post_transaction(
         &self,
         transaction: Transaction,
+        idempotency_key: Option<String>,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError>;
 
+    /// Returns the signed PLC operation that created the given DID. Its
+    /// [`SignedPLCOp::derive_did`] equals `did`.
+    async fn get_genesis_operation(&self, did: &str) -> Result<SignedPLCOp, PrismApiError>;
+
+    /// Returns the DID listing `handle` (e.g. `at://alice.test`) in its `alsoKnownAs`. If several
+    /// DIDs list the handle, the one created first is returned. Not supported unless
+    /// implemented.
+    async fn resolve_handle(&self, _handle: &str) -> Result<String, PrismApiError> {
+        Err(PrismApiError::Unsupported("resolve_handle".to_string()))
+    }
+
+    /// Posts multiple transactions, e.g. when migrating many DIDs. Each transaction is validated
+    /// and queued independently, so a rejected transaction does not fail the others.
+    async fn post_transactions(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionResult>, PrismApiError> {
+        let mut results = Vec::with_capacity(transactions.len());
+        for (index, transaction) in transactions.into_iter().enumerate() {
+            let result = match self.post_transaction(transaction, None).await {
+                Ok(_) => TransactionResult::queued(index),
+                Err(e) => TransactionResult::rejected(index, e.to_string()),
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+
     fn build_request(&self) -> RequestBuilder<'_, Self> {
         RequestBuilder::new_with_prism(self)
     }
@@ -85,6 +195,7 @@ where
         id: String,
         service_id: String,
         service_signing_key: &SigningKey,
+        challenge_expires_at: u64,
         signing_key: &SigningKey,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
         self.build_request()
@@ -92,6 +203,7 @@ where
             .with_id(id)
             .for_service_with_id(service_id)
             .with_key(signing_key.verifying_key())
+            .with_challenge_expiry(challenge_expires_at)
             .meeting_signed_challenge(service_signing_key)?
             .sign(signing_key)?
             .send()
@@ -112,27 +224,62 @@ where
             .await
     }
 
+    /// Creates a DID with the given verification methods, keyed by their id (e.g. `atproto`).
+    /// The DID is signed by `signing_key`, which has to be one of the `rotation_keys`.
     async fn create_did(
         &self,
-        verification_method: VerifyingKey,
+        verification_methods: HashMap<String, VerifyingKey>,
         rotation_keys: Vec<VerifyingKey>,
         also_known_as: String,
         atproto_pds: String,
         signing_key: &SigningKey,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
-        assert!(rotation_keys.contains(&signing_key.clone().verifying_key()));
+        if !rotation_keys.contains(&signing_key.verifying_key()) {
+            return Err(PrismApiError::InvalidTarget(
+                "signing key must be one of the rotation keys".to_string(),
+            ));
+        }
+        // the genesis operation is signed by the PLC rules, which only know these curves
+        if !matches!(
+            signing_key.algorithm(),
+            CryptoAlgorithm::Secp256k1 | CryptoAlgorithm::Secp256r1
+        ) {
+            return Err(PrismApiError::InvalidTarget(format!(
+                "{} signing keys can not sign PLC operations",
+                signing_key.algorithm()
+            )));
+        }
         self.build_request()
             .create_did()
             .with_also_known_as(also_known_as)
-            .with_verification_method("atproto".to_string(), verification_method)
+            .with_verification_methods(verification_methods)
             .with_atproto_pds(atproto_pds)
             .with_rotation_keys(rotation_keys)
-            .build()?
+            .build(signing_key)?
             .sign(signing_key)?
             .send()
             .await
     }
 
+    /// Creates a DID with a single atproto verification method, see [`PrismApi::create_did`].
+    async fn create_atproto_did(
+        &self,
+        verification_method: VerifyingKey,
+        rotation_keys: Vec<VerifyingKey>,
+        also_known_as: String,
+        atproto_pds: String,
+        signing_key: &SigningKey,
+    ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
+        self.create_did(
+            HashMap::from([(ATPROTO_VERIFICATION_METHOD.to_string(), verification_method)]),
+            rotation_keys,
+            also_known_as,
+            atproto_pds,
+            signing_key,
+        )
+        .await
+    }
+
     async fn revoke_key(
         &self,
         account: &Account,
@@ -148,11 +295,15 @@ where
     }
 }
 
-pub trait PrismApiTimer {
-    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send;
+/// Sleeps between the polls of a [`PendingTransaction`]. Pending transactions start with the
+/// default timer, see [`PendingTransactionImpl::with_timer`].
+pub trait PrismApiTimer: Default + Send + Sync {
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
 }
 
-const DEFAULT_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+/// Interval at which pending transactions poll for their application, unless configured
+/// otherwise.
+pub const DEFAULT_POLLING_INTERVAL: Duration = Duration::from_secs(5);
 
 #[async_trait]
 pub trait PendingTransaction<'a>
@@ -161,11 +312,33 @@ where
 {
     type Timer: PrismApiTimer;
 
+    /// Interval used by [`PendingTransaction::wait`] and
+    /// [`PendingTransaction::wait_for_commitment`].
+    fn polling_interval(&self) -> Duration {
+        DEFAULT_POLLING_INTERVAL
+    }
+
     async fn wait(&self) -> Result<Account, PrismApiError> {
-        self.wait_with_interval(DEFAULT_POLLING_INTERVAL).await
+        self.wait_with_interval(self.polling_interval()).await
     }
 
     async fn wait_with_interval(&self, interval: Duration) -> Result<Account, PrismApiError>;
+
+    /// Waits until the transaction is applied, like [`PendingTransaction::wait`], and returns the
+    /// account together with the commitment it was first observed under.
+    async fn wait_for_commitment(&self) -> Result<(Account, CommitmentResponse), PrismApiError> {
+        self.wait_for_commitment_with_interval(self.polling_interval()).await
+    }
+
+    async fn wait_for_commitment_with_interval(
+        &self,
+        interval: Duration,
+    ) -> Result<(Account, CommitmentResponse), PrismApiError>;
+
+    /// Returns where the node queued the transaction, if it reported it.
+    fn receipt(&self) -> Option<&QueuedTransaction> {
+        None
+    }
 }
 
 pub struct PendingTransactionImpl<'a, P>
@@ -174,6 +347,9 @@ where
 {
     prism: &'a P,
     transaction: Transaction,
+    polling_interval: Duration,
+    receipt: Option<QueuedTransaction>,
+    timer: P::Timer,
 }
 
 impl<'a, P> PendingTransactionImpl<'a, P>
@@ -181,7 +357,44 @@ where
     P: PrismApi,
 {
     pub fn new(prism: &'a P, transaction: Transaction) -> Self {
-        Self { prism, transaction }
+        Self {
+            prism,
+            transaction,
+            polling_interval: DEFAULT_POLLING_INTERVAL,
+            receipt: None,
+            timer: P::Timer::default(),
+        }
+    }
+
+    /// Attaches the receipt the node answered the posted transaction with, see
+    /// [`PendingTransaction::receipt`].
+    pub fn with_receipt(mut self, receipt: QueuedTransaction) -> Self {
+        self.receipt = Some(receipt);
+        self
+    }
+
+    /// Overrides the interval [`PendingTransaction::wait`] polls at, e.g. with a client's
+    /// configured interval.
+    pub fn with_polling_interval(mut self, polling_interval: Duration) -> Self {
+        self.polling_interval = polling_interval;
+        self
+    }
+
+    /// Overrides the timer sleeping between polls, e.g. with one recording the sleeps in tests.
+    pub fn with_timer(mut self, timer: P::Timer) -> Self {
+        self.timer = timer;
+        self
+    }
+
+    /// Returns the account if the transaction has been applied to it yet.
+    async fn applied_account(&self) -> Result<Option<Account>, PrismApiError> {
+        let response = match self.prism.get_account(&self.transaction.id).await {
+            Ok(response) => response,
+            // The account is not known to the node until the transaction is confirmed
+            Err(err) if err.is_not_found() => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(response.account.filter(|account| account.nonce() > self.transaction.nonce))
     }
 }
 
@@ -192,17 +405,40 @@ where
 {
     type Timer = P::Timer;
 
+    fn polling_interval(&self) -> Duration {
+        self.polling_interval
+    }
+
+    fn receipt(&self) -> Option<&QueuedTransaction> {
+        self.receipt.as_ref()
+    }
+
     async fn wait_with_interval(&self, interval: Duration) -> Result<Account, PrismApiError> {
         loop {
-            if let AccountResponse {
-                account: Some(account),
-                proof: _,
-            } = self.prism.get_account(&self.transaction.id).await?
-                && account.nonce() > self.transaction.nonce
-            {
+            if let Some(account) = self.applied_account().await? {
                 return Ok(account);
-            };
-            Self::Timer::sleep(interval).await;
+            }
+            self.timer.sleep(interval).await;
+        }
+    }
+
+    async fn wait_for_commitment_with_interval(
+        &self,
+        interval: Duration,
+    ) -> Result<(Account, CommitmentResponse), PrismApiError> {
+        loop {
+            // The account is only known to be under the commitment if the commitment did not
+            // change while the account was fetched. Timestamps may differ between two reads of
+            // the same commitment, so only the commitment and its epoch are compared.
+            let commitment = self.prism.get_commitment().await?;
+            if let Some(account) = self.applied_account().await? {
+                let current = self.prism.get_commitment().await?;
+                if current.commitment == commitment.commitment && current.epoch == commitment.epoch
+                {
+                    return Ok((account, commitment));
+                }
+            }
+            self.timer.sleep(interval).await;
         }
     }
 }
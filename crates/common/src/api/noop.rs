@@ -5,12 +5,13 @@ use super::{
     PendingTransaction, PrismApi, PrismApiError, PrismApiTimer,
     types::{AccountResponse, CommitmentResponse},
 };
-use crate::{account::Account, transaction::Transaction};
+use crate::{account::Account, operation::SignedPLCOp, transaction::Transaction};
 
+#[derive(Default)]
 pub struct NoopTimer;
 
 impl PrismApiTimer for NoopTimer {
-    async fn sleep(_: Duration) {}
+    async fn sleep(&self, _: Duration) {}
 }
 
 pub struct NoopPendingTransaction;
@@ -22,6 +23,13 @@ impl PendingTransaction<'_> for NoopPendingTransaction {
     async fn wait_with_interval(&self, _: Duration) -> Result<Account, PrismApiError> {
         Err(PrismApiError::Unknown)
     }
+
+    async fn wait_for_commitment_with_interval(
+        &self,
+        _: Duration,
+    ) -> Result<(Account, CommitmentResponse), PrismApiError> {
+        Err(PrismApiError::Unknown)
+    }
 }
 
 pub struct NoopPrismApi;
@@ -41,7 +49,16 @@ impl PrismApi for NoopPrismApi {
     async fn post_transaction(
         &self,
         _: Transaction,
+        _: Option<String>,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
         Result::<NoopPendingTransaction, PrismApiError>::Err(PrismApiError::Unknown)
     }
+
+    async fn get_genesis_operation(&self, _: &str) -> Result<SignedPLCOp, PrismApiError> {
+        Err(PrismApiError::Unknown)
+    }
+
+    async fn resolve_handle(&self, _: &str) -> Result<String, PrismApiError> {
+        Err(PrismApiError::Unknown)
+    }
 }
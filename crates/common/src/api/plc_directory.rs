@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+
+use super::{PlcDirectory, PrismApiError, types::DidDocument};
+use crate::operation::SignedPLCOp;
+
+pub const DEFAULT_PLC_DIRECTORY_URL: &str = "https://plc.directory";
+
+/// HTTP client for a PLC directory, resolving `did:plc:...` identifiers and submitting
+/// operations for them.
+pub struct PlcDirectoryClient {
+    client: reqwest::Client,
+    base_url: reqwest::Url,
+}
+
+impl PlcDirectoryClient {
+    pub fn new(base_url: &str) -> Result<Self, PrismApiError> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base_url: reqwest::Url::parse(base_url)
+                .map_err(|e| PrismApiError::InvalidTarget(e.to_string()))?,
+        })
+    }
+
+    /// Resolves `did` to its current DID document.
+    pub async fn resolve(&self, did: &str) -> Result<DidDocument, PrismApiError> {
+        let response = self.client.get(self.did_url(did)?).send().await.map_err(request_failed)?;
+        let body = check_status(response).await?.bytes().await.map_err(request_failed)?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    fn did_url(&self, did: &str) -> Result<reqwest::Url, PrismApiError> {
+        // The leading slash keeps the `did:` prefix from being parsed as a URL scheme
+        self.base_url
+            .join(&format!("/{}", did))
+            .map_err(|e| PrismApiError::InvalidTarget(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl PlcDirectory for PlcDirectoryClient {
+    async fn get_did_document(&self, did: &str) -> Result<DidDocument, PrismApiError> {
+        self.resolve(did).await
+    }
+
+    async fn submit(&self, did: &str, operation: &SignedPLCOp) -> Result<(), PrismApiError> {
+        // plc.directory expects the camelCase operation with its base64url `sig`, as serialized
+        let response = self
+            .client
+            .post(self.did_url(did)?)
+            .json(operation)
+            .send()
+            .await
+            .map_err(request_failed)?;
+        check_status(response).await?;
+        Ok(())
+    }
+}
+
+fn request_failed(err: reqwest::Error) -> PrismApiError {
+    PrismApiError::RequestFailed(err.to_string())
+}
+
+/// Turns non-success responses into [`PrismApiError::Http`], keeping the body.
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, PrismApiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(PrismApiError::Http {
+        status: status.as_u16(),
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use prism_keys::SigningKey;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        account::Account,
+        test_utils::{create_did_tx, serve_once},
+    };
+
+    fn create_did_account() -> (Account, SignedPLCOp) {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let operation = SignedPLCOp::try_from(&transaction.operation).unwrap();
+        let mut account = Account::default();
+        account.process_transaction(&transaction).unwrap();
+        (account, operation)
+    }
+
+    #[tokio::test]
+    async fn test_submit_posts_plc_operation() {
+        let (account, operation) = create_did_account();
+        let did = account.id().replace("did:prism:", "did:plc:");
+        let (url, request) = serve_once("200 OK", String::new()).await;
+
+        let directory = PlcDirectoryClient::new(&url).unwrap();
+        directory.submit(&did, &operation).await.unwrap();
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with(&format!("POST /{} HTTP/1.1", did)));
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        let body: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["type"], "plc_operation");
+        assert_eq!(body["sig"], operation.sig);
+        assert_eq!(body["rotationKeys"][0], operation.unsigned.rotation_keys[0]);
+        assert!(body["verificationMethods"].is_object());
+        assert!(body["alsoKnownAs"].is_array());
+        assert!(body["services"]["atproto_pds"].is_object());
+        assert!(body["prev"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_submit_reports_rejection() {
+        let (account, operation) = create_did_account();
+        let (url, _) = serve_once(
+            "400 Bad Request",
+            r#"{"message":"Invalid Signature"}"#.to_string(),
+        )
+        .await;
+
+        let directory = PlcDirectoryClient::new(&url).unwrap();
+        let err = directory.submit(account.id(), &operation).await.unwrap_err();
+        assert!(matches!(err, PrismApiError::Http { status: 400, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_fetches_did_document() {
+        let (account, _) = create_did_account();
+        let document = DidDocument::from(&account);
+        let (url, request) = serve_once("200 OK", serde_json::to_string(&document).unwrap()).await;
+
+        let directory = PlcDirectoryClient::new(&url).unwrap();
+        let resolved = directory.resolve(account.id()).await.unwrap();
+
+        assert_eq!(resolved, document);
+        assert!(request.await.unwrap().starts_with(&format!("GET /{} HTTP/1.1", account.id())));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_reports_missing_and_malformed_documents() {
+        let (url, _) = serve_once(
+            "404 Not Found",
+            r#"{"message":"DID not registered"}"#.to_string(),
+        )
+        .await;
+        let directory = PlcDirectoryClient::new(&url).unwrap();
+        let err = directory.resolve("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa").await.unwrap_err();
+        assert!(err.is_not_found());
+
+        let (url, _) = serve_once("200 OK", r#"{"id": "did:plc:"#.to_string()).await;
+        let directory = PlcDirectoryClient::new(&url).unwrap();
+        let err = directory.get_did_document("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa").await.unwrap_err();
+        assert!(matches!(err, PrismApiError::SerdeFailed(msg) if msg.contains("EOF")));
+    }
+}
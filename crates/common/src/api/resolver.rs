@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+
+use super::{PrismApi, PrismApiError, types::DidDocument};
+use crate::operation::SignedPLCOp;
+
+/// The DID methods that can be resolved through a [`DidResolver`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DidMethod {
+    /// `did:plc:...` identifiers, as registered at a PLC directory such as plc.directory
+    Plc,
+    /// `did:prism:...` identifiers, as stored by a prism node
+    Prism,
+}
+
+impl DidMethod {
+    /// Returns the prefix that identifiers of this method start with.
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Plc => "did:plc:",
+            Self::Prism => "did:prism:",
+        }
+    }
+
+    /// Determines the method of the given DID, or errors if the method is not supported.
+    pub fn of(did: &str) -> Result<Self, PrismApiError> {
+        [Self::Plc, Self::Prism]
+            .into_iter()
+            .find(|method| did.starts_with(method.prefix()))
+            .ok_or_else(|| PrismApiError::InvalidTarget(format!("Unsupported DID method: {}", did)))
+    }
+}
+
+/// A PLC directory that serves DID documents for `did:plc:...` identifiers.
+#[async_trait]
+pub trait PlcDirectory
+where
+    Self: Send + Sync,
+{
+    /// Fetches the current DID document of `did`.
+    async fn get_did_document(&self, did: &str) -> Result<DidDocument, PrismApiError>;
+
+    /// Submits a signed operation for `did`, e.g. to register a DID created through prism at
+    /// the directory as well. The directory verifies the signature against the DID's rotation
+    /// keys.
+    async fn submit(&self, did: &str, operation: &SignedPLCOp) -> Result<(), PrismApiError>;
+}
+
+/// Resolves DIDs to their DID documents by dispatching on the [`DidMethod`].
+pub struct DidResolver<'a, P, D>
+where
+    P: PrismApi,
+    D: PlcDirectory,
+{
+    prism: &'a P,
+    plc: &'a D,
+}
+
+impl<'a, P, D> DidResolver<'a, P, D>
+where
+    P: PrismApi,
+    D: PlcDirectory,
+{
+    pub const fn new(prism: &'a P, plc: &'a D) -> Self {
+        Self { prism, plc }
+    }
+
+    /// Resolves `did` to its DID document. `did:plc:` identifiers are fetched from the PLC
+    /// directory, `did:prism:` identifiers from the prism node.
+    ///
+    /// Returns `None` if the DID is not known to the responsible source.
+    pub async fn resolve(&self, did: &str) -> Result<Option<DidDocument>, PrismApiError> {
+        match DidMethod::of(did)? {
+            DidMethod::Plc => match self.plc.get_did_document(did).await {
+                Ok(document) => Ok(Some(document)),
+                Err(err) if err.is_not_found() => Ok(None),
+                Err(err) => Err(err),
+            },
+            DidMethod::Prism => {
+                let response = self.prism.get_account(did).await?;
+                Ok(response.account.as_ref().map(DidDocument::from))
+            }
+        }
+    }
+}
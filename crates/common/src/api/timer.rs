@@ -0,0 +1,111 @@
+//! [`PrismApiTimer`] implementations for the supported runtimes.
+
+#[cfg(any(feature = "tokio", feature = "wasm"))]
+use std::{future::Future, time::Duration};
+
+#[cfg(any(feature = "tokio", feature = "wasm"))]
+use super::PrismApiTimer;
+
+/// Timer sleeping on the tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Default)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl PrismApiTimer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// Timer sleeping via the browser's `setTimeout`.
+///
+/// The timeout future is bound to the JS thread, so it must not be polled from another thread.
+#[cfg(feature = "wasm")]
+#[derive(Default)]
+pub struct WasmTimer;
+
+#[cfg(feature = "wasm")]
+impl PrismApiTimer for WasmTimer {
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        send_wrapper::SendWrapper::new(gloo_timers::future::sleep(duration))
+    }
+}
+
+#[cfg(all(test, feature = "tokio", feature = "mockall"))]
+mod tests {
+    use std::time::Instant;
+
+    use async_trait::async_trait;
+    use prism_keys::SigningKey;
+
+    use super::*;
+    use crate::{
+        account::Account,
+        api::{
+            PendingTransaction, PendingTransactionImpl, PrismApi, PrismApiError,
+            mock::MockPrismApi,
+            types::{AccountResponse, CommitmentResponse},
+        },
+        operation::SignedPLCOp,
+        test_utils::create_did_tx,
+        transaction::Transaction,
+    };
+
+    /// Delegates to a [`MockPrismApi`], but sleeps on tokio.
+    struct TokioPrismApi(MockPrismApi);
+
+    #[async_trait]
+    impl PrismApi for TokioPrismApi {
+        type Timer = TokioTimer;
+
+        async fn get_account(&self, id: &str) -> Result<AccountResponse, PrismApiError> {
+            self.0.get_account(id).await
+        }
+
+        async fn get_commitment(&self) -> Result<CommitmentResponse, PrismApiError> {
+            self.0.get_commitment().await
+        }
+
+        async fn post_transaction(
+            &self,
+            transaction: Transaction,
+            _: Option<String>,
+        ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
+            Ok(PendingTransactionImpl::new(self, transaction))
+        }
+
+        async fn get_genesis_operation(&self, did: &str) -> Result<SignedPLCOp, PrismApiError> {
+            self.0.get_genesis_operation(did).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_with_interval_sleeps_on_tokio() {
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let mut account = Account::default();
+        account.process_transaction(&transaction).unwrap();
+
+        let mut mock = MockPrismApi::new();
+        let mut polls = 0;
+        mock.expect_get_account().returning(move |_| {
+            polls += 1;
+            Ok(AccountResponse {
+                account: (polls > 2).then(|| account.clone()),
+                ..Default::default()
+            })
+        });
+        let api = TokioPrismApi(mock);
+
+        let interval = Duration::from_millis(20);
+        let start = Instant::now();
+        let applied = PendingTransactionImpl::new(&api, transaction)
+            .wait_with_interval(interval)
+            .await
+            .unwrap();
+
+        assert_eq!(applied.nonce(), 1);
+        assert!(start.elapsed() >= 2 * interval);
+    }
+}
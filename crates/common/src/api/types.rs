@@ -1,7 +1,12 @@
+use anyhow::Result;
+use prism_errors::{AccountError, OperationError, TransactionError};
+use prism_keys::CryptoAlgorithm;
+use prism_serde::binary::{FromBinary, ToBinary};
 use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, error::Error};
 use utoipa::ToSchema;
 
-use crate::{account::Account, digest::Digest};
+use crate::{account::Account, api::PrismApiError, digest::Digest, transaction::Transaction};
 
 #[derive(Default, Debug, Serialize, Deserialize, ToSchema)]
 /// Request to retrieve account information
@@ -10,6 +15,29 @@ pub struct AccountRequest {
     pub id: String,
 }
 
+#[derive(Default, Debug, Serialize, Deserialize, ToSchema)]
+/// Request to look up the DID claiming a handle
+pub struct ResolveHandleRequest {
+    /// The handle as listed in `alsoKnownAs`, e.g. `at://alice.test`
+    pub handle: String,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, ToSchema)]
+/// Response containing the DID claiming a handle
+pub struct ResolveHandleResponse {
+    /// The DID listing the handle in its `alsoKnownAs`
+    pub did: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+/// Response containing the most recent operation applied to a DID
+pub struct LastOperationResponse {
+    /// CID of the operation, i.e. the current head of the DID
+    pub cid: String,
+    /// The transaction carrying the operation. Its `prev` is the CID of the operation it replaced
+    pub transaction: Transaction,
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, ToSchema)]
 /// Response containing account data and a corresponding Merkle proof
 pub struct AccountResponse {
@@ -19,16 +47,298 @@ pub struct AccountResponse {
     pub proof: HashedMerkleProof,
 }
 
+impl AccountResponse {
+    /// Verifies that the response for `id` is part of the state committed to by `commitment`:
+    /// the proven leaf has to hold the returned account, or, if no account was returned, the
+    /// path of `id` has to end in an empty subtree or in a leaf whose key is not `id`.
+    pub fn verify(&self, id: &str, commitment: &Digest) -> Result<(), PrismApiError> {
+        verify_account_proof(self.account.as_ref(), &self.proof, id, commitment)
+    }
+}
+
+/// An account lookup whose outcome, presence or absence, is backed by a Merkle proof.
+#[derive(Debug)]
+pub enum AccountLookup {
+    /// The account exists, shown by a membership proof
+    Present(Box<Account>, HashedMerkleProof),
+    /// No account exists under the id, shown by a non-membership proof whose path ends in an empty
+    /// subtree or in the leaf of another key
+    Absent(HashedMerkleProof),
+}
+
+impl AccountLookup {
+    pub fn account(&self) -> Option<&Account> {
+        match self {
+            Self::Present(account, _) => Some(account),
+            Self::Absent(_) => None,
+        }
+    }
+
+    pub const fn proof(&self) -> &HashedMerkleProof {
+        match self {
+            Self::Present(_, proof) | Self::Absent(proof) => proof,
+        }
+    }
+
+    /// Verifies the proof of the lookup of `id` against `commitment`, see
+    /// [`AccountResponse::verify`].
+    pub fn verify(&self, id: &str, commitment: &Digest) -> Result<(), PrismApiError> {
+        verify_account_proof(self.account(), self.proof(), id, commitment)
+    }
+}
+
+impl From<AccountResponse> for AccountLookup {
+    fn from(response: AccountResponse) -> Self {
+        match response.account {
+            Some(account) => Self::Present(Box::new(account), response.proof),
+            None => Self::Absent(response.proof),
+        }
+    }
+}
+
+impl From<AccountLookup> for AccountResponse {
+    fn from(lookup: AccountLookup) -> Self {
+        match lookup {
+            AccountLookup::Present(account, proof) => Self {
+                account: Some(*account),
+                proof,
+            },
+            AccountLookup::Absent(proof) => Self {
+                account: None,
+                proof,
+            },
+        }
+    }
+}
+
+fn verify_account_proof(
+    account: Option<&Account>,
+    proof: &HashedMerkleProof,
+    id: &str,
+    commitment: &Digest,
+) -> Result<(), PrismApiError> {
+    let invalid = |msg: &str| PrismApiError::InvalidProof(msg.to_string());
+
+    match (account, proof.leaf) {
+        (Some(account), Some(leaf)) => {
+            if account.id() != id {
+                return Err(invalid("account does not match the requested id"));
+            }
+            if leaf != HashedMerkleProof::leaf_hash(id, &account.encode_to_bytes()?) {
+                return Err(invalid("leaf does not hold the account"));
+            }
+        }
+        (Some(_), None) => return Err(invalid("membership proof is missing its leaf")),
+        (None, Some(leaf)) => {
+            // the path of `id` ends in the leaf of another account
+            let (Some(key_hash), Some(value_hash)) = (proof.leaf_key_hash, proof.leaf_value_hash)
+            else {
+                return Err(invalid(
+                    "non-membership proof does not reveal the key of its leaf",
+                ));
+            };
+            if key_hash == Digest::hash(id) {
+                return Err(invalid("leaf belongs to the requested id"));
+            }
+            if leaf != HashedMerkleProof::leaf_hash_of(&key_hash, &value_hash) {
+                return Err(invalid("leaf does not match its key and value"));
+            }
+        }
+        (None, None) => {}
+    }
+
+    if proof.siblings.len() > 256 {
+        return Err(invalid("proof is deeper than the tree"));
+    }
+    if proof.compute_root(id) != *commitment {
+        return Err(invalid("proof does not match the commitment"));
+    }
+    Ok(())
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+/// Result of validating a transaction without queuing it
+pub struct ValidationResponse {
+    /// Whether the transaction would be accepted
+    pub valid: bool,
+    /// The reason the transaction would be rejected, if invalid
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+/// Error returned for a rejected transaction, so that clients can react to specific failures
+pub struct TransactionErrorResponse {
+    /// Machine-readable error code, e.g. `nonce_mismatch` or `invalid_signature`
+    pub error_code: String,
+    /// Human-readable description of the error
+    pub message: String,
+    /// The transaction field the error refers to, if any
+    pub field: Option<String>,
+}
+
+impl From<&anyhow::Error> for TransactionErrorResponse {
+    fn from(err: &anyhow::Error) -> Self {
+        let (error_code, field) =
+            err.chain().find_map(error_code).unwrap_or(("invalid_transaction", None));
+        Self {
+            error_code: error_code.to_string(),
+            message: err.to_string(),
+            field: field.map(str::to_string),
+        }
+    }
+}
+
+/// Returns the error code and affected transaction field of the typed errors a transaction can
+/// be rejected with.
+fn error_code(err: &(dyn Error + 'static)) -> Option<(&'static str, Option<&'static str>)> {
+    if let Some(err) = err.downcast_ref::<AccountError>() {
+        return Some(account_error_code(err));
+    }
+    if let Some(err) = err.downcast_ref::<TransactionError>() {
+        return Some(transaction_error_code(err));
+    }
+    err.downcast_ref::<OperationError>().map(|err| (operation_error_code(err), Some("operation")))
+}
+
+fn account_error_code(err: &AccountError) -> (&'static str, Option<&'static str>) {
+    match err {
+        AccountError::NonceError(..) => ("nonce_mismatch", Some("nonce")),
+        AccountError::NonceOverflow(_) => ("nonce_overflow", Some("nonce")),
+        AccountError::PrevError(..) => ("prev_mismatch", Some("prev")),
+        AccountError::AccountIdError(..) => ("account_id_mismatch", Some("id")),
+        AccountError::AccountKeyError(..) => ("account_key_mismatch", Some("vk")),
+        AccountError::TransactionIdError(..) => ("transaction_id_mismatch", Some("id")),
+        AccountError::InsufficientAuthority(_) => ("insufficient_authority", Some("vk")),
+        AccountError::UnknownForkBase(_) => ("unknown_fork_base", Some("prev")),
+        AccountError::Deactivated(_) => ("account_deactivated", Some("id")),
+        AccountError::InvalidKey => ("invalid_key", Some("vk")),
+        AccountError::NotAService(_) => ("not_a_service", Some("operation")),
+        AccountError::InvalidChallenge(_) => ("invalid_challenge", Some("operation")),
+        AccountError::TransactionError(err) => transaction_error_code(err),
+    }
+}
+
+fn transaction_error_code(err: &TransactionError) -> (&'static str, Option<&'static str>) {
+    match err {
+        TransactionError::InvalidOp(_) => ("invalid_operation", Some("operation")),
+        TransactionError::InvalidNonce(_) => ("invalid_nonce", Some("nonce")),
+        TransactionError::MissingKey => ("missing_key", Some("vk")),
+        TransactionError::MissingChallengeExpiry => ("missing_challenge_expiry", Some("operation")),
+        TransactionError::IdMismatch(..) => ("id_mismatch", Some("id")),
+        TransactionError::EncodingFailed(_) => ("encoding_failed", None),
+        TransactionError::SigningFailed => ("signing_failed", Some("signature")),
+        TransactionError::MissingSender => ("missing_sender", Some("vk")),
+        TransactionError::UnknownBlobVersion(_) => ("unknown_blob_version", None),
+        TransactionError::DecodingFailed(_) => ("decoding_failed", None),
+        TransactionError::InvalidSignature(_) => ("invalid_signature", Some("signature")),
+        TransactionError::IdempotencyKeyReused(_) => ("idempotency_key_reused", None),
+    }
+}
+
+fn operation_error_code(err: &OperationError) -> &'static str {
+    match err {
+        OperationError::EmptyServiceId => "empty_service_id",
+        OperationError::EmptyAccountId => "empty_account_id",
+        OperationError::EmptyServiceIdForAccount => "empty_service_id_for_account",
+        OperationError::DataTooLarge(_) => "data_too_large",
+        OperationError::InvalidPLCConversion => "invalid_plc_conversion",
+        OperationError::UnsupportedPlcSignature(_) => "unsupported_plc_signature",
+        OperationError::UnsupportedPlcKey(_) => "unsupported_plc_key",
+        OperationError::InvalidServiceEndpoint(_) => "invalid_service_endpoint",
+        OperationError::EmptyServiceType => "empty_service_type",
+        OperationError::UnsupportedPlcOperationType(_) => "unsupported_plc_operation_type",
+        OperationError::UnknownOperation(_) => "unknown_operation",
+        OperationError::EmptyRotationKeys => "empty_rotation_keys",
+        OperationError::DuplicateRotationKey(..) => "duplicate_rotation_key",
+        OperationError::PrevMismatch { .. } => "prev_mismatch",
+        OperationError::ChallengeExpired { .. } => "challenge_expired",
+        OperationError::InvalidDidKey(..) => "invalid_did_key",
+        OperationError::InvalidPdsServiceType(_) => "invalid_pds_service_type",
+        OperationError::OperationNotAllowed(_) => "operation_not_allowed",
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+/// Receipt of a transaction queued for insertion, so that clients know when to look for it
+pub struct QueuedTransaction {
+    /// Hash of the queued transaction
+    pub tx_hash: Digest,
+    /// Position of the transaction in the queue of pending transactions, starting at 0
+    pub queue_position: usize,
+    /// The earliest epoch that can include the transaction, i.e. the epoch finalized next
+    pub target_epoch: u64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+/// Outcome of a single transaction of a batch submission
+pub enum TransactionStatus {
+    /// The transaction was queued for insertion into the next epoch
+    Queued,
+    /// The transaction was rejected
+    Rejected,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+/// Result of a single transaction of a batch submission
+pub struct TransactionResult {
+    /// Position of the transaction in the submitted batch
+    pub index: usize,
+    /// Whether the transaction was queued
+    pub status: TransactionStatus,
+    /// The reason the transaction was rejected, if rejected
+    pub error: Option<String>,
+}
+
+impl TransactionResult {
+    pub fn queued(index: usize) -> Self {
+        Self {
+            index,
+            status: TransactionStatus::Queued,
+            error: None,
+        }
+    }
+
+    pub fn rejected(index: usize, error: String) -> Self {
+        Self {
+            index,
+            status: TransactionStatus::Rejected,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 /// Response representing a cryptographic commitment towards the current state of prism
 pub struct CommitmentResponse {
     /// Commitment as root hash of Merkle tree
     pub commitment: Digest,
+    /// Version of the tree the commitment is the root of. It grows with every state change, so a
+    /// commitment with a greater epoch commits to a later state.
+    #[serde(default)]
+    pub epoch: u64,
+    /// Unix time in seconds at which the prover committed to the epoch. Absent if the prover
+    /// does not know it, e.g. for the state it was restarted with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+/// Orders commitments by epoch. Unequal responses of the same epoch, e.g. taken at different
+/// times, are not comparable.
+impl PartialOrd for CommitmentResponse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.epoch.cmp(&other.epoch) {
+            Ordering::Equal => (self == other).then_some(Ordering::Equal),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 #[schema(example = r#"{
     "leaf": "1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef",
+    "leaf_key_hash": "fedcba0987654321fedcba0987654321fedcba0987654321fedcba0987654321",
+    "leaf_value_hash": "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
     "siblings": [
         "abcdef1234567890abcdef1234567890abcdef1234567890abcdef1234567890",
         "9876543210fedcba9876543210fedcba9876543210fedcba9876543210fedcba"
@@ -39,17 +349,77 @@ pub struct CommitmentResponse {
 pub struct HashedMerkleProof {
     /// The hash of the leaf node being proven, if it exists. None if proving non-existence.
     pub leaf: Option<Digest>,
+    /// The hash of the key stored in the leaf. Shows that the leaf of a non-membership proof
+    /// belongs to another key.
+    #[serde(default)]
+    pub leaf_key_hash: Option<Digest>,
+    /// The hash of the value stored in the leaf.
+    #[serde(default)]
+    pub leaf_value_hash: Option<Digest>,
     /// The hashes of sibling nodes along the path from the leaf to the root.
     pub siblings: Vec<Digest>,
 }
 
+/// Domain separators of the leaf and internal node hashes of the Jellyfish Merkle Tree holding
+/// the accounts.
+const JMT_LEAF_DOMAIN_SEPARATOR: &[u8] = b"JMT::LeafNode";
+const JMT_INTERNAL_DOMAIN_SEPARATOR: &[u8] = b"JMT::IntrnalNode";
+
+/// Hash of an empty subtree of the Jellyfish Merkle Tree.
+const JMT_PLACEHOLDER_HASH: Digest = Digest::new(*b"SPARSE_MERKLE_PLACEHOLDER_HASH__");
+
 impl HashedMerkleProof {
     pub fn empty() -> Self {
         Self {
             leaf: None,
+            leaf_key_hash: None,
+            leaf_value_hash: None,
             siblings: vec![],
         }
     }
+
+    /// Returns the hash of the leaf storing the encoded `value` under `key`, as found in
+    /// [`HashedMerkleProof::leaf`].
+    pub fn leaf_hash(key: &str, value: &[u8]) -> Digest {
+        Self::leaf_hash_of(&Digest::hash(key), &Digest::hash(value))
+    }
+
+    /// Returns the hash of the leaf storing a value under a key, given the hashes of both.
+    pub fn leaf_hash_of(key_hash: &Digest, value_hash: &Digest) -> Digest {
+        Digest::hash_items(&[
+            JMT_LEAF_DOMAIN_SEPARATOR,
+            key_hash.as_bytes(),
+            value_hash.as_bytes(),
+        ])
+    }
+
+    /// Recomputes the root by hashing the leaf with the siblings, ordered from the bottom level
+    /// up, along the path of `key`. Proofs without a leaf start from an empty subtree.
+    pub fn compute_root(&self, key: &str) -> Digest {
+        let key_hash = Digest::hash(key);
+        let path = key_hash
+            .as_bytes()
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1))
+            .take(self.siblings.len())
+            .collect::<Vec<_>>();
+
+        self.siblings.iter().zip(path.iter().rev()).fold(
+            self.leaf.unwrap_or(JMT_PLACEHOLDER_HASH),
+            |hash, (sibling, is_right)| {
+                let (left, right) = if *is_right {
+                    (sibling, &hash)
+                } else {
+                    (&hash, sibling)
+                };
+                Digest::hash_items(&[
+                    JMT_INTERNAL_DOMAIN_SEPARATOR,
+                    left.as_bytes(),
+                    right.as_bytes(),
+                ])
+            },
+        )
+    }
 }
 
 impl Default for HashedMerkleProof {
@@ -58,7 +428,7 @@ impl Default for HashedMerkleProof {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 /// A verification method in a DID document
 pub struct VerificationMethod {
     /// The verification method identifier
@@ -73,7 +443,49 @@ pub struct VerificationMethod {
     pub public_key_multibase: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+/// How the `type` of verification methods is rendered in a DID document.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerificationMethodType {
+    /// Every method is a `Multikey`, as in current atproto DID documents.
+    #[default]
+    Multikey,
+    /// The method type is chosen by the key's algorithm, e.g.
+    /// `EcdsaSecp256k1VerificationKey2019`, for consumers expecting the older suites. Keys
+    /// without such a suite stay `Multikey`.
+    Legacy,
+}
+
+impl VerificationMethodType {
+    /// Returns the `type` of a verification method holding a key of the given algorithm.
+    pub const fn method_type(self, algorithm: CryptoAlgorithm) -> &'static str {
+        match (self, algorithm) {
+            (Self::Legacy, CryptoAlgorithm::Ed25519) => "Ed25519VerificationKey2020",
+            (
+                Self::Legacy,
+                CryptoAlgorithm::Secp256k1 | CryptoAlgorithm::Eip191 | CryptoAlgorithm::CosmosAdr36,
+            ) => "EcdsaSecp256k1VerificationKey2019",
+            (Self::Multikey, _) | (Self::Legacy, CryptoAlgorithm::Secp256r1) => "Multikey",
+        }
+    }
+
+    /// Returns the JSON-LD context defining [`Self::method_type`] for the given algorithm.
+    pub const fn context(self, algorithm: CryptoAlgorithm) -> &'static str {
+        match (self, algorithm) {
+            (Self::Legacy, CryptoAlgorithm::Ed25519) => {
+                "https://w3id.org/security/suites/ed25519-2020/v1"
+            }
+            (
+                Self::Legacy,
+                CryptoAlgorithm::Secp256k1 | CryptoAlgorithm::Eip191 | CryptoAlgorithm::CosmosAdr36,
+            ) => "https://w3id.org/security/suites/secp256k1-2019/v1",
+            (Self::Multikey, _) | (Self::Legacy, CryptoAlgorithm::Secp256r1) => {
+                "https://w3id.org/security/multikey/v1"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 /// A service endpoint in a DID document
 pub struct DidService {
     /// The service identifier
@@ -86,7 +498,7 @@ pub struct DidService {
     pub service_endpoint: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 /// A complete DID document
 pub struct DidDocument {
     /// The JSON-LD context
@@ -102,6 +514,94 @@ pub struct DidDocument {
     pub verification_method: Vec<VerificationMethod>,
     /// Services
     pub service: Vec<DidService>,
+    /// Whether the DID has been deactivated, as in plc.directory's resolution responses. Omitted
+    /// for active DIDs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deactivated: bool,
+}
+
+impl DidDocument {
+    /// Encodes the document as DAG-CBOR, as served for `application/did+cbor`.
+    pub fn to_dag_cbor(&self) -> Result<Vec<u8>> {
+        Ok(self.encode_to_bytes()?)
+    }
+
+    /// Decodes a document encoded by [`DidDocument::to_dag_cbor`].
+    pub fn from_dag_cbor(bytes: &[u8]) -> Result<Self> {
+        Ok(Self::decode_from_bytes(bytes)?)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+/// Metadata about a DID document, as defined by W3C DID resolution
+pub struct DidDocumentMetadata {
+    /// Whether the DID has been deactivated. Omitted for active DIDs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deactivated: bool,
+    /// The CID of the most recent operation applied to the DID, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+/// Metadata about the resolution process, as defined by W3C DID resolution
+pub struct DidResolutionMetadata {
+    /// The media type of the returned DID document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// The error code if resolution failed, e.g. `notFound` or `invalidDid`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+/// Result of resolving a DID, as defined by W3C DID resolution
+pub struct DidResolutionResult {
+    /// The resolved DID document, if resolution succeeded
+    pub did_document: Option<DidDocument>,
+    /// Metadata about the DID document
+    pub did_document_metadata: DidDocumentMetadata,
+    /// Metadata about the resolution process
+    pub did_resolution_metadata: DidResolutionMetadata,
+}
+
+/// Media type of DID documents returned by resolution.
+pub const DID_DOCUMENT_CONTENT_TYPE: &str = "application/did+ld+json";
+
+/// Media type of DAG-CBOR encoded DID documents, see [`DidDocument::to_dag_cbor`].
+pub const DID_DOCUMENT_CBOR_CONTENT_TYPE: &str = "application/did+cbor";
+
+impl DidResolutionResult {
+    /// Returns a failed resolution result with the given W3C error code.
+    pub fn error(error: &str) -> Self {
+        Self {
+            did_document: None,
+            did_document_metadata: DidDocumentMetadata::default(),
+            did_resolution_metadata: DidResolutionMetadata {
+                content_type: None,
+                error: Some(error.to_string()),
+            },
+        }
+    }
+}
+
+impl From<&Account> for DidResolutionResult {
+    fn from(account: &Account) -> Self {
+        Self {
+            did_document: Some(DidDocument::from(account)),
+            did_document_metadata: DidDocumentMetadata {
+                deactivated: account.is_deactivated(),
+                version_id: account.head_cid().map(str::to_string),
+            },
+            did_resolution_metadata: DidResolutionMetadata {
+                content_type: Some(DID_DOCUMENT_CONTENT_TYPE.to_string()),
+                error: None,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
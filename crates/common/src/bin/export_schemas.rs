@@ -0,0 +1,6 @@
+//! Prints the JSON schemas of the operation types, see
+//! [`prism_common::schemas::export_openapi_components`].
+
+fn main() {
+    println!("{}", prism_common::schemas::export_openapi_components());
+}
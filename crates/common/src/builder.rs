@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
 use prism_errors::TransactionError;
-use prism_keys::{SigningKey, VerifyingKey};
+use prism_keys::{Signature, SigningKey, VerifyingKey};
+use prism_serde::{base64::FromBase64, binary::ToBinary};
 
 use crate::{
-    account::Account,
+    account::{Account, ensure_http_prefix},
     api::{PendingTransaction, PrismApi, PrismApiError, noop::NoopPrismApi},
-    digest::Digest,
-    operation::{Operation, SignatureBundle},
+    operation::{
+        DidConfig, Operation, SignatureBundle, SignedPLCOp, UnsignedPLCOp, service_challenge_hash,
+    },
     transaction::{Transaction, UnsignedTransaction},
 };
 
@@ -27,6 +29,10 @@ where
         Self { prism: Some(prism) }
     }
 
+    pub fn register_service(self) -> RegisterServiceRequestBuilder<'a, P> {
+        RegisterServiceRequestBuilder::new(self.prism)
+    }
+
     pub fn create_account(self) -> CreateAccountRequestBuilder<'a, P> {
         CreateAccountRequestBuilder::new(self.prism)
     }
@@ -45,6 +51,87 @@ where
     ) -> SigningTransactionRequestBuilder<'a, P> {
         SigningTransactionRequestBuilder::new(self.prism, unsigned_transaction)
     }
+
+    /// Wraps a genesis operation that was formed and signed elsewhere, e.g. by the atproto SDK,
+    /// into a DID creation for the DID derived from it.
+    ///
+    /// The operation has to be signed by one of its rotation keys, and may only contain an
+    /// `atproto_pds` service, as that is all [`Operation::CreateDID`] carries.
+    pub fn create_did_from_plc_op(
+        self,
+        op: SignedPLCOp,
+    ) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
+        let invalid = |msg: String| TransactionError::InvalidOp(msg);
+        op.unsigned.validate().map_err(|e| invalid(e.to_string()))?;
+        if op.unsigned.prev.is_some() {
+            return Err(invalid(
+                "DIDs can only be created from genesis operations".to_string(),
+            ));
+        }
+
+        let parse_key = |did: &String| {
+            VerifyingKey::from_did(did).map_err(|e| invalid(format!("invalid key {}: {}", did, e)))
+        };
+        let rotation_keys =
+            op.unsigned.rotation_keys.iter().map(parse_key).collect::<Result<Vec<_>, _>>()?;
+        let verification_methods = op
+            .unsigned
+            .verification_methods
+            .iter()
+            .map(|(id, key)| Ok((id.clone(), parse_key(key)?)))
+            .collect::<Result<HashMap<_, _>, TransactionError>>()?;
+        let atproto_pds = match op.unsigned.services.get("atproto_pds") {
+            Some(service) if op.unsigned.services.len() == 1 => service.endpoint.clone(),
+            _ => {
+                return Err(invalid(
+                    "operation must contain exactly an atproto_pds service".to_string(),
+                ));
+            }
+        };
+
+        let payload = op
+            .unsigned
+            .encode_to_bytes()
+            .map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+        let sig_bytes = Vec::<u8>::from_base64(&op.sig).map_err(|e| invalid(e.to_string()))?;
+        let signature = rotation_keys
+            .iter()
+            .find_map(|key| {
+                let signature =
+                    Signature::from_algorithm_and_bytes(key.algorithm(), &sig_bytes).ok()?;
+                key.verify_signature(&payload, &signature).is_ok().then_some(signature)
+            })
+            .ok_or_else(|| {
+                invalid("Genesis operation must be signed by a rotation key".to_string())
+            })?;
+
+        let did = op.derive_did();
+        let operation = Operation::CreateDID {
+            did: did.clone(),
+            verification_methods,
+            rotation_keys,
+            also_known_as: op.unsigned.also_known_as.clone(),
+            atproto_pds,
+            signature,
+        };
+        operation.validate_basic().map_err(|e| invalid(e.to_string()))?;
+        // verifiers derive the DID from the re-encoded operation, so it has to be unchanged
+        if SignedPLCOp::try_from(&operation).map_err(|e| invalid(e.to_string()))? != op {
+            return Err(invalid(
+                "operation can not be represented as a CreateDID operation".to_string(),
+            ));
+        }
+
+        Ok(SigningTransactionRequestBuilder::new(
+            self.prism,
+            UnsignedTransaction {
+                id: did,
+                operation,
+                nonce: 0,
+                prev: None,
+            },
+        ))
+    }
 }
 
 impl<P> Default for RequestBuilder<'_, P>
@@ -56,6 +143,68 @@ where
     }
 }
 
+pub struct RegisterServiceRequestBuilder<'a, P>
+where
+    P: PrismApi,
+{
+    prism: Option<&'a P>,
+    id: String,
+    key: Option<VerifyingKey>,
+}
+
+impl<'a, P> RegisterServiceRequestBuilder<'a, P>
+where
+    P: PrismApi,
+{
+    pub fn new(prism: Option<&'a P>) -> Self {
+        Self {
+            prism,
+            id: String::new(),
+            key: None,
+        }
+    }
+
+    pub fn with_id(mut self, id: String) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn with_key(mut self, key: VerifyingKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Requires accounts created for this service to meet a challenge signed by
+    /// `challenge_key`, see [`CreateAccountRequestBuilder::meeting_signed_challenge`].
+    pub fn requiring_signed_challenge(
+        self,
+        challenge_key: VerifyingKey,
+    ) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
+        let Some(key) = self.key else {
+            return Err(TransactionError::MissingKey);
+        };
+
+        let operation = Operation::RegisterService {
+            id: self.id.clone(),
+            key,
+            challenge_key,
+        };
+
+        operation.validate_basic().map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+
+        let unsigned_transaction = UnsignedTransaction {
+            id: self.id,
+            operation,
+            nonce: 0,
+            prev: None,
+        };
+        Ok(SigningTransactionRequestBuilder::new(
+            self.prism,
+            unsigned_transaction,
+        ))
+    }
+}
+
 pub struct CreateAccountRequestBuilder<'a, P>
 where
     P: PrismApi,
@@ -64,6 +213,7 @@ where
     id: String,
     service_id: String,
     key: Option<VerifyingKey>,
+    challenge_expires_at: Option<u64>,
 }
 
 impl<'a, P> CreateAccountRequestBuilder<'a, P>
@@ -76,6 +226,7 @@ where
             id: String::new(),
             service_id: String::new(),
             key: None,
+            challenge_expires_at: None,
         }
     }
 
@@ -94,6 +245,12 @@ where
         self
     }
 
+    /// Sets the Unix time in seconds from which on the service challenge is rejected.
+    pub const fn with_challenge_expiry(mut self, expires_at: u64) -> Self {
+        self.challenge_expires_at = Some(expires_at);
+        self
+    }
+
     pub fn meeting_signed_challenge(
         self,
         service_signing_key: &SigningKey,
@@ -101,18 +258,20 @@ where
         let Some(key) = self.key else {
             return Err(TransactionError::MissingKey);
         };
+        let Some(challenge_expires_at) = self.challenge_expires_at else {
+            return Err(TransactionError::MissingChallengeExpiry);
+        };
 
         // This could be some external service signing account creation credentials
-        let hash = Digest::hash_items(&[
-            self.id.as_bytes(),
-            self.service_id.as_bytes(),
-            &key.to_bytes(),
-        ]);
-        let signature =
+        let hash = service_challenge_hash(&self.id, &self.service_id, &key, challenge_expires_at);
+        let challenge =
             service_signing_key.sign(hash).map_err(|_| TransactionError::SigningFailed)?;
 
         let operation = Operation::CreateAccount {
             id: self.id.clone(),
+            service_id: self.service_id,
+            challenge,
+            challenge_expires_at,
             key,
         };
 
@@ -122,6 +281,7 @@ where
             id: self.id,
             operation,
             nonce: 0,
+            prev: None,
         };
         Ok(SigningTransactionRequestBuilder::new(
             self.prism,
@@ -139,6 +299,7 @@ where
     rotation_keys: Vec<VerifyingKey>,
     also_known_as: Vec<String>,
     atproto_pds: String,
+    did_config: DidConfig,
 }
 
 impl<'a, P> CreateDIDRequestBuilder<'a, P>
@@ -152,6 +313,7 @@ where
             rotation_keys: Vec::new(),
             also_known_as: Vec::new(),
             atproto_pds: String::new(),
+            did_config: DidConfig::DEFAULT,
         }
     }
 
@@ -160,6 +322,11 @@ where
         self
     }
 
+    pub fn with_verification_methods(mut self, methods: HashMap<String, VerifyingKey>) -> Self {
+        self.verification_methods.extend(methods);
+        self
+    }
+
     pub fn with_rotation_keys(mut self, keys: Vec<VerifyingKey>) -> Self {
         self.rotation_keys = keys;
         self
@@ -171,55 +338,101 @@ where
     }
 
     pub fn with_atproto_pds(mut self, pds: String) -> Self {
-        self.atproto_pds = pds;
+        self.atproto_pds = ensure_http_prefix(pds);
         self
     }
 
-    // TODO(DID): atrocious, hacky rust
-    pub fn build(self) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
-        unimplemented!("TODO(DID)")
-        // let operation = Operation::CreateDID {
-        //     did: "".to_string(),
-        //     verification_methods: self.verification_methods.clone(),
-        //     rotation_keys: self.rotation_keys.clone(),
-        //     also_known_as: self.also_known_as.clone(),
-        //     atproto_pds: self.atproto_pds.clone(),
-        //     // TODO(DID): fix
-        //     signature: "".to_string(),
-        // };
-
-        // // TODO(DID): This needs to use DAG-CBOR encoding
-        // let op_hash = Digest::hash(
-        //     &operation
-        //         .encode_to_bytes()
-        //         .map_err(|e| TransactionError::EncodingFailed(e.to_string()))?,
-        // );
-
-        // let mut b32 = encode_base32(op_hash.as_bytes());
-        // b32.split_off(24);
-        // let did = format!("did:prism:{}", b32);
-
-        // let operation = Operation::CreateDID {
-        //     did: did.clone(),
-        //     verification_methods: self.verification_methods,
-        //     rotation_keys: self.rotation_keys,
-        //     also_known_as: self.also_known_as,
-        //     atproto_pds: self.atproto_pds,
-        //     // TODO(DID): fix
-        //     signature: "".to_string(),
-        // };
-
-        // operation.validate_basic().map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
-
-        // let unsigned_transaction = UnsignedTransaction {
-        //     id: self.did,
-        //     operation,
-        //     nonce: 0,
-        // };
-        // Ok(SigningTransactionRequestBuilder::new(
-        //     self.prism,
-        //     unsigned_transaction,
-        // ))
+    /// Derives the DID under the method of `config` instead of `did:prism:`.
+    pub fn with_did_config(mut self, config: DidConfig) -> Self {
+        self.did_config = config;
+        self
+    }
+
+    /// Returns the PLC genesis operation described by this builder, before signing.
+    pub fn unsigned_operation(&self) -> Result<UnsignedPLCOp, TransactionError> {
+        let to_did = |key: &VerifyingKey| {
+            key.to_did().map_err(|e| TransactionError::InvalidOp(e.to_string()))
+        };
+        let rotation_keys = self.rotation_keys.iter().map(to_did).collect::<Result<_, _>>()?;
+        let verification_methods = self
+            .verification_methods
+            .iter()
+            .map(|(id, key)| Ok((id.clone(), to_did(key)?)))
+            .collect::<Result<HashMap<_, _>, TransactionError>>()?;
+
+        Ok(UnsignedPLCOp::new_genesis(
+            rotation_keys,
+            verification_methods,
+            self.also_known_as.clone(),
+            self.atproto_pds.clone(),
+        ))
+    }
+
+    /// Returns the DAG-CBOR payload of the genesis operation, which has to be signed by one of
+    /// the rotation keys. Use this with [`Self::build_externally_signed`] for external signers.
+    pub fn signing_payload(&self) -> Result<Vec<u8>, TransactionError> {
+        self.unsigned_operation()?
+            .encode_to_bytes()
+            .map_err(|e| TransactionError::EncodingFailed(e.to_string()))
+    }
+
+    /// Signs the genesis operation with the given rotation key and derives the DID from it.
+    pub fn build(
+        self,
+        signing_key: &SigningKey,
+    ) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
+        let payload = self.signing_payload()?;
+        let signature = signing_key.sign(&payload).map_err(|_| TransactionError::SigningFailed)?;
+        self.build_externally_signed(SignatureBundle::new(signing_key.verifying_key(), signature))
+    }
+
+    /// Like [`Self::build`], but uses a signature over [`Self::signing_payload`] that has been
+    /// created by an external source, such as a hardware wallet.
+    pub fn build_externally_signed(
+        self,
+        signature_bundle: SignatureBundle,
+    ) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
+        if !self.rotation_keys.contains(&signature_bundle.verifying_key) {
+            return Err(TransactionError::InvalidOp(
+                "Genesis operation must be signed by a rotation key".to_string(),
+            ));
+        }
+
+        let unsigned = self.unsigned_operation()?;
+        let payload = unsigned
+            .encode_to_bytes()
+            .map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+        signature_bundle.verify(&payload)?;
+
+        let did = SignedPLCOp {
+            unsigned,
+            sig: signature_bundle
+                .signature
+                .to_plc_signature()
+                .map_err(|e| TransactionError::InvalidOp(e.to_string()))?,
+        }
+        .derive_did_with(&self.did_config);
+
+        let operation = Operation::CreateDID {
+            did: did.clone(),
+            verification_methods: self.verification_methods,
+            rotation_keys: self.rotation_keys,
+            also_known_as: self.also_known_as,
+            atproto_pds: self.atproto_pds,
+            signature: signature_bundle.signature,
+        };
+        operation.validate_basic().map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+
+        let unsigned_transaction = UnsignedTransaction {
+            id: did,
+            operation,
+            nonce: 0,
+            prev: None,
+        };
+        Ok(SigningTransactionRequestBuilder::new(
+            self.prism,
+            unsigned_transaction,
+        ))
     }
 }
 
@@ -230,6 +443,7 @@ where
     prism: Option<&'a P>,
     id: String,
     nonce: u64,
+    prev: Option<String>,
 }
 
 impl<'a, P> ModifyAccountRequestBuilder<'a, P>
@@ -240,7 +454,9 @@ where
         Self {
             prism,
             id: account.id().to_string(),
-            nonce: account.nonce(),
+            // taken from the same account state, so both always advance together
+            nonce: account.nonce_for_next_transaction(),
+            prev: account.head_cid().map(str::to_string),
         }
     }
 
@@ -255,6 +471,7 @@ where
             id: self.id,
             operation,
             nonce: self.nonce,
+            prev: self.prev,
         };
         Ok(SigningTransactionRequestBuilder::new(
             self.prism,
@@ -273,6 +490,43 @@ where
             id: self.id,
             operation,
             nonce: self.nonce,
+            prev: self.prev,
+        };
+        Ok(SigningTransactionRequestBuilder::new(
+            self.prism,
+            unsigned_transaction,
+        ))
+    }
+
+    /// Replaces all rotation keys of the account with `keys`, revoking every current key that is
+    /// not part of the new set, e.g. to recover from a key compromise.
+    pub fn rotate_to(
+        self,
+        keys: Vec<VerifyingKey>,
+    ) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
+        self.validate_id_and_nonce()?;
+        let operation = Operation::SetRotationKeys { keys };
+        operation.validate_basic().map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+        let unsigned_transaction = UnsignedTransaction {
+            id: self.id,
+            operation,
+            nonce: self.nonce,
+            prev: self.prev,
+        };
+        Ok(SigningTransactionRequestBuilder::new(
+            self.prism,
+            unsigned_transaction,
+        ))
+    }
+
+    /// Deactivates the account. It has to be signed by one of its rotation keys.
+    pub fn deactivate(self) -> Result<SigningTransactionRequestBuilder<'a, P>, TransactionError> {
+        self.validate_id_and_nonce()?;
+        let unsigned_transaction = UnsignedTransaction {
+            id: self.id,
+            operation: Operation::DeactivateDID {},
+            nonce: self.nonce,
+            prev: self.prev,
         };
         Ok(SigningTransactionRequestBuilder::new(
             self.prism,
@@ -314,10 +568,20 @@ where
         }
     }
 
+    /// Signs the transaction. DID creations have to be signed by one of their rotation keys,
+    /// otherwise the resulting DID could never be updated by its creator.
     pub fn sign(
         self,
         signing_key: &SigningKey,
     ) -> Result<SendingTransactionRequestBuilder<'a, P>, TransactionError> {
+        if let Operation::CreateDID { rotation_keys, .. } = &self.unsigned_transaction.operation
+            && !rotation_keys.contains(&signing_key.verifying_key())
+        {
+            return Err(TransactionError::InvalidOp(
+                "DID creation must be signed by a rotation key".to_string(),
+            ));
+        }
+
         let transaction = self.unsigned_transaction.sign(signing_key)?;
         Ok(SendingTransactionRequestBuilder::new(
             self.prism,
@@ -335,6 +599,11 @@ where
         )
     }
 
+    /// Returns the payload that has to be signed by `vk` for [`Self::with_external_signature`].
+    pub fn signing_payload(&self, vk: &VerifyingKey) -> Result<Vec<u8>, TransactionError> {
+        self.unsigned_transaction.signing_payload_for(vk)
+    }
+
     pub fn transaction(self) -> UnsignedTransaction {
         self.unsigned_transaction
     }
@@ -346,6 +615,7 @@ where
 {
     prism: Option<&'a P>,
     transaction: Transaction,
+    idempotency_key: Option<String>,
 }
 
 impl<'a, P> SendingTransactionRequestBuilder<'a, P>
@@ -353,7 +623,18 @@ where
     P: PrismApi,
 {
     pub fn new(prism: Option<&'a P>, transaction: Transaction) -> Self {
-        Self { prism, transaction }
+        Self {
+            prism,
+            transaction,
+            idempotency_key: None,
+        }
+    }
+
+    /// Sends the transaction with an idempotency key, so that sending it again with the same key
+    /// does not queue it twice. See [`PrismApi::post_transaction`].
+    pub fn with_idempotency_key(mut self, idempotency_key: String) -> Self {
+        self.idempotency_key = Some(idempotency_key);
+        self
     }
 
     pub async fn send(
@@ -363,7 +644,7 @@ where
             return Err(TransactionError::MissingSender.into());
         };
 
-        prism.post_transaction(self.transaction).await
+        prism.post_transaction(self.transaction, self.idempotency_key).await
     }
 
     pub fn transaction(self) -> Transaction {
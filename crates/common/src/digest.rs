@@ -1,9 +1,13 @@
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
 
 use prism_serde::{
+    base32::{FromBase32, ToBase32},
     base64::FromBase64,
     hex::{FromHex, ToHex},
     raw_or_hex,
@@ -38,6 +42,18 @@ impl Digest {
         Self(hasher.finalize().into())
     }
 
+    /// Hashes `items` under the given `domain` tag. Unlike [`Digest::hash_items`], the domain and
+    /// every item are prefixed with their (big-endian `u64`) length, so that different splittings
+    /// of the same bytes, or the same items hashed for another purpose, yield different digests.
+    pub fn hash_items_domain(domain: &[u8], items: &[impl AsRef<[u8]>]) -> Self {
+        let mut hasher = Sha256::new();
+        for item in std::iter::once(domain).chain(items.iter().map(AsRef::as_ref)) {
+            hasher.update((item.len() as u64).to_be_bytes());
+            hasher.update(item);
+        }
+        Self(hasher.finalize().into())
+    }
+
     pub const fn zero() -> Self {
         Self([0u8; 32])
     }
@@ -49,6 +65,23 @@ impl Digest {
     pub fn as_bytes(&self) -> &[u8; 32] {
         &self.0
     }
+
+    /// Returns the multibase (base32, `b` prefixed) representation of the digest, as used for
+    /// CIDs. The hex representation is available via [`ToHex`].
+    pub fn to_multibase(&self) -> String {
+        format!("b{}", self.to_base32())
+    }
+
+    /// Parses the multibase representation returned by [`Digest::to_multibase`].
+    pub fn from_multibase(multibase: &str) -> Result<Self> {
+        let Some(base32) = multibase.strip_prefix('b') else {
+            bail!("unsupported multibase encoding of {}", multibase);
+        };
+        let bytes = Vec::<u8>::from_base32_strict(base32)?;
+        let bytes = <[u8; 32]>::try_from(bytes.as_slice())
+            .map_err(|_| anyhow!("invalid digest length: {}", bytes.len()))?;
+        Ok(Self(bytes))
+    }
 }
 
 impl Default for Digest {
@@ -91,6 +124,19 @@ impl FromBase64 for Digest {
     }
 }
 
+/// Parses either representation of a digest: 64 hex characters or multibase.
+impl FromStr for Digest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.len() == 64 {
+            Self::from_hex(s)
+        } else {
+            Self::from_multibase(s)
+        }
+    }
+}
+
 impl Display for Digest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.to_hex())
@@ -3,10 +3,14 @@ pub mod api;
 pub mod builder;
 pub mod digest;
 pub mod operation;
+#[cfg(feature = "schemas")]
+pub mod schemas;
 pub mod transaction;
 
 #[cfg(feature = "test_utils")]
 pub mod test_transaction_builder;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
 
 #[cfg(test)]
 mod tests;
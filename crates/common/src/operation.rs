@@ -1,14 +1,22 @@
 use prism_serde::{base32::ToBase32, binary::ToBinary};
-use serde::{Deserialize, Serialize};
-use std::{self, collections::HashMap, fmt::Display};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, EnumAccess, IgnoredAny, IntoDeserializer, Unexpected, VariantAccess, Visitor},
+};
+use std::{self, borrow::Cow, collections::HashMap, fmt::Display};
 use utoipa::ToSchema;
 
-use crate::{account::Service, digest::Digest};
-use prism_keys::{Signature, VerifyingKey};
+use crate::{
+    account::{ATPROTO_PDS_SERVICE, ATPROTO_PDS_SERVICE_TYPE, RotationKeySet, Service},
+    digest::Digest,
+};
+use prism_keys::{Signature, VerificationError, VerifyingKey};
 
-use prism_errors::OperationError;
+use prism_errors::{DidError, OperationError, TransactionError};
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
+// The derived impls are only used for the known variants, see the `Deserialize` impl below
+#[serde(remote = "Self")]
 #[schema(
     title = "Operation",
     description = "State transition operation in the system"
@@ -17,11 +25,19 @@ use prism_errors::OperationError;
 /// In a blockchain analogy, this would be the full set of our transaction types.
 pub enum Operation {
     #[schema(title = "CreateAccount")]
-    /// Creates a new account with the given id and key.
+    /// Creates a new account with the given id and key for a registered service.
     CreateAccount {
         /// Unique identifier for the account
         #[schema(example = "user123@prism.xyz")]
         id: String,
+        /// Id of the service the account is created for
+        #[schema(example = "service.prism.xyz")]
+        service_id: String,
+        /// Signature of the service's challenge key over the [`service_challenge_hash`]
+        challenge: Signature,
+        /// Unix time in seconds from which on the sequencer rejects the challenge, covered by
+        /// the challenge signature
+        challenge_expires_at: u64,
         /// Public key associated with the account
         key: VerifyingKey,
     },
@@ -47,6 +63,231 @@ pub enum Operation {
         /// Public key to be revoked from the account
         key: VerifyingKey,
     },
+    #[schema(title = "RegisterService")]
+    /// Registers a new service with the given id and key. Accounts created for the service have
+    /// to meet a challenge signed by the service's challenge key.
+    RegisterService {
+        /// Unique identifier for the service
+        #[schema(example = "service.prism.xyz")]
+        id: String,
+        /// Public key associated with the service account
+        key: VerifyingKey,
+        /// Public key that signs the challenges for account creation
+        challenge_key: VerifyingKey,
+    },
+    #[schema(title = "SetRotationKeys")]
+    /// Replaces all rotation keys of an existing account, e.g. to recover from a key compromise
+    /// in a single step.
+    SetRotationKeys {
+        /// The new rotation keys, non-empty and without duplicates
+        keys: Vec<VerifyingKey>,
+    },
+    #[schema(title = "DeactivateDID")]
+    /// Deactivates an existing account, like a PLC tombstone. It has to be signed by a rotation
+    /// key, and no further operations are accepted, unless a recovery fork overrides it.
+    DeactivateDID {},
+    /// An operation of a type this node does not know, e.g. one introduced by a newer version.
+    /// It is only produced by deserialization, so that such operations can be skipped instead of
+    /// failing the decoding of the surrounding data, and is rejected when processed. It can not
+    /// be serialized.
+    #[serde(skip)]
+    Unknown {
+        /// Name of the operation variant
+        name: String,
+    },
+}
+
+/// Names of the [`Operation`] variants this node can process, in declaration order.
+const KNOWN_OPERATIONS: &[&str] = &[
+    "CreateAccount",
+    "CreateDID",
+    "AddKey",
+    "RevokeKey",
+    "RegisterService",
+    "SetRotationKeys",
+    "DeactivateDID",
+];
+
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum("Operation", KNOWN_OPERATIONS, OperationVisitor)
+    }
+}
+
+struct OperationVisitor;
+
+impl<'de> Visitor<'de> for OperationVisitor {
+    type Value = Operation;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("an operation")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Operation, A::Error> {
+        match data.variant()? {
+            (OperationTag::Known(name), variant) => {
+                // hand the variant back to the derived impl
+                Operation::deserialize(de::value::EnumAccessDeserializer::new(KnownOperation {
+                    name,
+                    variant,
+                }))
+            }
+            (OperationTag::Unknown(name), variant) => {
+                variant.newtype_variant::<IgnoredAny>()?;
+                Ok(Operation::Unknown { name })
+            }
+        }
+    }
+}
+
+/// Variant tag of a serialized [`Operation`], either a name (self-describing formats) or an
+/// index (compact formats). Unknown indices can not be skipped and are rejected.
+enum OperationTag {
+    Known(&'static str),
+    Unknown(String),
+}
+
+impl<'de> Deserialize<'de> for OperationTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_identifier(OperationTagVisitor)
+    }
+}
+
+struct OperationTagVisitor;
+
+impl Visitor<'_> for OperationTagVisitor {
+    type Value = OperationTag;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("an operation name or index")
+    }
+
+    fn visit_u64<E: de::Error>(self, index: u64) -> Result<OperationTag, E> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| KNOWN_OPERATIONS.get(index))
+            .map(|name| OperationTag::Known(name))
+            .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(index), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<OperationTag, E> {
+        Ok(
+            KNOWN_OPERATIONS.iter().find(|known| **known == name).map_or_else(
+                || OperationTag::Unknown(name.to_string()),
+                |known| OperationTag::Known(known),
+            ),
+        )
+    }
+
+    fn visit_bytes<E: de::Error>(self, name: &[u8]) -> Result<OperationTag, E> {
+        let name = std::str::from_utf8(name)
+            .map_err(|_| E::invalid_value(Unexpected::Bytes(name), &self))?;
+        self.visit_str(name)
+    }
+}
+
+/// Replays an already read known variant tag to the derived [`Operation`] deserialization.
+struct KnownOperation<V> {
+    name: &'static str,
+    variant: V,
+}
+
+impl<'de, V: VariantAccess<'de>> EnumAccess<'de> for KnownOperation<V> {
+    type Error = V::Error;
+    type Variant = V;
+
+    fn variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, V), V::Error> {
+        let tag = seed.deserialize(self.name.into_deserializer())?;
+        Ok((tag, self.variant))
+    }
+}
+
+/// Domain tag of the [`service_challenge_hash`].
+pub const SERVICE_CHALLENGE_DOMAIN: &[u8] = b"prism::service_challenge";
+
+/// Returns the hash a service signs with its challenge key to allow the creation of the account
+/// `id` with `key` until `expires_at`, see [`Operation::CreateAccount`].
+pub fn service_challenge_hash(
+    id: &str,
+    service_id: &str,
+    key: &VerifyingKey,
+    expires_at: u64,
+) -> Digest {
+    Digest::hash_items_domain(
+        SERVICE_CHALLENGE_DOMAIN,
+        &[
+            id.as_bytes(),
+            service_id.as_bytes(),
+            &key.to_bytes(),
+            &expires_at.to_be_bytes(),
+        ],
+    )
+}
+
+/// The `type` of regular PLC operations. Legacy `create` and `plc_tombstone` operations are not
+/// supported.
+pub const PLC_OPERATION_TYPE: &str = "plc_operation";
+
+/// All values [`Operation::operation_type`] returns.
+pub const OPERATION_TYPES: [&str; 8] = [
+    "create_account",
+    "create_did",
+    "add_key",
+    "revoke_key",
+    "register_service",
+    "set_rotation_keys",
+    "deactivate_did",
+    "unknown",
+];
+
+/// The DID method of the DIDs derived from genesis operations, e.g. to keep the DIDs of a testnet
+/// (`did:prismtest:`) apart from those of the main deployment (`did:prism:`). Nodes of a
+/// deployment have to agree on it, as it is part of the DIDs their trees are keyed by.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DidConfig {
+    /// Method name between `did:` and the method-specific identifier
+    pub method: Cow<'static, str>,
+}
+
+impl DidConfig {
+    /// The `did:prism:` method.
+    pub const DEFAULT: Self = Self::new("prism");
+
+    pub const fn new(method: &'static str) -> Self {
+        Self {
+            method: Cow::Borrowed(method),
+        }
+    }
+
+    /// Returns the prefix of DIDs of this method, e.g. `did:prism:`.
+    pub fn prefix(&self) -> String {
+        format!("did:{}:", self.method)
+    }
+
+    /// Returns the method-specific identifier of `did`, rejecting DIDs of other methods.
+    pub fn parse<'a>(&self, did: &'a str) -> Result<&'a str, DidError> {
+        did.strip_prefix("did:")
+            .and_then(|did| did.strip_prefix(self.method.as_ref()))
+            .and_then(|did| did.strip_prefix(':'))
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| DidError::MethodMismatch {
+                expected: self.method.to_string(),
+                did: did.to_string(),
+            })
+    }
+}
+
+impl Default for DidConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
@@ -74,7 +315,7 @@ impl UnsignedPLCOp {
         atproto_pds: String,
     ) -> Self {
         UnsignedPLCOp {
-            type_: "plc_operation".to_string(),
+            type_: PLC_OPERATION_TYPE.to_string(),
             rotation_keys,
             verification_methods,
             also_known_as,
@@ -82,6 +323,43 @@ impl UnsignedPLCOp {
             prev: None,
         }
     }
+
+    /// Checks that the operation is of a supported `type`. The type is part of the signed and
+    /// hashed payload, so an operation without it yields a different DID than other PLC tooling.
+    pub fn validate_type(&self) -> Result<(), OperationError> {
+        if self.type_ != PLC_OPERATION_TYPE {
+            return Err(OperationError::UnsupportedPlcOperationType(
+                self.type_.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the structure PLC requires of an operation: a supported `type` (see
+    /// [`Self::validate_type`]), non-empty rotation keys, rotation keys and verification methods
+    /// that are all valid `did:key`s, and services with absolute http(s) endpoints, the
+    /// [`ATPROTO_PDS_SERVICE`] being of type [`ATPROTO_PDS_SERVICE_TYPE`].
+    pub fn validate(&self) -> Result<(), OperationError> {
+        self.validate_type()?;
+
+        if self.rotation_keys.is_empty() {
+            return Err(OperationError::EmptyRotationKeys);
+        }
+        for key in self.rotation_keys.iter().chain(self.verification_methods.values()) {
+            VerifyingKey::from_did(key)
+                .map_err(|e| OperationError::InvalidDidKey(key.clone(), e.to_string()))?;
+        }
+
+        for (id, service) in &self.services {
+            if id == ATPROTO_PDS_SERVICE && service.service_type != ATPROTO_PDS_SERVICE_TYPE {
+                return Err(OperationError::InvalidPdsServiceType(
+                    service.service_type.clone(),
+                ));
+            }
+            service.validate()?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
@@ -105,17 +383,19 @@ impl TryFrom<&Operation> for SignedPLCOp {
                 signature,
                 ..
             } => {
-                // TODO(DID): dangerous unwrap, not all key types are supported
+                let to_did = |key: &VerifyingKey| {
+                    key.to_did().map_err(|e| OperationError::UnsupportedPlcKey(e.to_string()))
+                };
                 let rotation_keys =
-                    rotation_keys.iter().map(|k| k.to_did().unwrap()).collect::<Vec<_>>();
+                    rotation_keys.iter().map(to_did).collect::<Result<Vec<_>, _>>()?;
 
                 let verification_methods = verification_methods
                     .iter()
-                    .map(|(n, k)| (n.clone(), k.to_did().unwrap()))
-                    .collect::<HashMap<String, String>>();
+                    .map(|(n, k)| Ok((n.clone(), to_did(k)?)))
+                    .collect::<Result<HashMap<String, String>, OperationError>>()?;
 
                 let plc_op = UnsignedPLCOp {
-                    type_: "plc_operation".to_string(),
+                    type_: PLC_OPERATION_TYPE.to_string(),
                     rotation_keys,
                     also_known_as: also_known_as.clone(),
                     verification_methods,
@@ -128,7 +408,9 @@ impl TryFrom<&Operation> for SignedPLCOp {
 
                 Ok(SignedPLCOp {
                     unsigned: plc_op,
-                    sig: signature.to_plc_signature(),
+                    sig: signature
+                        .to_plc_signature()
+                        .map_err(|e| OperationError::UnsupportedPlcSignature(e.to_string()))?,
                 })
             }
             _ => Err(OperationError::InvalidPLCConversion),
@@ -136,28 +418,106 @@ impl TryFrom<&Operation> for SignedPLCOp {
     }
 }
 
+/// Multicodec prefix of a CIDv1 for DAG-CBOR content, hashed with sha2-256.
+const DAG_CBOR_SHA256_CID_PREFIX: [u8; 4] = [0x01, 0x71, 0x12, 0x20];
+
+/// Returns the base32 multibase encoded CIDv1 of the given DAG-CBOR encoded content.
+fn dag_cbor_cid(bytes: &[u8]) -> String {
+    let hash = Digest::hash(bytes);
+    let cid = [DAG_CBOR_SHA256_CID_PREFIX.as_slice(), hash.as_bytes()].concat();
+    format!("b{}", cid.to_base32())
+}
+
 impl SignedPLCOp {
+    /// Returns the CID of this operation as referenced by the `prev` field of its successor: a
+    /// base32 multibase encoded CIDv1 of the DAG-CBOR encoded operation.
+    pub fn cid(&self) -> Result<String, DidError> {
+        let bytes = self.encode_to_bytes().map_err(|e| DidError::EncodingFailed(e.to_string()))?;
+        Ok(dag_cbor_cid(&bytes))
+    }
+
+    /// Checks that this operation's `prev` references the operation with the given CID, e.g. for
+    /// external verifiers that walk an audit log one operation at a time. Genesis operations have
+    /// no `prev` and never match.
+    pub fn verify_prev(&self, expected_prev_cid: &str) -> Result<(), OperationError> {
+        if self.unsigned.prev.as_deref() != Some(expected_prev_cid) {
+            return Err(OperationError::PrevMismatch {
+                expected: expected_prev_cid.to_string(),
+                actual: self.unsigned.prev.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Derives the `did:prism:` DID from the hash of the DAG-CBOR encoded operation. The encoding
+    /// sorts map keys, so the DID does not depend on the iteration order of the operation's maps.
     pub fn derive_did(&self) -> String {
+        self.derive_did_with(&DidConfig::DEFAULT)
+    }
+
+    /// Like [`Self::derive_did`], but derives a DID of the method configured in `config`.
+    pub fn derive_did_with(&self, config: &DidConfig) -> String {
         let cbor_val = self.encode_to_bytes().unwrap();
         let hash = Digest::hash(cbor_val.as_slice());
 
         let b32 = hash.to_base32();
-        let derived_did = format!("did:prism:{}", &b32[0..24]);
-
-        derived_did
+        format!("{}{}", config.prefix(), &b32[0..24])
     }
 
     // TODO(DID): This is very inefficient, and "reconverts" the signature back
     // into a string in circuit. Pretty sure this can already be done at the
     // operation level instead of here.
     pub fn verify_signature(&self, vk: VerifyingKey) -> Result<(), prism_keys::CryptoError> {
-        let cbor_val = self.encode_to_bytes().unwrap();
+        let cbor_val =
+            self.encode_to_bytes().map_err(|e| VerificationError::GeneralError(e.to_string()))?;
         let hash = Digest::hash(cbor_val.as_slice());
 
-        let sig = Signature::from_plc_signature(&self.sig).unwrap();
+        let sig = Signature::from_plc_signature(&self.sig)?;
 
         vk.verify_signature(hash, &sig)
     }
+
+    /// Serializes the operation to JSON in the format plc.directory uses, e.g. in
+    /// `/{did}/log`. Keys are emitted in the same order as the DAG-CBOR encoding
+    /// (sorted by length, then bytewise), and `prev` is always present.
+    pub fn to_plc_json(&self) -> anyhow::Result<String> {
+        let value = serde_json::to_value(self)?;
+        let mut json = String::new();
+        write_canonical_json(&value, &mut json)?;
+        Ok(json)
+    }
+}
+
+fn write_canonical_json(value: &serde_json::Value, out: &mut String) -> anyhow::Result<()> {
+    match value {
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out)?;
+            }
+            out.push(']');
+        }
+        serde_json::Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_canonical_json(item, out)?;
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&serde_json::to_string(scalar)?),
+    }
+    Ok(())
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
@@ -177,30 +537,141 @@ impl SignatureBundle {
             signature,
         }
     }
+
+    /// Verifies that the bundled signature was made over `message` by the bundled key.
+    pub fn verify(&self, message: &[u8]) -> Result<(), TransactionError> {
+        self.verifying_key
+            .verify_signature(message, &self.signature)
+            .map_err(|e| TransactionError::InvalidOp(e.to_string()))
+    }
 }
 
 impl Operation {
+    /// Returns the CID of the operation. For DID operations this is the CID of the PLC operation
+    /// (see [`SignedPLCOp::cid`]), for all others the CID of the DAG-CBOR encoded operation.
+    pub fn cid(&self) -> Result<String, DidError> {
+        match self {
+            Operation::CreateDID { .. } => SignedPLCOp::try_from(self)
+                .map_err(|e| DidError::EncodingFailed(e.to_string()))?
+                .cid(),
+            _ => {
+                let bytes =
+                    self.encode_to_bytes().map_err(|e| DidError::EncodingFailed(e.to_string()))?;
+                Ok(dag_cbor_cid(&bytes))
+            }
+        }
+    }
+
+    /// Returns a stable, machine-readable tag of the operation variant, used as log field and
+    /// metric label.
+    pub const fn operation_type(&self) -> &'static str {
+        match self {
+            Operation::CreateAccount { .. } => "create_account",
+            Operation::CreateDID { .. } => "create_did",
+            Operation::AddKey { .. } => "add_key",
+            Operation::RevokeKey { .. } => "revoke_key",
+            Operation::RegisterService { .. } => "register_service",
+            Operation::SetRotationKeys { .. } => "set_rotation_keys",
+            Operation::DeactivateDID {} => "deactivate_did",
+            Operation::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// Checks that the service challenge of an [`Operation::CreateAccount`] has not expired at
+    /// `now` (Unix time in seconds). Other operations carry no challenge and always pass.
+    pub const fn verify_challenge_expiry(&self, now: u64) -> Result<(), OperationError> {
+        if let Operation::CreateAccount {
+            challenge_expires_at,
+            ..
+        } = self
+            && now >= *challenge_expires_at
+        {
+            return Err(OperationError::ChallengeExpired {
+                expires_at: *challenge_expires_at,
+                now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the id of the account an operation creates, i.e. the account or service id, or the
+    /// DID. Operations on existing accounts carry no id.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Operation::CreateAccount { id, .. } | Operation::RegisterService { id, .. } => Some(id),
+            Operation::CreateDID { did, .. } => Some(did),
+            Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::SetRotationKeys { .. }
+            | Operation::DeactivateDID {}
+            | Operation::Unknown { .. } => None,
+        }
+    }
+
     pub fn get_public_key(&self) -> Option<&VerifyingKey> {
         match self {
             Operation::RevokeKey { key }
             | Operation::AddKey { key }
-            | Operation::CreateAccount { key, .. } => Some(key),
-            Operation::CreateDID { .. } => None,
+            | Operation::CreateAccount { key, .. }
+            | Operation::RegisterService { key, .. } => Some(key),
+            Operation::CreateDID { .. }
+            | Operation::SetRotationKeys { .. }
+            | Operation::DeactivateDID {}
+            | Operation::Unknown { .. } => None,
         }
     }
 
+    /// Returns the keys an operation adds, revokes or sets, e.g. to maintain a key-to-DID index,
+    /// without duplicates. Verification methods of a DID come before its rotation keys, ordered
+    /// by their id; a service's key comes before its challenge key.
+    pub fn affected_keys(&self) -> Vec<&VerifyingKey> {
+        let keys: Vec<&VerifyingKey> = match self {
+            Operation::CreateAccount { key, .. }
+            | Operation::AddKey { key }
+            | Operation::RevokeKey { key } => vec![key],
+            Operation::RegisterService {
+                key, challenge_key, ..
+            } => vec![key, challenge_key],
+            Operation::CreateDID {
+                verification_methods,
+                rotation_keys,
+                ..
+            } => {
+                let mut methods: Vec<_> = verification_methods.iter().collect();
+                methods.sort_by_key(|(id, _)| *id);
+                methods.into_iter().map(|(_, key)| key).chain(rotation_keys).collect()
+            }
+            Operation::SetRotationKeys { keys } => keys.iter().collect(),
+            Operation::DeactivateDID {} | Operation::Unknown { .. } => Vec::new(),
+        };
+
+        let mut unique = Vec::with_capacity(keys.len());
+        for key in keys {
+            if !unique.contains(&key) {
+                unique.push(key);
+            }
+        }
+        unique
+    }
+
     pub fn validate_basic(&self) -> Result<(), OperationError> {
         match &self {
-            Operation::CreateAccount { id, .. } => {
+            Operation::CreateAccount { id, service_id, .. } => {
                 if id.is_empty() {
                     return Err(OperationError::EmptyAccountId);
                 }
 
+                if service_id.is_empty() {
+                    return Err(OperationError::EmptyServiceIdForAccount);
+                }
+
                 Ok(())
             }
             Operation::CreateDID {
                 verification_methods,
                 rotation_keys,
+                atproto_pds,
+                signature,
                 ..
             } => {
                 // TODO(DID): Obviously placeholder validations, but they refer to the
@@ -212,10 +683,40 @@ impl Operation {
                 if rotation_keys.is_empty() {
                     return Err(OperationError::EmptyAccountId);
                 }
+                RotationKeySet::try_from(rotation_keys.clone())?;
+
+                // Wallet keys have no did:key representation, so they can not be part of a
+                // PLC operation.
+                for key in rotation_keys.iter().chain(verification_methods.values()) {
+                    key.to_did().map_err(|e| OperationError::UnsupportedPlcKey(e.to_string()))?;
+                }
 
+                // Empty signatures can not be decoded, but the algorithm has to be checked
+                // before the operation is re-encoded for PLC verification.
+                signature
+                    .to_plc_signature()
+                    .map_err(|e| OperationError::UnsupportedPlcSignature(e.to_string()))?;
+
+                Service::new_pds(atproto_pds.clone()).validate()
+            }
+            Operation::RegisterService { id, .. } => {
+                if id.is_empty() {
+                    return Err(OperationError::EmptyServiceId);
+                }
+
+                Ok(())
+            }
+            Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::DeactivateDID {} => Ok(()),
+            Operation::SetRotationKeys { keys } => {
+                if keys.is_empty() {
+                    return Err(OperationError::EmptyRotationKeys);
+                }
+                RotationKeySet::try_from(keys.clone())?;
                 Ok(())
             }
-            Operation::AddKey { .. } | Operation::RevokeKey { .. } => Ok(()),
+            Operation::Unknown { name } => Err(OperationError::UnknownOperation(name.clone())),
         }
     }
 }
@@ -0,0 +1,50 @@
+use utoipa::OpenApi;
+
+use crate::{
+    operation::Operation,
+    transaction::{SignedPlcTransaction, Transaction},
+};
+
+#[derive(OpenApi)]
+#[openapi(components(schemas(Operation, Transaction, SignedPlcTransaction)))]
+struct OperationSchemas;
+
+/// Returns the OpenAPI component schemas of [`Operation`], [`Transaction`] and
+/// [`SignedPlcTransaction`] (including the types they reference) as JSON, for generating clients
+/// in other languages.
+pub fn export_openapi_components() -> String {
+    let components = OperationSchemas::openapi().components.unwrap_or_default();
+    serde_json::to_string_pretty(&components).expect("component schemas serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_export_contains_create_did_schema() {
+        let components: Value = serde_json::from_str(&export_openapi_components()).unwrap();
+        let schemas = &components["schemas"];
+        assert!(schemas["Transaction"].is_object());
+        assert!(schemas["SignedPlcTransaction"].is_object());
+
+        let variants = schemas["Operation"]["oneOf"].as_array().unwrap();
+        let create_did = variants
+            .iter()
+            .find(|variant| variant["title"] == "CreateDID")
+            .expect("CreateDID variant is exported");
+        let fields = &create_did["properties"]["CreateDID"]["properties"];
+        for field in [
+            "did",
+            "verification_methods",
+            "rotation_keys",
+            "also_known_as",
+            "atproto_pds",
+            "signature",
+        ] {
+            assert!(fields[field].is_object(), "missing field {field}");
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use prism_keys::SigningKey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+use crate::{account::Account, transaction::Transaction};
+
+/// Builds a signed DID creation with `signing_key` as its only rotation key.
+pub fn create_did_tx(signing_key: &SigningKey) -> Transaction {
+    Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(signing_key)
+        .unwrap()
+        .sign(signing_key)
+        .unwrap()
+        .transaction()
+}
+
+/// Answers a single HTTP request with `status` and the JSON `body`. The handle resolves to the
+/// received request.
+pub async fn serve_once(status: &'static str, body: String) -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        loop {
+            let read = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&request);
+            let complete = text.split_once("\r\n\r\n").is_some_and(|(head, content)| {
+                let content_length = head
+                    .lines()
+                    .find_map(|line| {
+                        line.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|length| length.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or_default();
+                content.len() >= content_length
+            });
+            if complete || read == 0 {
+                break;
+            }
+        }
+        let response = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(request).unwrap()
+    });
+    (url, handle)
+}
@@ -1,15 +1,33 @@
-// use prism_keys::SigningKey;
-
 use std::collections::HashMap;
 
-use base64::{Engine as _, alphabet, engine::general_purpose};
-
-use prism_keys::{CryptoAlgorithm, Signature};
+use prism_errors::{AccountError, DidError, OperationError, TransactionError};
+use prism_keys::{CryptoAlgorithm, Signature, SigningKey, VerifyingKey};
+use prism_serde::{
+    base32::ToBase32,
+    binary::{FromBinary, ToBinary},
+    hex::{FromHex, ToHex},
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    account::Service,
-    operation::{SignedPLCOp, UnsignedPLCOp},
-    transaction::{SignedPlcTransaction, Transaction},
+    account::{Account, DEFAULT_PLC_RECOVERY_WINDOW, OperationHandler, RotationKeySet, Service},
+    api::{
+        DidMethod, DidResolver, PendingTransaction, PlcDirectory, PrismApi, PrismApiError,
+        noop::{NoopPendingTransaction, NoopPrismApi, NoopTimer},
+        types::{
+            AccountResponse, CommitmentResponse, DidDocument, DidResolutionResult,
+            HashedMerkleProof, VerificationMethodType,
+        },
+    },
+    digest::Digest,
+    operation::{
+        DidConfig, OPERATION_TYPES, Operation, SignatureBundle, SignedPLCOp, UnsignedPLCOp,
+    },
+    test_utils::create_did_tx,
+    transaction::{
+        SignedPlcTransaction, TRANSACTION_BATCH_BLOB_VERSION, TRANSACTION_BLOB_VERSION,
+        Transaction, UnsignedPlcTransaction, UnsignedTransaction, verify_did_transaction,
+    },
 };
 
 #[test]
@@ -43,18 +61,105 @@ fn test_did_creation() {
     assert_eq!(did, "did:prism:3l3bnfketdgiqyfxjju4pfda".to_string());
 }
 
+#[test]
+fn test_derive_did_under_custom_method() {
+    let config = DidConfig::new("prismtest");
+    let signing_key = SigningKey::new_secp256k1();
+    let transaction = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .with_did_config(config.clone())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let Operation::CreateDID { did, .. } = &transaction.operation else {
+        panic!("Expected a CreateDID operation");
+    };
+    assert!(did.starts_with("did:prismtest:"));
+    assert_eq!(&transaction.id, did);
+
+    // both derivation paths agree, and only the method differs from the default DID
+    let genesis = SignedPLCOp::try_from(&transaction.operation).unwrap();
+    assert_eq!(&genesis.derive_did_with(&config), did);
+    let default_did = genesis.derive_did();
+    assert_eq!(
+        config.parse(did).unwrap(),
+        DidConfig::DEFAULT.parse(&default_did).unwrap()
+    );
+
+    // DIDs of another method are rejected
+    assert!(matches!(
+        config.parse(&default_did),
+        Err(DidError::MethodMismatch { .. })
+    ));
+    assert!(DidConfig::DEFAULT.parse(did).is_err());
+    assert!(config.parse("did:prismtestnet:abc").is_err());
+    assert!(config.parse("did:prismtest:").is_err());
+
+    // the method can be configured in node config files
+    let configured: DidConfig = serde_json::from_str(r#"{"method":"prismtest"}"#).unwrap();
+    assert_eq!(configured, config);
+    assert_eq!(
+        serde_json::from_str::<DidConfig>("{}").unwrap(),
+        DidConfig::DEFAULT
+    );
+
+    let mut account = Account::default();
+    account.process_transaction(&transaction).unwrap();
+    assert_eq!(account.id(), did);
+}
+
+/// Directory of `{ source, operation, expected_did }` JSON fixtures of signed genesis operations
+/// with a `did:plc:` DID that was not computed by this crate. `source` records where both were
+/// taken from. Operations captured from plc.directory (the first entry of `/{did}/log/audit`) can
+/// be added with the captured DID as `expected_did`.
+const PLC_DID_FIXTURES_DIR: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/fixtures/plc_dids");
+
+#[derive(Deserialize)]
+struct PlcDidFixture {
+    source: String,
+    operation: SignedPLCOp,
+    expected_did: String,
+}
+
+#[test]
+fn test_derive_did_matches_plc_fixtures() {
+    let mut paths = std::fs::read_dir(PLC_DID_FIXTURES_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(
+        !paths.is_empty(),
+        "missing fixtures in {}",
+        PLC_DID_FIXTURES_DIR
+    );
+
+    for path in paths {
+        let fixture: PlcDidFixture =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            fixture.operation.derive_did_with(&DidConfig::new("plc")),
+            fixture.expected_did,
+            "{} ({})",
+            path.display(),
+            fixture.source
+        );
+    }
+}
+
 #[test]
 fn plc_signature_verification() {
     let signature =
         "C_EJ4mrlmfouuXepfMy2ZJkwTUGYyTy9TuL-EThzyYV9SwLenDeJaTOhYUFnJ-C4pxKOrghNjWueNQpEUAIbbw"
             .to_string();
-    let sig_bytes =
-        general_purpose::GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::NO_PAD)
-            .decode(&signature)
-            .unwrap();
-    let parsed_signature =
-        Signature::from_algorithm_and_bytes(CryptoAlgorithm::Secp256k1, &sig_bytes).unwrap();
-    let reparsed_signature = parsed_signature.to_plc_signature();
+    let parsed_signature = Signature::from_plc_signature(&signature).unwrap();
+    let reparsed_signature = parsed_signature.to_plc_signature().unwrap();
     assert_eq!(signature, reparsed_signature);
 
     let key_str = "did:key:zQ3shYxgqcVTCgB5z21jid9vfJy1GkFUySPMzLQDPUtdN5qPe";
@@ -87,7 +192,7 @@ fn plc_signature_verification() {
     let did = signed.derive_did();
     assert_eq!(did, "did:prism:moipkdqlz5x3qjmdqjwa6zsk");
 
-    let tx: Transaction = SignedPlcTransaction {
+    let plc_tx = SignedPlcTransaction {
         did: did.clone(),
         operation: SignedPLCOp {
             unsigned: UnsignedPLCOp::new_genesis(
@@ -101,144 +206,3034 @@ fn plc_signature_verification() {
         nonce: 0,
         signature: reparsed_signature,
         vk: key_str.to_string(),
+    };
+    verify_did_transaction(&plc_tx).unwrap();
+
+    let tx: Transaction = plc_tx.try_into().unwrap();
+    tx.verify_cbor_signature().unwrap();
+}
+
+#[test]
+fn test_create_did_from_plc_op() {
+    // genesis operation signed by the atproto SDK, see `plc_signature_verification`
+    let captured = SignedPLCOp {
+        unsigned: UnsignedPLCOp::new_genesis(
+            vec![
+                "did:key:zQ3shcmbGVVFBmW8kM1ffcrmPDFB8u4YFxWH7gemf6SpsGNzL".to_string(),
+                "did:key:zQ3shYxgqcVTCgB5z21jid9vfJy1GkFUySPMzLQDPUtdN5qPe".to_string(),
+            ],
+            HashMap::from([(
+                "atproto".to_string(),
+                "did:key:zQ3shnpPSGRJGPFVNYZSrrz4CHjqW5eFau6gsGXFrdmsJ4axx".to_string(),
+            )]),
+            vec!["at://mod-authority.test".to_string()],
+            "http://localhost:49793".to_string(),
+        ),
+        sig:
+            "yFKwHXi1q5if7hhyYjp5boUx-IrgEDzslnQl-fwwGNsr0Mrbcgkkgjxo_H8v6SW7i2IgVNUPmM-VStgTPIu0mQ"
+                .to_string(),
+    };
+
+    let unsigned_tx =
+        Account::builder().create_did_from_plc_op(captured.clone()).unwrap().transaction();
+    assert_eq!(unsigned_tx.id, "did:prism:moipkdqlz5x3qjmdqjwa6zsk");
+    assert_eq!(
+        SignedPLCOp::try_from(&unsigned_tx.operation).unwrap(),
+        captured
+    );
+
+    let mut unparsable = captured.clone();
+    unparsable.unsigned.rotation_keys[0] = "did:key:zNotAKey".to_string();
+    assert!(matches!(
+        Account::builder().create_did_from_plc_op(unparsable),
+        Err(TransactionError::InvalidOp(_))
+    ));
+
+    let mut tampered = captured;
+    tampered.unsigned.also_known_as = vec!["at://mallory.test".to_string()];
+    assert!(Account::builder().create_did_from_plc_op(tampered).is_err());
+}
+
+#[test]
+fn test_create_did_from_plc_op_is_submittable() {
+    let signing_key = SigningKey::new_secp256k1();
+    let did_builder = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_also_known_as("at://alice.test".to_string())
+        .with_atproto_pds("http://localhost:2583".to_string());
+    let sig = signing_key.sign(did_builder.signing_payload().unwrap()).unwrap();
+    let op = SignedPLCOp {
+        unsigned: did_builder.unsigned_operation().unwrap(),
+        sig: sig.to_plc_signature().unwrap(),
+    };
+
+    let tx = Account::builder()
+        .create_did_from_plc_op(op.clone())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+    assert_eq!(account.id(), op.derive_did());
+}
+
+#[test]
+fn test_verify_did_transaction_rejects_tampered_transaction() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+
+    let mut plc_tx: SignedPlcTransaction = tx.try_into().unwrap();
+    verify_did_transaction(&plc_tx).unwrap();
+
+    plc_tx.nonce = 1;
+    assert!(verify_did_transaction(&plc_tx).is_err());
+}
+
+#[test]
+fn test_process_register_service_transactions() {
+    let service_key = SigningKey::new_ed25519();
+    let challenge_key = SigningKey::new_ed25519();
+
+    // happy path - should succeed
+    let create_tx = Account::builder()
+        .register_service()
+        .with_id("Service".to_string())
+        .with_key(service_key.verifying_key())
+        .requiring_signed_challenge(challenge_key.verifying_key())
+        .unwrap()
+        .sign(&service_key)
+        .unwrap()
+        .transaction();
+
+    assert!(Account::default().process_transaction(&create_tx).is_ok());
+
+    // should fail with invalid nonce
+    let mut unsigned_invalid_tx = Account::builder()
+        .register_service()
+        .with_id("Service".to_string())
+        .with_key(service_key.verifying_key())
+        .requiring_signed_challenge(challenge_key.verifying_key())
+        .unwrap()
+        .transaction();
+
+    unsigned_invalid_tx.nonce = 1; // has to be 0 for RegisterService
+    let invalid_tx = unsigned_invalid_tx.sign(&service_key).unwrap();
+
+    assert!(Account::default().process_transaction(&invalid_tx).is_err());
+
+    // should fail when operation id and transaction id are not equal
+    let mut unsigned_invalid_tx = Account::builder()
+        .register_service()
+        .with_id("Service".to_string())
+        .with_key(service_key.verifying_key())
+        .requiring_signed_challenge(challenge_key.verifying_key())
+        .unwrap()
+        .transaction();
+
+    if let Operation::RegisterService { id, .. } = &mut unsigned_invalid_tx.operation {
+        *id = "DifferentService".to_string();
+    } else {
+        panic!("Unexpected operation type");
+    }
+    let invalid_tx = unsigned_invalid_tx.sign(&service_key).unwrap();
+
+    assert!(Account::default().process_transaction(&invalid_tx).is_err());
+
+    // should fail when transaction is signed with an invalid key
+    let invalid_key = SigningKey::new_ed25519();
+    let invalid_tx = Account::builder()
+        .register_service()
+        .with_id("Service".to_string())
+        .with_key(service_key.verifying_key())
+        .requiring_signed_challenge(challenge_key.verifying_key())
+        .unwrap()
+        .sign(&invalid_key)
+        .unwrap()
+        .transaction();
+
+    assert!(Account::default().process_transaction(&invalid_tx).is_err());
+}
+
+#[test]
+fn test_process_create_account_transactions() {
+    let service_key = SigningKey::new_ed25519();
+    let acc_key = SigningKey::new_ed25519();
+
+    // happy path - should succeed
+    let create_tx = Account::builder()
+        .create_account()
+        .with_id("Acc".to_string())
+        .for_service_with_id("Service".to_string())
+        .with_key(acc_key.verifying_key())
+        .with_challenge_expiry(u64::MAX)
+        .meeting_signed_challenge(&service_key)
+        .unwrap()
+        .sign(&acc_key)
+        .unwrap()
+        .transaction();
+
+    assert!(Account::default().process_transaction(&create_tx).is_ok());
+
+    // should fail with invalid nonce
+    let mut unsigned_invalid_tx = Account::builder()
+        .create_account()
+        .with_id("Acc".to_string())
+        .for_service_with_id("Service".to_string())
+        .with_key(acc_key.verifying_key())
+        .with_challenge_expiry(u64::MAX)
+        .meeting_signed_challenge(&service_key)
+        .unwrap()
+        .transaction();
+
+    unsigned_invalid_tx.nonce = 1; // has to be 0 for CreateAccount
+    let invalid_tx = unsigned_invalid_tx.sign(&acc_key).unwrap();
+
+    assert!(Account::default().process_transaction(&invalid_tx).is_err());
+
+    // should fail when operation id and transaction id are not equal
+    let mut unsigned_invalid_tx = Account::builder()
+        .create_account()
+        .with_id("Acc".to_string())
+        .for_service_with_id("Service".to_string())
+        .with_key(acc_key.verifying_key())
+        .with_challenge_expiry(u64::MAX)
+        .meeting_signed_challenge(&service_key)
+        .unwrap()
+        .transaction();
+
+    if let Operation::CreateAccount { id, .. } = &mut unsigned_invalid_tx.operation {
+        *id = "DifferentAcc".to_string();
+    } else {
+        panic!("Unexpected operation type");
+    }
+    let invalid_tx = unsigned_invalid_tx.sign(&acc_key).unwrap();
+
+    assert!(Account::default().process_transaction(&invalid_tx).is_err());
+
+    // should fail when transaction is signed with an invalid key
+    let invalid_key = SigningKey::new_ed25519();
+    let invalid_tx = Account::builder()
+        .create_account()
+        .with_id("Acc".to_string())
+        .for_service_with_id("Service".to_string())
+        .with_key(acc_key.verifying_key())
+        .with_challenge_expiry(u64::MAX)
+        .meeting_signed_challenge(&service_key)
+        .unwrap()
+        .sign(&invalid_key)
+        .unwrap()
+        .transaction();
+
+    assert!(Account::default().process_transaction(&invalid_tx).is_err());
+}
+
+#[test]
+fn test_verify_service_challenge() {
+    let service_key = SigningKey::new_ed25519();
+    let challenge_key = SigningKey::new_ed25519();
+    let acc_key = SigningKey::new_ed25519();
+
+    let register_tx = Account::builder()
+        .register_service()
+        .with_id("Service".to_string())
+        .with_key(service_key.verifying_key())
+        .requiring_signed_challenge(challenge_key.verifying_key())
+        .unwrap()
+        .sign(&service_key)
+        .unwrap()
+        .transaction();
+    let mut service = Account::default();
+    service.process_transaction(&register_tx).unwrap();
+    assert_eq!(
+        service.challenge_key(),
+        Some(&challenge_key.verifying_key())
+    );
+
+    let create_account = |service_id: &str, signing_key: &SigningKey| {
+        Account::builder()
+            .create_account()
+            .with_id("Acc".to_string())
+            .for_service_with_id(service_id.to_string())
+            .with_key(acc_key.verifying_key())
+            .with_challenge_expiry(u64::MAX)
+            .meeting_signed_challenge(signing_key)
+            .unwrap()
+            .transaction()
+            .operation
+    };
+
+    // happy path - should succeed
+    service.verify_service_challenge(&create_account("Service", &challenge_key)).unwrap();
+
+    // should fail when the challenge is not signed by the challenge key
+    assert!(matches!(
+        service.verify_service_challenge(&create_account("Service", &service_key)),
+        Err(AccountError::InvalidChallenge(_))
+    ));
+
+    // should fail when the account is created for another service
+    assert!(matches!(
+        service.verify_service_challenge(&create_account("OtherService", &challenge_key)),
+        Err(AccountError::AccountIdError(_, _))
+    ));
+
+    // should fail for accounts that are not a service
+    let create_tx = Account::builder()
+        .create_account()
+        .with_id("Acc".to_string())
+        .for_service_with_id("Service".to_string())
+        .with_key(acc_key.verifying_key())
+        .with_challenge_expiry(u64::MAX)
+        .meeting_signed_challenge(&challenge_key)
+        .unwrap()
+        .sign(&acc_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+    let mut not_a_service_op = create_tx.operation.clone();
+    if let Operation::CreateAccount { service_id, .. } = &mut not_a_service_op {
+        *service_id = "Acc".to_string();
+    }
+    assert!(matches!(
+        account.verify_service_challenge(&not_a_service_op),
+        Err(AccountError::NotAService(_))
+    ));
+}
+
+#[test]
+fn test_verify_challenge_expiry() {
+    let challenge_key = SigningKey::new_ed25519();
+    let acc_key = SigningKey::new_ed25519();
+    let expires_at = 1_700_000_600;
+
+    let create_account = |expires_at: u64| {
+        Account::builder()
+            .create_account()
+            .with_id("Acc".to_string())
+            .for_service_with_id("Service".to_string())
+            .with_key(acc_key.verifying_key())
+            .with_challenge_expiry(expires_at)
+            .meeting_signed_challenge(&challenge_key)
+            .unwrap()
+            .transaction()
+            .operation
+    };
+    let operation = create_account(expires_at);
+
+    // a fresh challenge passes
+    operation.verify_challenge_expiry(expires_at - 1).unwrap();
+
+    // a stale challenge is rejected
+    assert!(matches!(
+        operation.verify_challenge_expiry(expires_at),
+        Err(OperationError::ChallengeExpired {
+            expires_at: 1_700_000_600,
+            now: 1_700_000_600
+        })
+    ));
+
+    // the expiry is covered by the challenge signature, so it can not be extended afterwards
+    let mut extended = operation.clone();
+    if let Operation::CreateAccount {
+        challenge_expires_at,
+        ..
+    } = &mut extended
+    {
+        *challenge_expires_at = u64::MAX;
+    }
+    let register_tx = Account::builder()
+        .register_service()
+        .with_id("Service".to_string())
+        .with_key(acc_key.verifying_key())
+        .requiring_signed_challenge(challenge_key.verifying_key())
+        .unwrap()
+        .sign(&acc_key)
+        .unwrap()
+        .transaction();
+    let mut service = Account::default();
+    service.process_transaction(&register_tx).unwrap();
+    service.verify_service_challenge(&operation).unwrap();
+    assert!(matches!(
+        service.verify_service_challenge(&extended),
+        Err(AccountError::InvalidChallenge(_))
+    ));
+
+    // the expiry has to be set before signing the challenge
+    assert!(matches!(
+        Account::builder()
+            .create_account()
+            .with_id("Acc".to_string())
+            .for_service_with_id("Service".to_string())
+            .with_key(acc_key.verifying_key())
+            .meeting_signed_challenge(&challenge_key),
+        Err(TransactionError::MissingChallengeExpiry)
+    ));
+
+    // operations without a challenge never expire
+    Operation::AddKey {
+        key: acc_key.verifying_key(),
     }
-    .try_into()
+    .verify_challenge_expiry(u64::MAX)
     .unwrap();
+}
+
+#[test]
+fn test_externally_signed_did_creation() {
+    // stands in for a hardware wallet that never hands out its signing key
+    let external_key = SigningKey::new_secp256k1();
+    let external_vk = external_key.verifying_key();
+    let atproto_key = SigningKey::new_secp256k1().verifying_key();
+
+    let did_builder = Account::builder()
+        .create_did()
+        .with_verification_method("atproto".to_string(), atproto_key)
+        .with_rotation_keys(vec![external_vk.clone()])
+        .with_also_known_as("at://alice.test".to_string())
+        .with_atproto_pds("http://localhost:2583".to_string());
+
+    let op_payload = did_builder.signing_payload().unwrap();
+    let op_signature = external_key.sign(&op_payload).unwrap();
+    let signing_builder = did_builder
+        .build_externally_signed(SignatureBundle::new(external_vk.clone(), op_signature))
+        .unwrap();
+
+    let tx_payload = signing_builder.signing_payload(&external_vk).unwrap();
+    let tx_signature = external_key.sign(&tx_payload).unwrap();
+    let tx = signing_builder
+        .with_external_signature(SignatureBundle::new(external_vk.clone(), tx_signature))
+        .transaction();
+
+    assert!(tx.id.starts_with("did:prism:"));
+    tx.verify_cbor_signature().unwrap();
+
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+    assert_eq!(account.id(), tx.id);
+}
+
+#[test]
+fn test_externally_signed_did_creation_rejects_foreign_signature() {
+    let rotation_key = SigningKey::new_secp256k1();
+    let other_key = SigningKey::new_secp256k1();
+
+    let did_builder = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![rotation_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string());
+
+    let op_payload = did_builder.signing_payload().unwrap();
+    let signature = other_key.sign(&op_payload).unwrap();
+    let result = did_builder.build_externally_signed(SignatureBundle::new(
+        rotation_key.verifying_key(),
+        signature,
+    ));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_did_creation_signed_by_non_rotation_key_fails() {
+    let rotation_key = SigningKey::new_secp256k1();
+    let other_key = SigningKey::new_secp256k1();
+
+    let result = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![rotation_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&other_key);
+    assert!(matches!(result, Err(TransactionError::InvalidOp(_))));
 
+    let result = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![rotation_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&rotation_key)
+        .unwrap()
+        .sign(&other_key);
+    assert!(matches!(result, Err(TransactionError::InvalidOp(_))));
+}
+
+#[test]
+fn test_did_signing_payload_matches_verification() {
+    let signing_key = SigningKey::new_secp256k1();
+    let unsigned_tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .transaction();
+
+    let vk = signing_key.verifying_key();
+    let payload = unsigned_tx.did_signing_payload(&vk).unwrap();
+    let Operation::CreateDID { did, .. } = &unsigned_tx.operation else {
+        panic!("expected a CreateDID operation");
+    };
+    let plc_tx = UnsignedPlcTransaction {
+        did: did.clone(),
+        operation: SignedPLCOp::try_from(&unsigned_tx.operation).unwrap(),
+        nonce: unsigned_tx.nonce,
+        vk: vk.to_did().unwrap(),
+    };
+    assert_eq!(payload, plc_tx.encode_to_bytes().unwrap());
+
+    let signature = signing_key.sign(&payload).unwrap();
+    let tx =
+        unsigned_tx.externally_signed(SignatureBundle::new(signing_key.verifying_key(), signature));
     tx.verify_cbor_signature().unwrap();
 }
 
-// use crate::{account::Account, operation::Operation};
-// #[test]
-// fn test_process_register_service_transactions() {
-//     let service_key = SigningKey::new_ed25519();
-//     let challenge_key = SigningKey::new_ed25519();
-
-//     // happy path - should succeed
-//     let create_tx = Account::builder()
-//         .register_service()
-//         .with_id("Service".to_string())
-//         .with_key(service_key.verifying_key())
-//         .requiring_signed_challenge(challenge_key.verifying_key())
-//         .unwrap()
-//         .sign(&service_key)
-//         .unwrap()
-//         .transaction();
-
-//     assert!(Account::default().process_transaction(&create_tx).is_ok());
-
-//     // should fail with invalid nonce
-//     let mut unsigned_invalid_tx = Account::builder()
-//         .register_service()
-//         .with_id("Service".to_string())
-//         .with_key(service_key.verifying_key())
-//         .requiring_signed_challenge(challenge_key.verifying_key())
-//         .unwrap()
-//         .transaction();
-
-//     unsigned_invalid_tx.nonce = 1; // has to be 0 for RegisterService
-//     let invalid_tx = unsigned_invalid_tx.sign(&service_key).unwrap();
-
-//     assert!(Account::default().process_transaction(&invalid_tx).is_err());
-
-//     // should fail when operation id and transaction id are not equal
-//     let mut unsigned_invalid_tx = Account::builder()
-//         .register_service()
-//         .with_id("Service".to_string())
-//         .with_key(service_key.verifying_key())
-//         .requiring_signed_challenge(challenge_key.verifying_key())
-//         .unwrap()
-//         .transaction();
-
-//     if let Operation::RegisterService { id, .. } = &mut unsigned_invalid_tx.operation {
-//         *id = "DifferentService".to_string();
-//     } else {
-//         panic!("Unexpected operation type");
-//     }
-//     let invalid_tx = unsigned_invalid_tx.sign(&service_key).unwrap();
-
-//     assert!(Account::default().process_transaction(&invalid_tx).is_err());
-
-//     // should fail when transaction is signed with an invalid key
-//     let invalid_key = SigningKey::new_ed25519();
-//     let invalid_tx = Account::builder()
-//         .register_service()
-//         .with_id("Service".to_string())
-//         .with_key(service_key.verifying_key())
-//         .requiring_signed_challenge(challenge_key.verifying_key())
-//         .unwrap()
-//         .sign(&invalid_key)
-//         .unwrap()
-//         .transaction();
-
-//     assert!(Account::default().process_transaction(&invalid_tx).is_err());
-// }
-
-// #[test]
-// fn test_process_create_account_transactions() {
-//     let service_key = SigningKey::new_ed25519();
-//     let acc_key = SigningKey::new_ed25519();
-
-//     // happy path - should succeed
-//     let create_tx = Account::builder()
-//         .create_account()
-//         .with_id("Acc".to_string())
-//         .for_service_with_id("Service".to_string())
-//         .with_key(acc_key.verifying_key())
-//         .meeting_signed_challenge(&service_key)
-//         .unwrap()
-//         .sign(&acc_key)
-//         .unwrap()
-//         .transaction();
-
-//     assert!(Account::default().process_transaction(&create_tx).is_ok());
-
-//     // should fail with invalid nonce
-//     let mut unsigned_invalid_tx = Account::builder()
-//         .create_account()
-//         .with_id("Acc".to_string())
-//         .for_service_with_id("Service".to_string())
-//         .with_key(acc_key.verifying_key())
-//         .meeting_signed_challenge(&service_key)
-//         .unwrap()
-//         .transaction();
-
-//     unsigned_invalid_tx.nonce = 1; // has to be 0 for CreateAccount
-//     let invalid_tx = unsigned_invalid_tx.sign(&acc_key).unwrap();
-
-//     assert!(Account::default().process_transaction(&invalid_tx).is_err());
-
-//     // should fail when operation id and transaction id are not equal
-//     let mut unsigned_invalid_tx = Account::builder()
-//         .create_account()
-//         .with_id("Acc".to_string())
-//         .for_service_with_id("Service".to_string())
-//         .with_key(acc_key.verifying_key())
-//         .meeting_signed_challenge(&service_key)
-//         .unwrap()
-//         .transaction();
-
-//     if let Operation::CreateAccount { id, .. } = &mut unsigned_invalid_tx.operation {
-//         *id = "DifferentAcc".to_string();
-//     } else {
-//         panic!("Unexpected operation type");
-//     }
-//     let invalid_tx = unsigned_invalid_tx.sign(&acc_key).unwrap();
-
-//     assert!(Account::default().process_transaction(&invalid_tx).is_err());
-
-//     // should fail when transaction is signed with an invalid key
-//     let invalid_key = SigningKey::new_ed25519();
-//     let invalid_tx = Account::builder()
-//         .create_account()
-//         .with_id("Acc".to_string())
-//         .for_service_with_id("Service".to_string())
-//         .with_key(acc_key.verifying_key())
-//         .meeting_signed_challenge(&service_key)
-//         .unwrap()
-//         .sign(&invalid_key)
-//         .unwrap()
-//         .transaction();
-
-//     assert!(Account::default().process_transaction(&invalid_tx).is_err());
-// }
+#[test]
+fn test_did_signing_payload_rejects_non_did_operations() {
+    let unsigned_tx = UnsignedTransaction {
+        id: "user@prism.xyz".to_string(),
+        operation: Operation::AddKey {
+            key: SigningKey::new_ed25519().verifying_key(),
+        },
+        nonce: 1,
+        prev: None,
+    };
+
+    let vk = SigningKey::new_ed25519().verifying_key();
+    assert!(unsigned_tx.did_signing_payload(&vk).is_err());
+}
+
+#[test]
+fn test_create_did_signature_validation() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    assert!(tx.operation.validate_basic().is_ok());
+
+    // a zero-length signature is rejected when decoding the operation
+    let mut json = serde_json::to_value(&tx.operation).unwrap();
+    let bytes = &mut json["CreateDID"]["signature"]["bytes"];
+    assert!(bytes.as_str().is_some_and(|bytes| !bytes.is_empty()));
+    *bytes = "".into();
+    assert!(serde_json::from_value::<Operation>(json).is_err());
+
+    let mut ed25519_signed = tx.operation;
+    let Operation::CreateDID { signature, .. } = &mut ed25519_signed else {
+        panic!("expected a CreateDID operation");
+    };
+    *signature = SigningKey::new_ed25519().sign(b"operation").unwrap();
+    assert!(matches!(
+        ed25519_signed.validate_basic(),
+        Err(OperationError::UnsupportedPlcSignature(_))
+    ));
+}
+
+#[test]
+fn test_verify_cbor_signature_many_transactions() {
+    const TRANSACTIONS: usize = 100;
+
+    let txs: Vec<Transaction> = (0..TRANSACTIONS)
+        .map(|_| {
+            let signing_key = SigningKey::new_secp256k1();
+            create_did_tx(&signing_key)
+        })
+        .collect();
+
+    let verified = txs.iter().filter(|tx| tx.verify_cbor_signature().is_ok()).count();
+    assert_eq!(verified, TRANSACTIONS);
+
+    // each signature only verifies under the key of its own transaction
+    let mut swapped = txs[0].clone();
+    swapped.vk = txs[1].vk.clone();
+    assert!(matches!(
+        swapped.verify_cbor_signature(),
+        Err(TransactionError::InvalidSignature(_))
+    ));
+}
+
+#[test]
+fn test_verify_cbor_signature_rejects_malformed_operations() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+
+    // Ed25519 operation signatures have no PLC representation
+    let mut unrepresentable = tx.clone();
+    let Operation::CreateDID { signature, .. } = &mut unrepresentable.operation else {
+        panic!("expected a CreateDID operation");
+    };
+    *signature = SigningKey::new_ed25519().sign(b"operation").unwrap();
+    assert!(matches!(
+        unrepresentable.verify_cbor_signature(),
+        Err(TransactionError::InvalidOp(_))
+    ));
+
+    let mut unknown = tx;
+    unknown.operation = Operation::Unknown {
+        name: "RotateHandle".to_string(),
+    };
+    assert!(matches!(
+        unknown.verify_cbor_signature(),
+        Err(TransactionError::InvalidOp(_))
+    ));
+}
+
+#[test]
+fn test_plc_json_matches_plc_directory_format() {
+    // operation as returned by plc.directory's `/{did}/log` endpoint
+    let fixture = concat!(
+        r#"{"sig":"F0_AgX0tghOjtCMPsMGxHP-8JL11GiR8ikgf68XofQAa1vgEZvEe9VBWFko8isAjT5pkcZOf0GBPAq1cujBNHw","#,
+        r#""prev":null,"#,
+        r#""type":"plc_operation","#,
+        r#""services":{"atproto_pds":{"type":"AtprotoPersonalDataServer","endpoint":"http://localhost:65473"}},"#,
+        r#""alsoKnownAs":["at://mod-authority.test"],"#,
+        r#""rotationKeys":["did:key:zQ3shYUkjUJWLxshqnPbDb1bwc2wMeRy65yQ7TdeotDRoA54G","did:key:zQ3shZUHZuc3Z74mmMhZG2FS87oLqdiHBJyrv5vSc4tychPZF"],"#,
+        r#""verificationMethods":{"atproto":"did:key:zQ3shRqHqyhXgCjBmLyPhwN6ENSLMYCVUS7684MKrmVunRF8H"}}"#,
+    );
+
+    let signed = SignedPLCOp {
+        unsigned: UnsignedPLCOp {
+            type_: "plc_operation".to_string(),
+            services: HashMap::from([(
+                "atproto_pds".to_string(),
+                Service::new_pds("http://localhost:65473".to_string()),
+            )]),
+            verification_methods: HashMap::from([(
+                "atproto".to_string(),
+                "did:key:zQ3shRqHqyhXgCjBmLyPhwN6ENSLMYCVUS7684MKrmVunRF8H".to_string(),
+            )]),
+            rotation_keys: vec![
+                "did:key:zQ3shYUkjUJWLxshqnPbDb1bwc2wMeRy65yQ7TdeotDRoA54G".to_string(),
+                "did:key:zQ3shZUHZuc3Z74mmMhZG2FS87oLqdiHBJyrv5vSc4tychPZF".to_string(),
+            ],
+            also_known_as: vec!["at://mod-authority.test".to_string()],
+            prev: None,
+        },
+        sig:
+            "F0_AgX0tghOjtCMPsMGxHP-8JL11GiR8ikgf68XofQAa1vgEZvEe9VBWFko8isAjT5pkcZOf0GBPAq1cujBNHw"
+                .to_string(),
+    };
+
+    assert_eq!(signed.to_plc_json().unwrap(), fixture);
+    assert_eq!(
+        serde_json::from_str::<SignedPLCOp>(fixture).unwrap(),
+        signed
+    );
+}
+
+fn signed_plc_op(unsigned: UnsignedPLCOp, signing_key: &SigningKey) -> SignedPLCOp {
+    let signature = signing_key.sign(unsigned.encode_to_bytes().unwrap()).unwrap();
+    SignedPLCOp {
+        unsigned,
+        sig: signature.to_plc_signature().unwrap(),
+    }
+}
+
+/// Creates a log of a genesis operation followed by a rotation from the first to the second key.
+fn plc_log_with_key_rotation(
+    genesis_key: &SigningKey,
+    rotated_key: &SigningKey,
+) -> Vec<SignedPLCOp> {
+    let atproto_key = SigningKey::new_secp256k1().verifying_key().to_did().unwrap();
+    let genesis = signed_plc_op(
+        UnsignedPLCOp::new_genesis(
+            vec![genesis_key.verifying_key().to_did().unwrap()],
+            HashMap::from([("atproto".to_string(), atproto_key.clone())]),
+            vec!["at://alice.test".to_string()],
+            "https://pds.alice.test".to_string(),
+        ),
+        genesis_key,
+    );
+
+    let mut rotation = genesis.unsigned.clone();
+    rotation.rotation_keys = vec![rotated_key.verifying_key().to_did().unwrap()];
+    rotation.prev = Some(genesis.cid().unwrap());
+    let rotation = signed_plc_op(rotation, genesis_key);
+
+    vec![genesis, rotation]
+}
+
+#[test]
+fn test_account_from_plc_log() {
+    let genesis_key = SigningKey::new_secp256k1();
+    let rotated_key = SigningKey::new_secp256k1();
+    let log = plc_log_with_key_rotation(&genesis_key, &rotated_key);
+
+    let account = Account::from_plc_log(&log).unwrap();
+
+    assert_eq!(account.id(), log[0].derive_did());
+    assert_eq!(account.nonce(), 2);
+    assert_eq!(account.valid_keys(), &[rotated_key.verifying_key()]);
+    assert_eq!(account.also_known_as(), &["at://alice.test".to_string()]);
+    assert_eq!(account.head_cid(), Some(log[1].cid().unwrap().as_str()));
+}
+
+#[test]
+fn test_account_verify_did_binding() {
+    let genesis_key = SigningKey::new_secp256k1();
+    let log = plc_log_with_key_rotation(&genesis_key, &SigningKey::new_secp256k1());
+    let account = Account::from_plc_log(&log).unwrap();
+    account.verify_did_binding(&log[0]).unwrap();
+
+    // a genesis operation altered after the DID was derived from it
+    let mut corrupted = log[0].clone();
+    corrupted.unsigned.also_known_as = vec!["at://mallory.test".to_string()];
+    let corrupted = signed_plc_op(corrupted.unsigned, &genesis_key);
+    let Err(DidError::UnboundDid { did, derived }) = account.verify_did_binding(&corrupted) else {
+        panic!("expected an unbound DID");
+    };
+    assert_eq!(did, account.id());
+    assert_eq!(derived, corrupted.derive_did());
+
+    // later operations do not derive the DID
+    assert!(account.verify_did_binding(&log[1]).is_err());
+
+    let testnet = DidConfig::new("prismtest");
+    let tx = Account::builder()
+        .create_did()
+        .with_did_config(testnet.clone())
+        .with_rotation_keys(vec![genesis_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&genesis_key)
+        .unwrap()
+        .sign(&genesis_key)
+        .unwrap()
+        .transaction();
+    let mut testnet_account = Account::default();
+    testnet_account.process_transaction(&tx).unwrap();
+    let genesis = SignedPLCOp::try_from(&tx.operation).unwrap();
+    testnet_account.verify_did_binding_with(&genesis, &testnet).unwrap();
+    assert!(testnet_account.verify_did_binding(&genesis).is_err());
+}
+
+#[test]
+fn test_account_compact_log() {
+    let signing_key = SigningKey::new_secp256k1();
+    let mut log = vec![signed_plc_op(
+        UnsignedPLCOp::new_genesis(
+            vec![signing_key.verifying_key().to_did().unwrap()],
+            HashMap::new(),
+            vec!["at://alice.test".to_string()],
+            "https://pds.alice.test".to_string(),
+        ),
+        &signing_key,
+    )];
+    for i in 1..=5 {
+        let mut update = log[i - 1].unsigned.clone();
+        update.also_known_as = vec![format!("at://alice{}.test", i)];
+        update.prev = Some(log[i - 1].cid().unwrap());
+        log.push(signed_plc_op(update, &signing_key));
+    }
+
+    let compacted = Account::compact_log_with_recovery_window(&log, 2).unwrap();
+
+    assert_eq!(compacted.did(), log[0].derive_did());
+    assert_eq!(compacted.did(), compacted.account.id());
+    assert_eq!(compacted.account, Account::from_plc_log(&log).unwrap());
+    assert_eq!(compacted.genesis, log[0]);
+    // the operations a recovery could fork from are kept, the ones before are pruned
+    assert_eq!(compacted.recent, log[3..]);
+    assert_eq!(compacted.head(), &log[5]);
+
+    // short logs are kept as a whole
+    let compacted = Account::compact_log(&log).unwrap();
+    assert_eq!(compacted.recent, log[1..]);
+    let compacted = Account::compact_log(&log[..1]).unwrap();
+    assert!(compacted.recent.is_empty());
+    assert_eq!(compacted.head(), &log[0]);
+}
+
+#[test]
+fn test_account_compact_log_drops_nullified_operations() {
+    let recovery = SigningKey::new_secp256k1();
+    let signer = SigningKey::new_secp256k1();
+    let log = plc_log_with_fork(&recovery, &signer, &recovery);
+
+    let compacted = Account::compact_log(&log).unwrap();
+
+    assert_eq!(compacted.did(), log[0].derive_did());
+    assert_eq!(compacted.account, Account::from_plc_log(&log).unwrap());
+    // the takeover was overridden by the fork
+    assert_eq!(compacted.recent, vec![log[2].clone()]);
+}
+
+#[test]
+fn test_rotation_key_set() {
+    let recovery_key = SigningKey::new_secp256k1().verifying_key();
+    let signing_key = SigningKey::new_ed25519().verifying_key();
+
+    let mut keys =
+        RotationKeySet::try_from(vec![recovery_key.clone(), signing_key.clone()]).unwrap();
+    assert_eq!(keys.authority_index(&recovery_key), Some(0));
+    assert_eq!(keys.authority_index(&signing_key), Some(1));
+    assert_eq!(
+        keys.authority_index(&SigningKey::new_secp256k1().verifying_key()),
+        None
+    );
+
+    assert!(matches!(
+        RotationKeySet::try_from(vec![
+            recovery_key.clone(),
+            signing_key.clone(),
+            recovery_key.clone()
+        ]),
+        Err(OperationError::DuplicateRotationKey(2, _))
+    ));
+    assert!(matches!(
+        keys.push(signing_key.clone()),
+        Err(OperationError::DuplicateRotationKey(2, _))
+    ));
+
+    assert!(keys.remove(&recovery_key));
+    assert_eq!(keys.authority_index(&signing_key), Some(0));
+    assert_eq!(Vec::from(keys), vec![signing_key.clone()]);
+
+    // stored accounts may hold duplicates from before they were rejected
+    let duplicated = serde_json::to_value(vec![signing_key.clone(), signing_key]).unwrap();
+    let keys = serde_json::from_value::<RotationKeySet>(duplicated.clone()).unwrap();
+    assert_eq!(serde_json::to_value(keys).unwrap(), duplicated);
+}
+
+#[test]
+fn test_create_did_rejects_duplicate_rotation_keys() {
+    let signing_key = SigningKey::new_secp256k1();
+    let key = signing_key.verifying_key();
+    assert!(
+        Account::builder()
+            .create_did()
+            .with_rotation_keys(vec![key.clone(), key.clone()])
+            .with_atproto_pds("http://localhost:2583".to_string())
+            .build(&signing_key)
+            .is_err()
+    );
+
+    let operation = Operation::CreateDID {
+        did: "did:prism:alice".to_string(),
+        verification_methods: HashMap::new(),
+        rotation_keys: vec![key.clone(), key],
+        also_known_as: vec!["at://alice.test".to_string()],
+        atproto_pds: "http://localhost:2583".to_string(),
+        signature: signing_key.sign(b"genesis").unwrap(),
+    };
+    assert!(matches!(
+        operation.validate_basic(),
+        Err(OperationError::DuplicateRotationKey(1, _))
+    ));
+
+    let mut account = Account::default();
+    assert!(operation.validate(&account).is_err());
+    // applying fails before any field is assigned
+    assert!(operation.apply(&mut account).is_err());
+    assert_eq!(account, Account::default());
+}
+
+#[test]
+fn test_create_did_rejects_wallet_keys() {
+    let signing_key = SigningKey::new_secp256k1();
+    let wallet_key = SigningKey::new_eip191().verifying_key();
+    assert!(
+        Account::builder()
+            .create_did()
+            .with_rotation_keys(vec![signing_key.verifying_key(), wallet_key.clone()])
+            .with_atproto_pds("http://localhost:2583".to_string())
+            .build(&signing_key)
+            .is_err()
+    );
+
+    let operation = Operation::CreateDID {
+        did: "did:prism:alice".to_string(),
+        verification_methods: HashMap::from([("atproto".to_string(), wallet_key)]),
+        rotation_keys: vec![signing_key.verifying_key()],
+        also_known_as: vec!["at://alice.test".to_string()],
+        atproto_pds: "http://localhost:2583".to_string(),
+        signature: signing_key.sign(b"genesis").unwrap(),
+    };
+    assert!(matches!(
+        operation.validate_basic(),
+        Err(OperationError::UnsupportedPlcKey(_))
+    ));
+    assert!(matches!(
+        SignedPLCOp::try_from(&operation),
+        Err(OperationError::UnsupportedPlcKey(_))
+    ));
+}
+
+#[test]
+fn test_transaction_validate_consistency() {
+    let signing_key = SigningKey::new_secp256k1();
+    let transaction = create_did_tx(&signing_key);
+    transaction.validate_consistency().unwrap();
+
+    let mut inconsistent = transaction.clone();
+    inconsistent.id = "did:prism:someoneelse".to_string();
+    assert!(matches!(
+        inconsistent.validate_consistency(),
+        Err(TransactionError::IdMismatch(id, did))
+            if id == "did:prism:someoneelse" && did == transaction.id
+    ));
+
+    // operations on existing accounts carry no id to compare against
+    let mut account = Account::default();
+    account.process_transaction(&transaction).unwrap();
+    let add_key = account
+        .modify()
+        .add_key(SigningKey::new_ed25519().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    add_key.validate_consistency().unwrap();
+}
+
+#[test]
+fn test_verify_prev() {
+    let genesis_key = SigningKey::new_secp256k1();
+    let rotated_key = SigningKey::new_secp256k1();
+    let log = plc_log_with_key_rotation(&genesis_key, &rotated_key);
+    let genesis_cid = log[0].cid().unwrap();
+
+    assert!(log[1].verify_prev(&genesis_cid).is_ok());
+
+    let rotation_cid = log[1].cid().unwrap();
+    assert!(matches!(
+        log[1].verify_prev(&rotation_cid),
+        Err(OperationError::PrevMismatch { expected, actual })
+            if expected == rotation_cid && actual == Some(genesis_cid.clone())
+    ));
+
+    assert!(matches!(
+        log[0].verify_prev(&genesis_cid),
+        Err(OperationError::PrevMismatch { actual: None, .. })
+    ));
+}
+
+#[test]
+fn test_account_from_plc_log_rejects_broken_chain() {
+    let genesis_key = SigningKey::new_secp256k1();
+    let rotated_key = SigningKey::new_secp256k1();
+    let mut log = plc_log_with_key_rotation(&genesis_key, &rotated_key);
+
+    let mut unchained = log[1].unsigned.clone();
+    unchained.prev = None;
+    log[1] = signed_plc_op(unchained, &genesis_key);
+
+    assert!(matches!(
+        Account::from_plc_log(&log),
+        Err(DidError::BrokenChain { index: 1, .. })
+    ));
+}
+
+#[test]
+fn test_account_from_plc_log_rejects_invalid_signature() {
+    let genesis_key = SigningKey::new_secp256k1();
+    let rotated_key = SigningKey::new_secp256k1();
+    let mut log = plc_log_with_key_rotation(&genesis_key, &rotated_key);
+
+    // the rotation has to be signed by a key of the previous operation
+    log[1] = signed_plc_op(log[1].unsigned.clone(), &rotated_key);
+
+    assert!(matches!(
+        Account::from_plc_log(&log),
+        Err(DidError::InvalidSignature(1))
+    ));
+    assert!(matches!(
+        Account::from_plc_log(&[]),
+        Err(DidError::EmptyLog)
+    ));
+}
+
+/// Creates a log where the lower-authority `signer` rotates `recovery` out, followed by a fork
+/// off the genesis operation that is signed by `fork_signer`.
+fn plc_log_with_fork(
+    recovery: &SigningKey,
+    signer: &SigningKey,
+    fork_signer: &SigningKey,
+) -> Vec<SignedPLCOp> {
+    let genesis = signed_plc_op(
+        UnsignedPLCOp::new_genesis(
+            vec![
+                recovery.verifying_key().to_did().unwrap(),
+                signer.verifying_key().to_did().unwrap(),
+            ],
+            HashMap::new(),
+            vec!["at://alice.test".to_string()],
+            "https://pds.alice.test".to_string(),
+        ),
+        recovery,
+    );
+
+    let mut takeover = genesis.unsigned.clone();
+    takeover.rotation_keys = vec![signer.verifying_key().to_did().unwrap()];
+    takeover.prev = Some(genesis.cid().unwrap());
+    let takeover = signed_plc_op(takeover, signer);
+
+    let mut fork = genesis.unsigned.clone();
+    fork.also_known_as = vec!["at://recovered.alice.test".to_string()];
+    fork.prev = Some(genesis.cid().unwrap());
+    let fork = signed_plc_op(fork, fork_signer);
+
+    vec![genesis, takeover, fork]
+}
+
+#[test]
+fn test_account_from_plc_log_recovery_override() {
+    let recovery = SigningKey::new_secp256k1();
+    let signer = SigningKey::new_secp256k1();
+    let log = plc_log_with_fork(&recovery, &signer, &recovery);
+
+    let account = Account::from_plc_log(&log).unwrap();
+
+    assert_eq!(
+        account.valid_keys(),
+        &[recovery.verifying_key(), signer.verifying_key()]
+    );
+    assert_eq!(
+        account.also_known_as(),
+        &["at://recovered.alice.test".to_string()]
+    );
+}
+
+#[test]
+fn test_account_from_plc_log_rejects_invalid_recovery() {
+    let recovery = SigningKey::new_secp256k1();
+    let signer = SigningKey::new_secp256k1();
+
+    // the overridden operation was signed by the same key
+    let log = plc_log_with_fork(&recovery, &signer, &signer);
+    assert!(matches!(
+        Account::from_plc_log(&log),
+        Err(DidError::InsufficientAuthority(2))
+    ));
+
+    let log = plc_log_with_fork(&recovery, &signer, &recovery);
+    assert!(matches!(
+        Account::from_plc_log_with_recovery_window(&log, 0),
+        Err(DidError::RecoveryWindowExpired(2))
+    ));
+}
+
+/// Creates an account whose rotation keys are `recovery` and `signer`, after which the
+/// lower-authority `signer` rotates `recovery` out. Returns the account and its state after the
+/// genesis operation.
+fn account_with_takeover(recovery: &SigningKey, signer: &SigningKey) -> (Account, Account) {
+    let create_tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![recovery.verifying_key(), signer.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(recovery)
+        .unwrap()
+        .sign(recovery)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+    let genesis = account.clone();
+
+    let takeover_tx = account
+        .modify()
+        .rotate_to(vec![signer.verifying_key()])
+        .unwrap()
+        .sign(signer)
+        .unwrap()
+        .transaction();
+    account.process_transaction(&takeover_tx).unwrap();
+    (account, genesis)
+}
+
+/// Creates a transaction forking `account` off `prev`, which adds a new rotation key.
+fn fork_tx(account: &Account, prev: &str, signer: &SigningKey) -> Transaction {
+    UnsignedTransaction {
+        id: account.id().to_string(),
+        operation: Operation::AddKey {
+            key: SigningKey::new_secp256k1().verifying_key(),
+        },
+        nonce: account.nonce(),
+        prev: Some(prev.to_string()),
+    }
+    .sign(signer)
+    .unwrap()
+}
+
+#[test]
+fn test_process_transaction_recovery_override() {
+    let recovery = SigningKey::new_secp256k1();
+    let signer = SigningKey::new_secp256k1();
+    let (mut account, genesis) = account_with_takeover(&recovery, &signer);
+    assert_eq!(account.valid_keys(), &[signer.verifying_key()]);
+    let takeover = account.clone();
+    let takeover_cid = account.head_cid().unwrap().to_string();
+
+    let fork = fork_tx(&account, genesis.head_cid().unwrap(), &recovery);
+    assert_eq!(account.fork_prev(&fork), genesis.head_cid());
+    account.process_transaction_with_fork_base(&fork, Some(&genesis)).unwrap();
+
+    let Operation::AddKey { key } = &fork.operation else {
+        unreachable!()
+    };
+    assert_eq!(
+        account.valid_keys(),
+        &[
+            recovery.verifying_key(),
+            signer.verifying_key(),
+            key.clone()
+        ]
+    );
+    assert_eq!(
+        account.head_cid(),
+        Some(fork.operation.cid().unwrap().as_str())
+    );
+    assert_eq!(account.nonce(), 3);
+
+    // the nullified takeover can not be forked from anymore
+    let err = account
+        .clone()
+        .process_transaction_with_fork_base(
+            &fork_tx(&account, &takeover_cid, &recovery),
+            Some(&takeover),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::PrevError(..))
+    ));
+}
+
+#[test]
+fn test_process_transaction_rejects_invalid_recovery() {
+    let recovery = SigningKey::new_secp256k1();
+    let signer = SigningKey::new_secp256k1();
+    let (account, genesis) = account_with_takeover(&recovery, &signer);
+    let genesis_cid = genesis.head_cid().unwrap();
+
+    // the overridden operation was signed by the same key
+    let err = account
+        .clone()
+        .process_transaction_with_fork_base(
+            &fork_tx(&account, genesis_cid, &signer),
+            Some(&genesis),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::InsufficientAuthority(_))
+    ));
+
+    // the state to fork from has to be provided, and match the one the account committed to
+    let fork = fork_tx(&account, genesis_cid, &recovery);
+    for fork_base in [None, Some(&account)] {
+        let err = account.clone().process_transaction_with_fork_base(&fork, fork_base).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AccountError>(),
+            Some(AccountError::UnknownForkBase(_))
+        ));
+    }
+
+    // the genesis operation is pushed out of the recovery window
+    let mut account = account;
+    for _ in 0..DEFAULT_PLC_RECOVERY_WINDOW {
+        let add_key_tx = account
+            .modify()
+            .add_key(SigningKey::new_secp256k1().verifying_key())
+            .unwrap()
+            .sign(&signer)
+            .unwrap()
+            .transaction();
+        account.process_transaction(&add_key_tx).unwrap();
+    }
+    let err = account
+        .clone()
+        .process_transaction_with_fork_base(
+            &fork_tx(&account, genesis_cid, &recovery),
+            Some(&genesis),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::PrevError(..))
+    ));
+}
+
+#[test]
+fn test_account_verification_method_helpers() {
+    let signing_key = SigningKey::new_secp256k1();
+    let atproto_key = SigningKey::new_secp256k1().verifying_key();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_method("atproto".to_string(), atproto_key.clone())
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    assert_eq!(account.atproto_key(), Some(&atproto_key));
+    assert_eq!(account.verification_method("atproto"), Some(&atproto_key));
+    assert_eq!(account.verification_method("#atproto"), None);
+    assert_eq!(Account::default().atproto_key(), None);
+}
+
+#[test]
+fn test_account_handles() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .with_also_known_as("at://alice.test".to_string())
+        .with_also_known_as("https://alice.example.com".to_string())
+        .with_also_known_as("at://alice.example.com".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    assert_eq!(account.primary_handle(), Some("alice.test"));
+    assert_eq!(
+        account.handles().collect::<Vec<_>>(),
+        vec!["alice.test", "alice.example.com"]
+    );
+}
+
+#[test]
+fn test_account_without_handles() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    assert_eq!(account.primary_handle(), None);
+    assert_eq!(account.handles().count(), 0);
+}
+
+#[test]
+fn test_account_head_cid_advances() {
+    let signing_key = SigningKey::new_secp256k1();
+    let create_tx = create_did_tx(&signing_key);
+
+    let mut account = Account::default();
+    assert_eq!(account.head_cid(), None);
+    account.process_transaction(&create_tx).unwrap();
+
+    let genesis_cid = SignedPLCOp::try_from(&create_tx.operation).unwrap().cid().unwrap();
+    assert_eq!(account.head_cid(), Some(genesis_cid.as_str()));
+
+    let add_key_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    account.process_transaction(&add_key_tx).unwrap();
+
+    // CIDv1, dag-cbor, sha2-256 multihash, base32 multibase
+    let hash = Digest::hash(add_key_tx.operation.encode_to_bytes().unwrap());
+    let expected_cid = format!(
+        "b{}",
+        [[0x01, 0x71, 0x12, 0x20].as_slice(), hash.as_bytes()].concat().to_base32()
+    );
+    assert_ne!(account.head_cid(), Some(genesis_cid.as_str()));
+    assert_eq!(account.head_cid(), Some(expected_cid.as_str()));
+}
+
+#[test]
+fn test_transaction_id_must_match_account() {
+    let signing_key = SigningKey::new_secp256k1();
+    let create_tx = create_did_tx(&signing_key);
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+
+    let unsigned_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .transaction();
+
+    let mut spoofed_tx = unsigned_tx.clone();
+    spoofed_tx.id = "did:prism:someoneelse".to_string();
+    let spoofed_tx = spoofed_tx.sign(&signing_key).unwrap();
+    let err = account.clone().process_transaction(&spoofed_tx).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::TransactionIdError(..))
+    ));
+
+    let tx = unsigned_tx.sign(&signing_key).unwrap();
+    account.process_transaction(&tx).unwrap();
+}
+
+#[test]
+fn test_api_error_conversions_preserve_message() {
+    fn propagate<E>(err: E) -> Result<(), PrismApiError>
+    where
+        PrismApiError: From<E>,
+    {
+        Err(err)?
+    }
+
+    let operation_err = OperationError::DuplicateRotationKey(1, "did:key:z".to_string());
+    let err = propagate(operation_err.clone()).unwrap_err();
+    assert!(matches!(
+        &err,
+        PrismApiError::Transaction(TransactionError::InvalidOp(msg))
+            if msg == &operation_err.to_string()
+    ));
+    assert!(err.to_string().contains(&operation_err.to_string()));
+
+    let account_err = AccountError::NonceError(2, 1);
+    let err = propagate(account_err.clone()).unwrap_err();
+    assert!(matches!(
+        &err,
+        PrismApiError::Account(AccountError::NonceError(2, 1))
+    ));
+    assert!(err.to_string().contains(&account_err.to_string()));
+}
+
+#[test]
+fn test_api_error_from_serde_errors() {
+    fn parse_json(body: &str) -> Result<SignedPLCOp, PrismApiError> {
+        Ok(serde_json::from_str(body)?)
+    }
+    fn parse_cbor(body: &[u8]) -> Result<SignedPLCOp, PrismApiError> {
+        Ok(SignedPLCOp::decode_from_bytes(body)?)
+    }
+
+    let json_err = serde_json::from_str::<SignedPLCOp>(r#"{"sig": "#).unwrap_err();
+    assert!(matches!(
+        parse_json(r#"{"sig": "#),
+        Err(PrismApiError::SerdeFailed(msg)) if msg == json_err.to_string()
+    ));
+    assert!(matches!(
+        parse_cbor(&[0xff, 0x00]),
+        Err(PrismApiError::SerdeFailed(_))
+    ));
+}
+
+#[test]
+fn test_api_error_is_not_found() {
+    let http = |status| PrismApiError::Http {
+        status,
+        body: String::new(),
+    };
+
+    assert!(http(404).is_not_found());
+    assert!(!http(400).is_not_found());
+    assert!(!http(500).is_not_found());
+    assert!(!PrismApiError::RequestFailed("404".to_string()).is_not_found());
+    assert!(!PrismApiError::Unknown.is_not_found());
+}
+
+/// Implements only the required [`PrismApi`] methods, to exercise the provided ones.
+struct RequiredMethodsPrismApi;
+
+#[async_trait::async_trait]
+impl PrismApi for RequiredMethodsPrismApi {
+    type Timer = NoopTimer;
+
+    async fn get_account(&self, _: &str) -> Result<AccountResponse, PrismApiError> {
+        Err(PrismApiError::Unknown)
+    }
+
+    async fn get_commitment(&self) -> Result<CommitmentResponse, PrismApiError> {
+        Err(PrismApiError::Unknown)
+    }
+
+    async fn post_transaction(
+        &self,
+        _: Transaction,
+        _: Option<String>,
+    ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
+        Result::<NoopPendingTransaction, PrismApiError>::Err(PrismApiError::Unknown)
+    }
+
+    async fn get_genesis_operation(&self, _: &str) -> Result<SignedPLCOp, PrismApiError> {
+        Err(PrismApiError::Unknown)
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_handle_is_unsupported_by_default() {
+    assert!(matches!(
+        RequiredMethodsPrismApi.resolve_handle("at://alice.test").await,
+        Err(PrismApiError::Unsupported(request)) if request == "resolve_handle"
+    ));
+}
+
+const PLC_DID_FIXTURE: &str = "did:plc:ewvi7nxzyoun6zhxrhs64oiz";
+
+const PLC_DOCUMENT_FIXTURE: &str = r##"{
+    "@context": [
+        "https://www.w3.org/ns/did/v1",
+        "https://w3id.org/security/multikey/v1",
+        "https://w3id.org/security/suites/secp256k1-2019/v1"
+    ],
+    "id": "did:plc:ewvi7nxzyoun6zhxrhs64oiz",
+    "alsoKnownAs": ["at://atproto.com"],
+    "verificationMethod": [
+        {
+            "id": "did:plc:ewvi7nxzyoun6zhxrhs64oiz#atproto",
+            "type": "Multikey",
+            "controller": "did:plc:ewvi7nxzyoun6zhxrhs64oiz",
+            "publicKeyMultibase": "zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme"
+        }
+    ],
+    "service": [
+        {
+            "id": "#atproto_pds",
+            "type": "AtprotoPersonalDataServer",
+            "serviceEndpoint": "https://enoki.us-east.host.bsky.network"
+        }
+    ]
+}"##;
+
+/// A plc.directory `/{did}/data` response for [`PLC_DID_FIXTURE`]. Tests only compare its
+/// structure, not its values.
+const PLC_DATA_FIXTURE: &str = r#"{
+    "did": "did:plc:ewvi7nxzyoun6zhxrhs64oiz",
+    "verificationMethods": {
+        "atproto": "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme"
+    },
+    "rotationKeys": [
+        "did:key:zQ3shhCGUqDKjStzuDxPkTxN6ujddP4RkEKJJouJGRRkaLGbg",
+        "did:key:zQ3shpKnbdPx3g3CmPf5cRVTPe1HtSwVn5ish3wSnDPQCbLJK"
+    ],
+    "alsoKnownAs": ["at://atproto.com"],
+    "services": {
+        "atproto_pds": {
+            "type": "AtprotoPersonalDataServer",
+            "endpoint": "https://enoki.us-east.host.bsky.network"
+        }
+    }
+}"#;
+
+/// Serves [`PLC_DOCUMENT_FIXTURE`] and answers 404 for every other DID, like plc.directory.
+struct FixturePlcDirectory;
+
+#[async_trait::async_trait]
+impl PlcDirectory for FixturePlcDirectory {
+    async fn get_did_document(&self, did: &str) -> Result<DidDocument, PrismApiError> {
+        if did != PLC_DID_FIXTURE {
+            return Err(PrismApiError::Http {
+                status: 404,
+                body: format!("DID not registered: {}", did),
+            });
+        }
+        Ok(serde_json::from_str(PLC_DOCUMENT_FIXTURE)?)
+    }
+
+    async fn submit(&self, _: &str, _: &SignedPLCOp) -> Result<(), PrismApiError> {
+        Err(PrismApiError::InvalidTarget(
+            "the fixture directory is read-only".to_string(),
+        ))
+    }
+}
+
+#[test]
+fn test_did_method_of() {
+    assert_eq!(DidMethod::of(PLC_DID_FIXTURE).unwrap(), DidMethod::Plc);
+    assert_eq!(DidMethod::of("did:prism:abc").unwrap(), DidMethod::Prism);
+    assert!(matches!(
+        DidMethod::of("did:web:example.com"),
+        Err(PrismApiError::InvalidTarget(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_resolve_plc_did() {
+    let resolver = DidResolver::new(&NoopPrismApi, &FixturePlcDirectory);
+
+    let document = resolver.resolve(PLC_DID_FIXTURE).await.unwrap().unwrap();
+    assert_eq!(document.id, PLC_DID_FIXTURE);
+    assert_eq!(document.also_known_as, vec!["at://atproto.com".to_string()]);
+    assert_eq!(document.verification_method.len(), 1);
+    assert_eq!(
+        document.verification_method[0].public_key_multibase,
+        "zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme"
+    );
+    assert_eq!(
+        document.service[0].service_endpoint,
+        "https://enoki.us-east.host.bsky.network"
+    );
+
+    let unknown = resolver.resolve("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa").await.unwrap();
+    assert!(unknown.is_none());
+}
+
+#[tokio::test]
+async fn test_resolve_routes_prism_dids_to_node() {
+    let resolver = DidResolver::new(&NoopPrismApi, &FixturePlcDirectory);
+
+    // The noop node fails every request, so reaching it shows up as `Unknown`
+    let result = resolver.resolve("did:prism:abc").await;
+    assert!(matches!(result, Err(PrismApiError::Unknown)));
+
+    let result = resolver.resolve("did:web:example.com").await;
+    assert!(matches!(result, Err(PrismApiError::InvalidTarget(_))));
+}
+
+#[tokio::test]
+async fn test_create_did_rejects_invalid_signers() {
+    let create_did = |signing_key: SigningKey, rotation_keys: Vec<VerifyingKey>| async move {
+        NoopPrismApi
+            .create_atproto_did(
+                SigningKey::new_secp256k1().verifying_key(),
+                rotation_keys,
+                "at://alice.example.com".to_string(),
+                "http://localhost:2583".to_string(),
+                &signing_key,
+            )
+            .await
+            .map(|_| ())
+    };
+
+    // a valid request reaches the noop node, which fails every request with `Unknown`
+    let signing_key = SigningKey::new_secp256k1();
+    let result = create_did(signing_key.clone(), vec![signing_key.verifying_key()]).await;
+    assert!(matches!(result, Err(PrismApiError::Unknown)));
+
+    let result = create_did(
+        SigningKey::new_secp256k1(),
+        vec![signing_key.verifying_key()],
+    )
+    .await;
+    assert!(matches!(result, Err(PrismApiError::InvalidTarget(_))));
+
+    let ed25519_key = SigningKey::new_ed25519();
+    let result = create_did(ed25519_key.clone(), vec![ed25519_key.verifying_key()]).await;
+    assert!(matches!(result, Err(PrismApiError::InvalidTarget(_))));
+}
+
+#[test]
+fn test_genesis_plc_op_derives_account_did() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_method(
+            "atproto".to_string(),
+            SigningKey::new_secp256k1().verifying_key(),
+        )
+        .with_also_known_as("at://alice.example.com".to_string())
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let genesis = SignedPLCOp {
+        unsigned: account.to_genesis_plc_op().unwrap(),
+        sig: SignedPLCOp::try_from(&tx.operation).unwrap().sig,
+    };
+    assert_eq!(genesis.derive_did(), account.id());
+
+    let add_key_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    account.process_transaction(&add_key_tx).unwrap();
+    assert!(matches!(
+        account.to_genesis_plc_op(),
+        Err(DidError::NotGenesis(_))
+    ));
+}
+
+#[test]
+fn test_map_order_does_not_affect_did_derivation() {
+    let signing_key = SigningKey::new_secp256k1();
+    let methods: Vec<_> = (0..8)
+        .map(|i| {
+            (
+                format!("key-{i}"),
+                SigningKey::new_secp256k1().verifying_key(),
+            )
+        })
+        .collect();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_methods(methods.iter().cloned().collect())
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+
+    // the same operation, with its map populated in reverse order
+    let mut reordered = tx.operation.clone();
+    let Operation::CreateDID {
+        verification_methods,
+        ..
+    } = &mut reordered
+    else {
+        panic!("expected a CreateDID operation");
+    };
+    *verification_methods = methods.into_iter().rev().collect();
+
+    assert_eq!(reordered, tx.operation);
+    assert_eq!(
+        reordered.encode_to_bytes().unwrap(),
+        tx.operation.encode_to_bytes().unwrap()
+    );
+    let derived_did = SignedPLCOp::try_from(&reordered).unwrap().derive_did();
+    assert_eq!(derived_did, tx.id);
+}
+
+#[test]
+fn test_create_did_transaction_id_is_derived_did() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+
+    let derived_did = SignedPLCOp::try_from(&tx.operation).unwrap().derive_did();
+    assert_eq!(tx.id, derived_did);
+    assert!(matches!(&tx.operation, Operation::CreateDID { did, .. } if *did == derived_did));
+
+    let mut account = Account::default();
+    assert_eq!(account.nonce_for_next_transaction(), tx.nonce);
+    account.process_transaction(&tx).unwrap();
+
+    let add_key_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    assert_eq!(add_key_tx.nonce, account.nonce_for_next_transaction());
+    assert_eq!(add_key_tx.nonce, 1);
+}
+
+#[test]
+fn test_transaction_hash() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+
+    assert_eq!(tx.hash().unwrap(), tx.clone().hash().unwrap());
+    assert_eq!(tx.tx_hash().unwrap(), tx.hash().unwrap().to_hex());
+
+    let mut changed = tx.clone();
+    changed.nonce += 1;
+    assert_ne!(changed.hash().unwrap(), tx.hash().unwrap());
+
+    let unknown = Transaction {
+        operation: Operation::Unknown {
+            name: "Future".to_string(),
+        },
+        ..tx
+    };
+    assert!(unknown.hash().is_err());
+}
+
+#[test]
+fn test_nonce_overflow_is_rejected() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let mut value = serde_json::to_value(&account).unwrap();
+    value["nonce"] = u64::MAX.into();
+    let mut account: Account = serde_json::from_value(value).unwrap();
+
+    let add_key_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let err = account.process_transaction(&add_key_tx).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::NonceOverflow(_))
+    ));
+    assert_eq!(account.nonce(), u64::MAX);
+    assert_eq!(account.valid_keys().len(), 1);
+}
+
+#[test]
+fn test_account_merge() {
+    let signing_key = SigningKey::new_secp256k1();
+    let create_did = |signing_key: &SigningKey| {
+        let tx = create_did_tx(signing_key);
+        let mut account = Account::default();
+        account.process_transaction(&tx).unwrap();
+        account
+    };
+    let add_random_key = |account: &Account| {
+        let tx = account
+            .modify()
+            .add_key(SigningKey::new_secp256k1().verifying_key())
+            .unwrap()
+            .sign(&signing_key)
+            .unwrap()
+            .transaction();
+        let mut account = account.clone();
+        account.process_transaction(&tx).unwrap();
+        account
+    };
+
+    let genesis = create_did(&signing_key);
+    let updated = add_random_key(&genesis);
+    let diverged = add_random_key(&genesis);
+
+    // identical
+    assert_eq!(genesis.merge(&genesis).unwrap(), genesis);
+    // compatible, regardless of order
+    assert_eq!(genesis.merge(&updated).unwrap(), updated);
+    assert_eq!(updated.merge(&genesis).unwrap(), updated);
+    // conflicting
+    assert!(matches!(
+        updated.merge(&diverged),
+        Err(DidError::ConflictingState { nonce: 2, .. })
+    ));
+    let other = create_did(&SigningKey::new_secp256k1());
+    assert!(matches!(
+        genesis.merge(&other),
+        Err(DidError::DidMismatch(_, _))
+    ));
+}
+
+#[test]
+fn test_account_diff() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_method(
+            "atproto".to_string(),
+            SigningKey::new_secp256k1().verifying_key(),
+        )
+        .with_also_known_as("at://alice.test".to_string())
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut genesis = Account::default();
+    genesis.process_transaction(&tx).unwrap();
+    let edit = |edit: fn(&mut serde_json::Value)| {
+        let mut json = serde_json::to_value(&genesis).unwrap();
+        edit(&mut json);
+        serde_json::from_value::<Account>(json).unwrap()
+    };
+
+    // identical accounts
+    assert!(genesis.diff(&genesis).is_empty());
+
+    // rotation keys
+    let new_key = SigningKey::new_secp256k1().verifying_key();
+    let tx = genesis
+        .modify()
+        .add_key(new_key.clone())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut rotated = genesis.clone();
+    rotated.process_transaction(&tx).unwrap();
+    let diff = rotated.diff(&genesis);
+    assert_eq!(diff.added_rotation_keys, vec![new_key.clone()]);
+    assert!(diff.removed_rotation_keys.is_empty());
+    assert_eq!(genesis.diff(&rotated).removed_rotation_keys, vec![new_key]);
+
+    // services
+    let relocated = edit(|json| {
+        json["services"]["atproto_pds"]["endpoint"] = "https://pds.example.com".into();
+        json["services"]["atproto_labeler"] =
+            serde_json::to_value(Service::new_labeler("https://mod.example.com".to_string()))
+                .unwrap();
+    });
+    let diff = relocated.diff(&genesis);
+    assert_eq!(diff.changed_services, relocated.services().clone());
+    assert!(diff.removed_services.is_empty());
+    let diff = genesis.diff(&relocated);
+    assert_eq!(diff.removed_services, vec!["atproto_labeler".to_string()]);
+    assert_eq!(
+        diff.changed_services.keys().collect::<Vec<_>>(),
+        vec!["atproto_pds"]
+    );
+
+    // handles
+    let renamed = edit(|json| json["alsoKnownAs"] = serde_json::json!(["at://bob.test"]));
+    let diff = renamed.diff(&genesis);
+    assert_eq!(diff.added_also_known_as, vec!["at://bob.test".to_string()]);
+    assert_eq!(
+        diff.removed_also_known_as,
+        vec!["at://alice.test".to_string()]
+    );
+    assert!(diff.changed_services.is_empty());
+    assert!(diff.added_rotation_keys.is_empty());
+
+    // verification methods
+    let rekeyed = edit(|json| {
+        json["verificationMethods"].as_object_mut().unwrap().remove("atproto");
+    });
+    let diff = rekeyed.diff(&genesis);
+    assert_eq!(
+        diff.removed_verification_methods,
+        vec!["atproto".to_string()]
+    );
+    assert_eq!(
+        genesis.diff(&rekeyed).changed_verification_methods,
+        genesis.verification_methods().clone()
+    );
+}
+
+#[test]
+fn test_account_did_document_round_trip() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_method(
+            "atproto".to_string(),
+            SigningKey::new_secp256k1().verifying_key(),
+        )
+        .with_verification_method(
+            "fallback".to_string(),
+            SigningKey::new_secp256r1().verifying_key(),
+        )
+        .with_also_known_as("at://alice.example.com".to_string())
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let restored = Account::try_from(&DidDocument::from(&account)).unwrap();
+
+    assert_eq!(restored.id(), account.id());
+    assert_eq!(
+        restored.verification_methods(),
+        account.verification_methods()
+    );
+    assert_eq!(restored.services(), account.services());
+    assert_eq!(restored.also_known_as(), account.also_known_as());
+    assert_eq!(restored.nonce(), 0);
+}
+
+#[test]
+fn test_account_from_did_document() {
+    let document: DidDocument = serde_json::from_str(PLC_DOCUMENT_FIXTURE).unwrap();
+    let account = Account::try_from(&document).unwrap();
+
+    assert_eq!(account.id(), PLC_DID_FIXTURE);
+    assert_eq!(
+        account.atproto_key().unwrap().to_did().unwrap(),
+        "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme"
+    );
+    assert_eq!(
+        account.services()["atproto_pds"],
+        Service::new_pds("https://enoki.us-east.host.bsky.network".to_string())
+    );
+}
+
+#[test]
+fn test_plc_data_matches_plc_directory_format() {
+    fn keys(value: &serde_json::Value) -> Vec<&String> {
+        let mut keys: Vec<_> = value.as_object().unwrap().keys().collect();
+        keys.sort();
+        keys
+    }
+
+    let signing_key = SigningKey::new_secp256k1();
+    let atproto_key = SigningKey::new_secp256k1().verifying_key();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_method("atproto".to_string(), atproto_key.clone())
+        .with_also_known_as("at://alice.example.com".to_string())
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let data = account.to_plc_data();
+    let fixture: serde_json::Value = serde_json::from_str(PLC_DATA_FIXTURE).unwrap();
+    assert_eq!(keys(&data), keys(&fixture));
+    assert_eq!(
+        keys(&data["services"]["atproto_pds"]),
+        keys(&fixture["services"]["atproto_pds"])
+    );
+
+    assert_eq!(data["did"], account.id());
+    assert_eq!(
+        data["verificationMethods"]["atproto"],
+        atproto_key.to_did().unwrap()
+    );
+    assert_eq!(
+        data["rotationKeys"],
+        serde_json::json!([signing_key.verifying_key().to_did().unwrap()])
+    );
+    assert_eq!(
+        data["alsoKnownAs"],
+        serde_json::json!(["at://alice.example.com"])
+    );
+    assert_eq!(
+        data["services"]["atproto_pds"]["endpoint"],
+        "http://localhost:2583"
+    );
+}
+
+#[test]
+fn test_account_from_did_document_rejects_invalid_multibase() {
+    let mut document: DidDocument = serde_json::from_str(PLC_DOCUMENT_FIXTURE).unwrap();
+    document.verification_method[0].public_key_multibase = "zNotAKey".to_string();
+
+    assert!(matches!(
+        Account::try_from(&document),
+        Err(DidError::InvalidKey(..))
+    ));
+}
+
+#[test]
+fn test_signature_bundle_verify() {
+    let signing_key = SigningKey::new_secp256k1();
+    let message = b"prism signature bundle";
+    let bundle = SignatureBundle::new(
+        signing_key.verifying_key(),
+        signing_key.sign(message).unwrap(),
+    );
+
+    assert!(bundle.verify(message).is_ok());
+    assert!(bundle.verify(b"tampered signature bundle").is_err());
+}
+
+#[test]
+fn test_service_constructors() {
+    let labeler = Service::new_labeler("https://mod.example.com".to_string());
+    assert_eq!(labeler.service_type, "AtprotoLabeler");
+    assert_eq!(labeler.endpoint, "https://mod.example.com");
+    assert!(labeler.validate().is_ok());
+
+    assert_eq!(
+        Service::new("AtprotoPersonalDataServer", "pds.example.com".to_string()),
+        Service::new_pds("pds.example.com".to_string())
+    );
+
+    for service_type in ["", "  "] {
+        assert!(matches!(
+            Service::new(service_type, "https://example.com".to_string()).validate(),
+            Err(OperationError::EmptyServiceType)
+        ));
+    }
+}
+
+#[test]
+fn test_did_document_verification_method_types() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = Account::builder()
+        .create_did()
+        .with_verification_method(
+            "secp256k1".to_string(),
+            SigningKey::new_secp256k1().verifying_key(),
+        )
+        .with_verification_method(
+            "secp256r1".to_string(),
+            SigningKey::new_secp256r1().verifying_key(),
+        )
+        .with_verification_method(
+            "ed25519".to_string(),
+            SigningKey::new_ed25519().verifying_key(),
+        )
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let method_types = |document: &DidDocument| {
+        document
+            .verification_method
+            .iter()
+            .map(|method| {
+                let (_, key_id) = method.id.rsplit_once('#').unwrap();
+                (key_id.to_string(), method.method_type.clone())
+            })
+            .collect::<HashMap<_, _>>()
+    };
+
+    let document = DidDocument::from(&account);
+    assert_eq!(
+        document,
+        account.to_did_document(VerificationMethodType::Multikey)
+    );
+    assert!(method_types(&document).values().all(|method_type| method_type == "Multikey"));
+
+    let legacy = account.to_did_document(VerificationMethodType::Legacy);
+    let legacy_types = method_types(&legacy);
+    assert_eq!(
+        legacy_types["secp256k1"],
+        "EcdsaSecp256k1VerificationKey2019"
+    );
+    assert_eq!(legacy_types["ed25519"], "Ed25519VerificationKey2020");
+    assert_eq!(legacy_types["secp256r1"], "Multikey");
+    for context in [
+        "https://w3id.org/security/suites/secp256k1-2019/v1",
+        "https://w3id.org/security/suites/ed25519-2020/v1",
+        "https://w3id.org/security/multikey/v1",
+    ] {
+        assert!(legacy.context.iter().any(|c| c == context));
+    }
+
+    let restored = Account::try_from(&legacy).unwrap();
+    assert_eq!(
+        restored.verification_methods(),
+        account.verification_methods()
+    );
+}
+
+#[test]
+fn test_did_document_renders_labeler_service() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let mut account_json = serde_json::to_value(&account).unwrap();
+    account_json["services"]["atproto_labeler"] =
+        serde_json::to_value(Service::new_labeler("https://mod.example.com".to_string())).unwrap();
+    let account: Account = serde_json::from_value(account_json).unwrap();
+
+    let document = serde_json::to_value(DidDocument::from(&account)).unwrap();
+    let services = document["service"].as_array().unwrap();
+    assert_eq!(services.len(), 2);
+    assert!(services.contains(&serde_json::json!({
+        "id": "#atproto_labeler",
+        "type": "AtprotoLabeler",
+        "serviceEndpoint": "https://mod.example.com",
+    })));
+}
+
+#[test]
+fn test_service_endpoint_validation() {
+    let valid = Service::new_pds("http://localhost:65473".to_string());
+    assert_eq!(valid.endpoint, "http://localhost:65473");
+    assert!(valid.validate().is_ok());
+
+    let bare_host = Service::new_pds("pds.example.com".to_string());
+    assert_eq!(bare_host.endpoint, "pds.example.com");
+    assert!(matches!(
+        bare_host.validate(),
+        Err(OperationError::InvalidServiceEndpoint(_))
+    ));
+
+    let garbage = Service::new_pds("not a url".to_string());
+    assert!(matches!(
+        garbage.validate(),
+        Err(OperationError::InvalidServiceEndpoint(_))
+    ));
+
+    let unprefixed = Service {
+        service_type: "AtprotoPersonalDataServer".to_string(),
+        endpoint: "pds.example.com".to_string(),
+    };
+    assert!(unprefixed.validate().is_err());
+    let ftp = Service {
+        service_type: "AtprotoPersonalDataServer".to_string(),
+        endpoint: "ftp://pds.example.com".to_string(),
+    };
+    assert!(ftp.validate().is_err());
+}
+
+#[test]
+fn test_did_creation_rejects_invalid_pds() {
+    let signing_key = SigningKey::new_secp256k1();
+    let result = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("not a url".to_string())
+        .build(&signing_key);
+    assert!(result.is_err());
+
+    // the builder normalizes bare hosts before signing, operations are validated as signed
+    let tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("pds.example.com".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let Operation::CreateDID { atproto_pds, .. } = &tx.operation else {
+        panic!("expected a CreateDID operation");
+    };
+    assert_eq!(atproto_pds, "https://pds.example.com");
+    let plc_op = SignedPLCOp::try_from(&tx.operation).unwrap();
+    assert_eq!(
+        plc_op.unsigned.services["atproto_pds"].endpoint,
+        "https://pds.example.com"
+    );
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let mut unprefixed = tx.operation;
+    let Operation::CreateDID { atproto_pds, .. } = &mut unprefixed else {
+        unreachable!()
+    };
+    *atproto_pds = "pds.example.com".to_string();
+    assert!(matches!(
+        unprefixed.validate_basic(),
+        Err(OperationError::InvalidServiceEndpoint(_))
+    ));
+}
+
+#[test]
+fn test_plc_operation_type_is_part_of_did() {
+    // Same genesis operation as in `test_did_creation`, built without setting the type by hand
+    let unsigned = UnsignedPLCOp::new_genesis(
+        vec![
+            "did:key:zQ3shYUkjUJWLxshqnPbDb1bwc2wMeRy65yQ7TdeotDRoA54G".to_string(),
+            "did:key:zQ3shZUHZuc3Z74mmMhZG2FS87oLqdiHBJyrv5vSc4tychPZF".to_string(),
+        ],
+        HashMap::from([(
+            "atproto".to_string(),
+            "did:key:zQ3shRqHqyhXgCjBmLyPhwN6ENSLMYCVUS7684MKrmVunRF8H".to_string(),
+        )]),
+        vec!["at://mod-authority.test".to_string()],
+        "http://localhost:65473".to_string(),
+    );
+    assert!(unsigned.validate_type().is_ok());
+
+    let mut signed = SignedPLCOp {
+        unsigned,
+        sig:
+            "F0_AgX0tghOjtCMPsMGxHP-8JL11GiR8ikgf68XofQAa1vgEZvEe9VBWFko8isAjT5pkcZOf0GBPAq1cujBNHw"
+                .to_string(),
+    };
+    assert_eq!(signed.derive_did(), "did:prism:3l3bnfketdgiqyfxjju4pfda");
+
+    signed.unsigned.type_ = String::new();
+    assert_ne!(signed.derive_did(), "did:prism:3l3bnfketdgiqyfxjju4pfda");
+    assert!(matches!(
+        signed.unsigned.validate_type(),
+        Err(OperationError::UnsupportedPlcOperationType(_))
+    ));
+}
+
+#[test]
+fn test_plc_operation_validate() {
+    let unsigned = UnsignedPLCOp::new_genesis(
+        vec!["did:key:zQ3shYUkjUJWLxshqnPbDb1bwc2wMeRy65yQ7TdeotDRoA54G".to_string()],
+        HashMap::from([(
+            "atproto".to_string(),
+            "did:key:zQ3shRqHqyhXgCjBmLyPhwN6ENSLMYCVUS7684MKrmVunRF8H".to_string(),
+        )]),
+        vec!["at://mod-authority.test".to_string()],
+        "http://localhost:65473".to_string(),
+    );
+    unsigned.validate().unwrap();
+
+    let mut wrong_pds_type = unsigned.clone();
+    wrong_pds_type.services.insert(
+        "atproto_pds".to_string(),
+        Service::new_labeler("http://localhost:65473".to_string()),
+    );
+    assert!(matches!(
+        wrong_pds_type.validate(),
+        Err(OperationError::InvalidPdsServiceType(service_type)) if service_type == "AtprotoLabeler"
+    ));
+
+    let mut malformed_key = unsigned.clone();
+    malformed_key
+        .verification_methods
+        .insert("atproto".to_string(), "did:key:zinvalid".to_string());
+    assert!(matches!(
+        malformed_key.validate(),
+        Err(OperationError::InvalidDidKey(key, _)) if key == "did:key:zinvalid"
+    ));
+
+    let mut no_rotation_keys = unsigned;
+    no_rotation_keys.rotation_keys.clear();
+    assert!(matches!(
+        no_rotation_keys.validate(),
+        Err(OperationError::EmptyRotationKeys)
+    ));
+}
+
+#[test]
+fn test_verify_did_transaction_rejects_unknown_operation_type() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    let plc_op = SignedPLCOp::try_from(&tx.operation).unwrap();
+    assert_eq!(plc_op.unsigned.type_, "plc_operation");
+
+    let mut plc_tx: SignedPlcTransaction = tx.try_into().unwrap();
+    assert!(verify_did_transaction(&plc_tx).is_ok());
+
+    plc_tx.operation.unsigned.type_ = "plc_tombstone".to_string();
+    assert!(verify_did_transaction(&plc_tx).is_err());
+}
+
+/// Returns one operation of every [`Operation`] variant in declaration order, the unknown one
+/// last. The DID creation is built with `signing_key` as rotation key, the other operations
+/// carry `key`.
+fn all_operation_variants(signing_key: &SigningKey, key: &VerifyingKey) -> Vec<Operation> {
+    let create_did = Account::builder()
+        .create_did()
+        .with_verification_methods(HashMap::from([
+            (
+                "admin".to_string(),
+                SigningKey::new_secp256r1().verifying_key(),
+            ),
+            ("backup".to_string(), key.clone()),
+        ]))
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .with_also_known_as("at://alice.test".to_string())
+        .build(signing_key)
+        .unwrap()
+        .transaction()
+        .operation;
+
+    let operations = vec![
+        Operation::CreateAccount {
+            id: "user123@prism.xyz".to_string(),
+            service_id: "service.prism.xyz".to_string(),
+            challenge: SigningKey::new_ed25519().sign(b"challenge").unwrap(),
+            challenge_expires_at: 1_700_000_000,
+            key: key.clone(),
+        },
+        create_did,
+        Operation::AddKey { key: key.clone() },
+        Operation::RevokeKey { key: key.clone() },
+        Operation::RegisterService {
+            id: "service.prism.xyz".to_string(),
+            key: key.clone(),
+            challenge_key: SigningKey::new_secp256r1().verifying_key(),
+        },
+        Operation::SetRotationKeys {
+            keys: vec![signing_key.verifying_key(), key.clone()],
+        },
+        Operation::DeactivateDID {},
+        Operation::Unknown {
+            name: "RotateHandle".to_string(),
+        },
+    ];
+    // Adding a variant breaks this match, so it has to be added to `operations` as well
+    for operation in &operations {
+        match operation {
+            Operation::CreateAccount { .. }
+            | Operation::CreateDID { .. }
+            | Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::RegisterService { .. }
+            | Operation::SetRotationKeys { .. }
+            | Operation::DeactivateDID {}
+            | Operation::Unknown { .. } => {}
+        }
+    }
+    operations
+}
+
+/// Returns the operations of [`all_operation_variants`] that can be encoded.
+fn encodable_operation_variants(signing_key: &SigningKey, key: &VerifyingKey) -> Vec<Operation> {
+    all_operation_variants(signing_key, key)
+        .into_iter()
+        .filter(|operation| !matches!(operation, Operation::Unknown { .. }))
+        .collect()
+}
+
+#[test]
+fn test_operation_type_covers_every_variant() {
+    let signing_key = SigningKey::new_secp256k1();
+    let operations = all_operation_variants(&signing_key, &signing_key.verifying_key());
+
+    let types = operations.iter().map(Operation::operation_type).collect::<Vec<_>>();
+    assert_eq!(types, OPERATION_TYPES);
+}
+
+#[test]
+fn test_valid_keys_with_algorithm() {
+    let secp256k1_key = SigningKey::new_secp256k1();
+    let secp256r1_key = SigningKey::new_secp256r1();
+    let other_secp256k1_key = SigningKey::new_secp256k1();
+    let tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![
+            secp256k1_key.verifying_key(),
+            secp256r1_key.verifying_key(),
+            other_secp256k1_key.verifying_key(),
+        ])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&secp256k1_key)
+        .unwrap()
+        .sign(&secp256k1_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    assert_eq!(
+        account.valid_keys_with_algorithm(CryptoAlgorithm::Secp256k1),
+        vec![
+            &secp256k1_key.verifying_key(),
+            &other_secp256k1_key.verifying_key()
+        ]
+    );
+    assert_eq!(
+        account.valid_keys_with_algorithm(CryptoAlgorithm::Secp256r1),
+        vec![&secp256r1_key.verifying_key()]
+    );
+    assert!(account.valid_keys_with_algorithm(CryptoAlgorithm::Ed25519).is_empty());
+}
+
+#[test]
+fn test_rotate_to_replaces_rotation_keys() {
+    let rotation_keys: Vec<_> = (0..3).map(|_| SigningKey::new_secp256k1()).collect();
+    let create_tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(rotation_keys.iter().map(SigningKey::verifying_key).collect())
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&rotation_keys[0])
+        .unwrap()
+        .sign(&rotation_keys[0])
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+
+    let new_signing_keys = [SigningKey::new_secp256k1(), SigningKey::new_secp256r1()];
+    let new_keys: Vec<_> = new_signing_keys.iter().map(SigningKey::verifying_key).collect();
+    let rotate_tx = account
+        .modify()
+        .rotate_to(new_keys.clone())
+        .unwrap()
+        .sign(&rotation_keys[1])
+        .unwrap()
+        .transaction();
+    account.process_transaction(&rotate_tx).unwrap();
+    assert_eq!(account.valid_keys(), new_keys.as_slice());
+    assert_eq!(account.nonce(), 2);
+
+    assert!(account.modify().rotate_to(vec![]).is_err());
+    assert!(account.modify().rotate_to(vec![new_keys[0].clone(), new_keys[0].clone()]).is_err());
+
+    // the account rejects invalid sets that bypass the builder
+    let duplicate_tx = UnsignedTransaction {
+        id: account.id().to_string(),
+        operation: Operation::SetRotationKeys {
+            keys: vec![new_keys[1].clone(), new_keys[1].clone()],
+        },
+        nonce: account.nonce(),
+        prev: account.head_cid().map(str::to_string),
+    }
+    .sign(&new_signing_keys[1])
+    .unwrap();
+    let err = account.process_transaction(&duplicate_tx).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<OperationError>(),
+        Some(OperationError::DuplicateRotationKey(1, _))
+    ));
+}
+
+#[test]
+fn test_updates_require_rotation_key_signer() {
+    let signing_key = SigningKey::new_secp256k1();
+    let mut account = Account::default();
+    account.process_transaction(&create_did_tx(&signing_key)).unwrap();
+
+    let attacker = SigningKey::new_secp256k1();
+    let transactions = [
+        account.modify().rotate_to(vec![attacker.verifying_key()]).unwrap(),
+        account.modify().add_key(attacker.verifying_key()).unwrap(),
+        account.modify().revoke_key(signing_key.verifying_key()).unwrap(),
+        account.modify().deactivate().unwrap(),
+    ]
+    .map(|update| update.sign(&attacker).unwrap().transaction());
+    for tx in transactions {
+        let err = account.process_transaction(&tx).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<AccountError>(),
+                Some(AccountError::InvalidKey)
+            ),
+            "{}",
+            tx.operation.operation_type()
+        );
+    }
+    assert_eq!(account.valid_keys(), [signing_key.verifying_key()]);
+    assert_eq!(account.nonce(), 1);
+}
+
+#[test]
+fn test_sequential_updates_chain_nonce_and_prev() {
+    let signing_key = SigningKey::new_secp256k1();
+    let create_tx = create_did_tx(&signing_key);
+    assert_eq!(create_tx.prev, None);
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+    let genesis_cid = create_tx.operation.cid().unwrap();
+
+    let first_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    assert_eq!(first_tx.nonce, 1);
+    assert_eq!(first_tx.prev.as_ref(), Some(&genesis_cid));
+    account.process_transaction(&first_tx).unwrap();
+
+    let second_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    assert_eq!(second_tx.nonce, 2);
+    assert_eq!(second_tx.prev, Some(first_tx.operation.cid().unwrap()));
+    account.process_transaction(&second_tx).unwrap();
+    assert_eq!(
+        account.head_cid(),
+        Some(second_tx.operation.cid().unwrap().as_str())
+    );
+}
+
+#[test]
+fn test_transaction_with_stale_prev_is_rejected() {
+    let signing_key = SigningKey::new_secp256k1();
+    let create_tx = create_did_tx(&signing_key);
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+
+    let mut unsigned_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .transaction();
+    unsigned_tx.prev = None;
+    let tx = unsigned_tx.sign(&signing_key).unwrap();
+
+    let err = account.process_transaction(&tx).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::PrevError(None, Some(_)))
+    ));
+}
+
+#[test]
+fn test_transaction_blob_versioning() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+
+    let blob_bytes = tx.encode_to_blob_bytes().unwrap();
+    assert_eq!(blob_bytes[0], TRANSACTION_BLOB_VERSION);
+    assert_eq!(&blob_bytes[1..], tx.encode_to_bytes().unwrap().as_slice());
+    assert_eq!(
+        Transaction::decode_from_blob_bytes(&blob_bytes).unwrap(),
+        tx
+    );
+
+    // an AddKey transaction posted before the version byte was introduced
+    let legacy = include_bytes!("fixtures/legacy_transaction_blob.bin");
+    assert!(matches!(
+        Transaction::decode_from_blob_bytes(legacy),
+        Err(TransactionError::UnknownBlobVersion(0xa5))
+    ));
+    assert!(Transaction::decode_all_from_blob_bytes(legacy, false).is_err());
+
+    let mut unknown_version = blob_bytes;
+    unknown_version[0] = 2;
+    assert!(matches!(
+        Transaction::decode_from_blob_bytes(&unknown_version),
+        Err(TransactionError::UnknownBlobVersion(2))
+    ));
+    assert!(Transaction::decode_from_blob_bytes(&[]).is_err());
+}
+
+#[test]
+fn test_try_decode_rejects_malformed_input() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    assert_eq!(
+        Transaction::try_decode(&tx.encode_to_bytes().unwrap()).unwrap(),
+        tx
+    );
+
+    let mut inconsistent = tx.clone();
+    inconsistent.id = "did:prism:someoneelse".to_string();
+    assert!(matches!(
+        Transaction::try_decode(&inconsistent.encode_to_bytes().unwrap()),
+        Err(TransactionError::IdMismatch(..))
+    ));
+
+    // regression seeds of the `transaction_decode` fuzz target
+    let seeds: [&[u8]; 5] = [
+        include_bytes!("../../../../fuzz/corpus/transaction_decode/empty"),
+        include_bytes!("../../../../fuzz/corpus/transaction_decode/truncated_map"),
+        include_bytes!("../../../../fuzz/corpus/transaction_decode/huge_length"),
+        include_bytes!("../../../../fuzz/corpus/transaction_decode/nested_arrays"),
+        include_bytes!("../../../../fuzz/corpus/transaction_decode/batch_overlong_entry"),
+    ];
+    for seed in seeds {
+        assert!(matches!(
+            Transaction::try_decode(seed),
+            Err(TransactionError::DecodingFailed(_))
+        ));
+        assert!(Transaction::decode_all_from_blob_bytes(seed, true).is_err());
+    }
+}
+
+#[test]
+fn test_transaction_json_and_base64_round_trip() {
+    let signing_key = SigningKey::new_secp256k1();
+    let key = SigningKey::new_ed25519().verifying_key();
+
+    let mut transactions = Vec::new();
+    for operation in encodable_operation_variants(&signing_key, &key) {
+        let id = operation.id().unwrap_or("did:prism:alice").to_string();
+        let unsigned = UnsignedTransaction {
+            id,
+            operation,
+            nonce: 1,
+            prev: None,
+        };
+        transactions.push(unsigned.sign(&signing_key).unwrap());
+    }
+
+    for transaction in transactions {
+        let json = transaction.to_json_pretty().unwrap();
+        assert_eq!(Transaction::from_json(&json).unwrap(), transaction);
+
+        let base64 = transaction.encode_to_base64().unwrap();
+        assert_eq!(
+            Transaction::decode_from_base64(&format!("{}\n", base64)).unwrap(),
+            transaction
+        );
+    }
+}
+
+#[test]
+fn test_operation_affected_keys() {
+    let signing_key = SigningKey::new_secp256k1();
+    let rotation_key = signing_key.verifying_key();
+    let atproto_key = SigningKey::new_secp256k1().verifying_key();
+    let key = SigningKey::new_ed25519().verifying_key();
+
+    let create_did = Operation::CreateDID {
+        did: "did:prism:alice".to_string(),
+        verification_methods: HashMap::from([
+            ("atproto".to_string(), atproto_key.clone()),
+            ("admin".to_string(), rotation_key.clone()),
+        ]),
+        rotation_keys: vec![rotation_key.clone(), key.clone()],
+        also_known_as: vec![],
+        atproto_pds: "http://localhost:2583".to_string(),
+        signature: signing_key.sign(b"genesis").unwrap(),
+    };
+    // the rotation key doubling as the admin verification method is listed once
+    assert_eq!(
+        create_did.affected_keys(),
+        vec![&rotation_key, &atproto_key, &key]
+    );
+
+    for operation in all_operation_variants(&signing_key, &key) {
+        let expected = match &operation {
+            Operation::CreateAccount { .. }
+            | Operation::AddKey { .. }
+            | Operation::RevokeKey { .. } => vec![&key],
+            Operation::RegisterService { challenge_key, .. } => vec![&key, challenge_key],
+            // verification methods by id, then the rotation keys
+            Operation::CreateDID {
+                verification_methods,
+                ..
+            } => vec![&verification_methods["admin"], &key, &rotation_key],
+            Operation::SetRotationKeys { .. } => vec![&rotation_key, &key],
+            Operation::DeactivateDID {} | Operation::Unknown { .. } => vec![],
+        };
+        assert_eq!(
+            operation.affected_keys(),
+            expected,
+            "{}",
+            operation.operation_type()
+        );
+    }
+}
+
+#[test]
+fn test_operation_json_and_cbor_symmetry() {
+    let signing_key = SigningKey::new_secp256k1();
+    let key = SigningKey::new_ed25519().verifying_key();
+    let operations = encodable_operation_variants(&signing_key, &key);
+
+    for operation in operations {
+        let json = serde_json::to_value(&operation).unwrap();
+        let cbor = operation.encode_to_bytes().unwrap();
+        let from_json: Operation = serde_json::from_value(json.clone()).unwrap();
+        let from_cbor = Operation::decode_from_bytes(&cbor).unwrap();
+        assert_eq!(from_json, operation);
+        assert_eq!(from_cbor, operation);
+
+        // both formats agree on the representation, whichever one the operation came from
+        assert_eq!(from_json.encode_to_bytes().unwrap(), cbor);
+        assert_eq!(serde_json::to_value(&from_cbor).unwrap(), json);
+
+        let unsigned = UnsignedTransaction {
+            id: operation.id().unwrap_or("did:prism:alice").to_string(),
+            operation,
+            nonce: 1,
+            prev: None,
+        };
+        let payload = unsigned.signing_payload_for(&signing_key.verifying_key()).unwrap();
+        let transaction = unsigned.sign(&signing_key).unwrap();
+
+        let from_json = Transaction::from_json(&transaction.to_json_pretty().unwrap()).unwrap();
+        let from_cbor =
+            Transaction::decode_from_bytes(transaction.encode_to_bytes().unwrap()).unwrap();
+        for decoded in [from_json, from_cbor] {
+            // the signed payload is reproduced exactly after a trip through either format
+            let resigned = UnsignedTransaction {
+                id: decoded.id.clone(),
+                operation: decoded.operation.clone(),
+                nonce: decoded.nonce,
+                prev: decoded.prev.clone(),
+            };
+            assert_eq!(resigned.signing_payload_for(&decoded.vk).unwrap(), payload);
+            match decoded.operation {
+                Operation::CreateDID { .. } => decoded.verify_cbor_signature().unwrap(),
+                _ => decoded.verify_signature().unwrap(),
+            }
+            assert_eq!(decoded, transaction);
+        }
+    }
+
+    let unknown = Operation::Unknown {
+        name: "RotateHandle".to_string(),
+    };
+    assert!(serde_json::to_string(&unknown).is_err());
+    assert!(unknown.encode_to_bytes().is_err());
+}
+
+fn batch_transactions() -> Vec<Transaction> {
+    (0..3)
+        .map(|_| {
+            let signing_key = SigningKey::new_secp256k1();
+            create_did_tx(&signing_key)
+        })
+        .collect()
+}
+
+#[test]
+fn test_decode_batch_blob() {
+    let transactions = batch_transactions();
+
+    let blob_bytes = Transaction::encode_batch_to_blob_bytes(&transactions).unwrap();
+    assert_eq!(blob_bytes[0], TRANSACTION_BATCH_BLOB_VERSION);
+    assert_eq!(
+        Transaction::decode_all_from_blob_bytes(&blob_bytes, false).unwrap(),
+        transactions
+    );
+
+    // single transaction blobs decode as well
+    let single = transactions[0].encode_to_blob_bytes().unwrap();
+    assert_eq!(
+        Transaction::decode_all_from_blob_bytes(&single, false).unwrap(),
+        transactions[..1]
+    );
+}
+
+#[test]
+fn test_decode_batch_blob_with_corrupt_entry() {
+    let transactions = batch_transactions();
+
+    let mut blob_bytes = vec![TRANSACTION_BATCH_BLOB_VERSION];
+    for (i, transaction) in transactions.iter().enumerate() {
+        let mut encoded = transaction.encode_to_bytes().unwrap();
+        if i == 1 {
+            encoded.fill(0xff);
+        }
+        blob_bytes.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        blob_bytes.extend_from_slice(&encoded);
+    }
+
+    assert!(matches!(
+        Transaction::decode_all_from_blob_bytes(&blob_bytes, false),
+        Err(TransactionError::DecodingFailed(_))
+    ));
+    assert_eq!(
+        Transaction::decode_all_from_blob_bytes(&blob_bytes, true).unwrap(),
+        vec![transactions[0].clone(), transactions[2].clone()]
+    );
+
+    // a length prefix pointing past the blob can't be skipped
+    let truncated = &blob_bytes[..blob_bytes.len() - 1];
+    assert!(Transaction::decode_all_from_blob_bytes(truncated, true).is_err());
+}
+
+#[test]
+fn test_decode_blobs_with_unknown_operations() {
+    /// Stands in for an operation introduced by a future version.
+    #[derive(Serialize)]
+    enum FutureOperation {
+        RotateHandle { handle: String },
+    }
+
+    #[derive(Serialize)]
+    struct FutureTransaction {
+        id: String,
+        operation: FutureOperation,
+        nonce: u64,
+        prev: Option<String>,
+        signature: Signature,
+        vk: VerifyingKey,
+    }
+
+    let signing_key = SigningKey::new_secp256k1();
+    let known_tx = create_did_tx(&signing_key);
+
+    let future_tx = FutureTransaction {
+        id: known_tx.id.clone(),
+        operation: FutureOperation::RotateHandle {
+            handle: "at://alice.test".to_string(),
+        },
+        nonce: 1,
+        prev: Some(known_tx.operation.cid().unwrap()),
+        signature: known_tx.signature.clone(),
+        vk: known_tx.vk.clone(),
+    };
+    let future_blob = [
+        [TRANSACTION_BLOB_VERSION].as_slice(),
+        &future_tx.encode_to_bytes().unwrap(),
+    ]
+    .concat();
+
+    let blobs = [known_tx.encode_to_blob_bytes().unwrap(), future_blob];
+    let decoded = blobs
+        .iter()
+        .map(|blob| Transaction::decode_from_blob_bytes(blob).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(decoded[0], known_tx);
+    assert_eq!(
+        decoded[1].operation,
+        Operation::Unknown {
+            name: "RotateHandle".to_string()
+        }
+    );
+    assert_eq!(decoded[1].operation.operation_type(), "unknown");
+    assert_eq!(decoded[1].nonce, 1);
+
+    // unknown operations are decoded, but never applied
+    let mut account = Account::default();
+    account.process_transaction(&decoded[0]).unwrap();
+    let err = account.process_transaction(&decoded[1]).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<OperationError>(),
+        Some(OperationError::UnknownOperation(name)) if name == "RotateHandle"
+    ));
+    assert_eq!(account.nonce(), 1);
+}
+
+#[test]
+fn test_did_document_deactivated_flag() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    assert!(!account.is_deactivated());
+    let active = serde_json::to_value(DidDocument::from(&account)).unwrap();
+    assert!(active.get("deactivated").is_none());
+
+    let deactivate_tx =
+        account.modify().deactivate().unwrap().sign(&signing_key).unwrap().transaction();
+    let mut deactivated_account = account.clone();
+    deactivated_account.process_transaction(&deactivate_tx).unwrap();
+    assert!(deactivated_account.is_deactivated());
+
+    let document = DidDocument::from(&deactivated_account);
+    let deactivated = serde_json::to_value(&document).unwrap();
+    assert_eq!(deactivated["deactivated"], serde_json::Value::Bool(true));
+    assert_eq!(deactivated["id"], active["id"]);
+    assert!(Account::try_from(&document).unwrap().is_deactivated());
+}
+
+#[test]
+fn test_deactivate_did() {
+    let recovery = SigningKey::new_secp256k1();
+    let signer = SigningKey::new_secp256k1();
+    let create_tx = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![recovery.verifying_key(), signer.verifying_key()])
+        .with_atproto_pds("http://localhost:2583".to_string())
+        .build(&recovery)
+        .unwrap()
+        .sign(&recovery)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&create_tx).unwrap();
+    let genesis = account.clone();
+
+    // only rotation keys may deactivate the account
+    let err = account
+        .clone()
+        .process_transaction(
+            &account
+                .modify()
+                .deactivate()
+                .unwrap()
+                .sign(&SigningKey::new_secp256k1())
+                .unwrap()
+                .transaction(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::InvalidKey)
+    ));
+
+    let deactivate_tx = account.modify().deactivate().unwrap().sign(&signer).unwrap().transaction();
+    account.process_transaction(&deactivate_tx).unwrap();
+    assert!(account.is_deactivated());
+
+    // deactivated accounts accept no further operations
+    let add_key_tx = account
+        .modify()
+        .add_key(SigningKey::new_secp256k1().verifying_key())
+        .unwrap()
+        .sign(&signer)
+        .unwrap()
+        .transaction();
+    let err = account.clone().process_transaction(&add_key_tx).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AccountError>(),
+        Some(AccountError::Deactivated(did)) if did == account.id()
+    ));
+
+    // but a higher-authority key can override the deactivation
+    let fork = fork_tx(&account, genesis.head_cid().unwrap(), &recovery);
+    account.process_transaction_with_fork_base(&fork, Some(&genesis)).unwrap();
+    assert!(!account.is_deactivated());
+}
+
+#[test]
+fn test_resolution_metadata_of_deactivated_did() {
+    let signing_key = SigningKey::new_secp256k1();
+    let tx = create_did_tx(&signing_key);
+    let mut account = Account::default();
+    account.process_transaction(&tx).unwrap();
+
+    let active = DidResolutionResult::from(&account);
+    assert!(!active.did_document_metadata.deactivated);
+    assert_eq!(
+        active.did_document_metadata.version_id.as_deref(),
+        account.head_cid()
+    );
+    let active_json = serde_json::to_value(&active).unwrap();
+    assert!(active_json["didDocumentMetadata"].get("deactivated").is_none());
+
+    let deactivate_tx =
+        account.modify().deactivate().unwrap().sign(&signing_key).unwrap().transaction();
+    let mut deactivated_account = account.clone();
+    deactivated_account.process_transaction(&deactivate_tx).unwrap();
+
+    let deactivated = DidResolutionResult::from(&deactivated_account);
+    assert!(deactivated.did_document_metadata.deactivated);
+    assert!(deactivated.did_document.is_some());
+    let deactivated_json = serde_json::to_value(&deactivated).unwrap();
+    assert_eq!(
+        deactivated_json["didDocumentMetadata"]["deactivated"],
+        serde_json::Value::Bool(true)
+    );
+    assert_eq!(
+        deactivated_json["didResolutionMetadata"]["contentType"],
+        "application/did+ld+json"
+    );
+}
+
+#[test]
+fn test_digest_hash_items_domain_separates_splittings() {
+    let ab_c: [&[u8]; 2] = [b"ab", b"c"];
+    let a_bc: [&[u8]; 2] = [b"a", b"bc"];
+
+    // plain concatenation can't tell the splittings apart
+    assert_eq!(Digest::hash_items(&ab_c), Digest::hash_items(&a_bc));
+    assert_ne!(
+        Digest::hash_items_domain(b"test", &ab_c),
+        Digest::hash_items_domain(b"test", &a_bc)
+    );
+    assert_ne!(
+        Digest::hash_items_domain(b"test", &ab_c),
+        Digest::hash_items_domain(b"other", &ab_c)
+    );
+    // nor can the domain be shifted into the items
+    assert_ne!(
+        Digest::hash_items_domain(b"testab", &[b"c"]),
+        Digest::hash_items_domain(b"test", &ab_c)
+    );
+}
+
+#[test]
+fn test_digest_string_encodings_round_trip() {
+    let digest = Digest::hash(b"prism");
+
+    let hex = digest.to_hex();
+    assert_eq!(hex.len(), 64);
+    assert_eq!(Digest::from_hex(&hex).unwrap(), digest);
+    assert_eq!(hex.parse::<Digest>().unwrap(), digest);
+    assert_eq!(digest.to_string(), hex);
+
+    let multibase = digest.to_multibase();
+    assert!(multibase.starts_with('b'));
+    assert_eq!(Digest::from_multibase(&multibase).unwrap(), digest);
+    assert_eq!(multibase.parse::<Digest>().unwrap(), digest);
+
+    assert!(Digest::from_multibase(&format!("z{}", &multibase[1..])).is_err());
+    assert!(Digest::from_multibase(&multibase[..multibase.len() - 8]).is_err());
+    assert!("not a digest".parse::<Digest>().is_err());
+}
+
+#[test]
+fn test_commitment_response_orders_by_epoch() {
+    let earlier = CommitmentResponse {
+        commitment: Digest::hash(b"earlier"),
+        epoch: 1,
+        timestamp: Some(1_700_000_000),
+    };
+    let later = CommitmentResponse {
+        commitment: Digest::hash(b"later"),
+        epoch: 2,
+        timestamp: Some(1_700_000_000),
+    };
+
+    assert!(later > earlier);
+    assert!(earlier < later);
+    assert_eq!(
+        earlier.partial_cmp(&earlier.clone()),
+        Some(std::cmp::Ordering::Equal)
+    );
+
+    // snapshots of the same epoch taken at different times can not be ordered
+    let resampled = CommitmentResponse {
+        timestamp: Some(1_700_000_060),
+        ..earlier.clone()
+    };
+    assert_eq!(earlier.partial_cmp(&resampled), None);
+
+    // responses of provers without epochs still deserialize
+    let legacy: CommitmentResponse =
+        serde_json::from_value(serde_json::json!({ "commitment": earlier.commitment })).unwrap();
+    assert_eq!(legacy.epoch, 0);
+    assert_eq!(legacy.timestamp, None);
+}
+
+#[test]
+fn test_account_response_verify() {
+    let accounts = batch_transactions()
+        .iter()
+        .map(|tx| {
+            let mut account = Account::default();
+            account.process_transaction(tx).unwrap();
+            account
+        })
+        .collect::<Vec<_>>();
+    let (account, other) = (&accounts[0], &accounts[1]);
+    let leaf = HashedMerkleProof::leaf_hash(account.id(), &account.encode_to_bytes().unwrap());
+
+    // a tree of the account and a sibling subtree, placed by the first bit of the key hash
+    let sibling = Digest::hash(b"sibling subtree");
+    let (left, right) = match Digest::hash(account.id()).as_bytes()[0] & 0x80 {
+        0 => (leaf, sibling),
+        _ => (sibling, leaf),
+    };
+    let commitment = Digest::hash_items(&[
+        b"JMT::IntrnalNode".as_slice(),
+        left.as_bytes(),
+        right.as_bytes(),
+    ]);
+
+    let key_hash = Digest::hash(account.id());
+    let value_hash = Digest::hash(account.encode_to_bytes().unwrap());
+    let membership_proof = HashedMerkleProof {
+        leaf: Some(leaf),
+        leaf_key_hash: Some(key_hash),
+        leaf_value_hash: Some(value_hash),
+        siblings: vec![sibling],
+    };
+    let mut response = AccountResponse {
+        account: Some(account.clone()),
+        proof: membership_proof.clone(),
+    };
+    response.verify(account.id(), &commitment).unwrap();
+    assert!(matches!(
+        response.verify(other.id(), &commitment),
+        Err(PrismApiError::InvalidProof(_))
+    ));
+
+    response.proof.siblings[0] = Digest::hash(b"tampered subtree");
+    assert!(matches!(
+        response.verify(account.id(), &commitment),
+        Err(PrismApiError::InvalidProof(_))
+    ));
+
+    // a present account can not be passed off as missing by stripping it from its proof
+    let mut stripped = AccountResponse {
+        account: None,
+        proof: membership_proof,
+    };
+    assert!(matches!(
+        stripped.verify(account.id(), &commitment),
+        Err(PrismApiError::InvalidProof(_))
+    ));
+    stripped.proof.leaf_key_hash = Some(Digest::hash(other.id()));
+    assert!(stripped.verify(account.id(), &commitment).is_err());
+    stripped.proof.leaf_key_hash = None;
+    assert!(stripped.verify(account.id(), &commitment).is_err());
+
+    // the path of the missing account ends in the leaf of the only other account
+    let other_key_hash = Digest::hash(other.id());
+    let other_value_hash = Digest::hash(other.encode_to_bytes().unwrap());
+    let other_leaf = HashedMerkleProof::leaf_hash_of(&other_key_hash, &other_value_hash);
+    let mut non_membership = AccountResponse {
+        account: None,
+        proof: HashedMerkleProof {
+            leaf: Some(other_leaf),
+            leaf_key_hash: Some(other_key_hash),
+            leaf_value_hash: Some(other_value_hash),
+            siblings: vec![],
+        },
+    };
+    non_membership.verify(account.id(), &other_leaf).unwrap();
+    assert!(non_membership.verify(account.id(), &commitment).is_err());
+    assert!(non_membership.verify(other.id(), &other_leaf).is_err());
+    non_membership.proof.leaf_key_hash = None;
+    assert!(non_membership.verify(account.id(), &other_leaf).is_err());
+
+    let empty_tree = AccountResponse::default();
+    empty_tree
+        .verify(
+            account.id(),
+            &Digest::new(*b"SPARSE_MERKLE_PLACEHOLDER_HASH__"),
+        )
+        .unwrap();
+}
@@ -3,11 +3,27 @@ use std::collections::HashMap;
 use celestia_types::Blob;
 use prism_errors::TransactionError;
 use prism_keys::{Signature, SigningKey, VerifyingKey};
-use prism_serde::binary::{FromBinary, ToBinary};
+use prism_serde::{
+    base64::{FromBase64, ToBase64},
+    binary::{FromBinary, ToBinary},
+    hex::ToHex,
+};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::operation::{Operation, SignatureBundle, SignedPLCOp, UnsignedPLCOp};
+use crate::{
+    digest::Digest,
+    operation::{Operation, SignatureBundle, SignedPLCOp, UnsignedPLCOp},
+};
+
+/// Version of the transaction encoding in blobs, written as their first byte by
+/// [`Transaction::encode_to_blob_bytes`]. Version 0 is the DAG-CBOR encoded [`Transaction`].
+pub const TRANSACTION_BLOB_VERSION: u8 = 0;
+
+/// Version of blobs carrying several transactions, written by
+/// [`Transaction::encode_batch_to_blob_bytes`]. Each DAG-CBOR encoded [`Transaction`] is prefixed
+/// with its length as a big-endian `u32`.
+pub const TRANSACTION_BATCH_BLOB_VERSION: u8 = 1;
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 /// Represents a partial prism transaction that still needs to be signed.
@@ -18,20 +34,29 @@ pub struct UnsignedTransaction {
     pub operation: Operation,
     /// The nonce of the account at the time of this transaction
     pub nonce: u64,
+    /// The head CID of the account at the time of this transaction, see [`Account::head_cid`].
+    /// `None` for transactions creating an account.
+    ///
+    /// [`Account::head_cid`]: crate::account::Account::head_cid
+    // Always serialized: compact formats like bincode can not skip fields
+    #[serde(default)]
+    pub prev: Option<String>,
 }
 
 impl UnsignedTransaction {
     /// Signs the transaction with the given [`SigningKey`] and gives out a full [`Transaction`].
     pub fn sign(self, sk: &SigningKey) -> Result<Transaction, TransactionError> {
-        let bytes = self.signing_payload()?;
+        let vk = sk.verifying_key();
+        let bytes = self.signing_payload_for(&vk)?;
         let signature = sk.sign(&bytes).map_err(|_| TransactionError::SigningFailed)?;
 
         Ok(Transaction {
             id: self.id,
             operation: self.operation,
             nonce: self.nonce,
+            prev: self.prev,
             signature,
-            vk: sk.verifying_key(),
+            vk,
         })
     }
 
@@ -43,6 +68,7 @@ impl UnsignedTransaction {
             id: self.id,
             operation: self.operation,
             nonce: self.nonce,
+            prev: self.prev,
             signature: signature_bundle.signature,
             vk: signature_bundle.verifying_key,
         }
@@ -53,6 +79,53 @@ impl UnsignedTransaction {
     pub fn signing_payload(&self) -> Result<Vec<u8>, TransactionError> {
         self.encode_to_bytes().map_err(|e| TransactionError::EncodingFailed(e.to_string()))
     }
+
+    /// Returns the payload that has to be signed by `vk` to authorize this transaction.
+    ///
+    /// For DID operations this is [`UnsignedTransaction::did_signing_payload`], for all other
+    /// operations it is equal to [`UnsignedTransaction::signing_payload`].
+    pub fn signing_payload_for(&self, vk: &VerifyingKey) -> Result<Vec<u8>, TransactionError> {
+        match self.operation {
+            Operation::CreateDID { .. } => self.did_signing_payload(vk),
+            _ => self.signing_payload(),
+        }
+    }
+
+    /// Returns the DAG-CBOR encoded [`UnsignedPlcTransaction`] of a DID operation, as signed by
+    /// `vk`. This is exactly the payload [`Transaction::verify_cbor_signature`] checks against.
+    ///
+    /// Errors for operations that have no PLC representation.
+    pub fn did_signing_payload(&self, vk: &VerifyingKey) -> Result<Vec<u8>, TransactionError> {
+        did_signing_payload(&self.operation, self.nonce, vk)
+    }
+}
+
+/// Encodes the [`UnsignedPlcTransaction`] of a DID operation, as signed by `vk`.
+///
+/// Works on borrowed transaction fields, so signatures can be checked without cloning the
+/// operation into an [`UnsignedTransaction`] first.
+fn did_signing_payload(
+    operation: &Operation,
+    nonce: u64,
+    vk: &VerifyingKey,
+) -> Result<Vec<u8>, TransactionError> {
+    let Operation::CreateDID { did, .. } = operation else {
+        return Err(TransactionError::InvalidOp(format!(
+            "DID signing payload is only defined for DID operations, got {}",
+            operation
+        )));
+    };
+    let operation =
+        SignedPLCOp::try_from(operation).map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+
+    UnsignedPlcTransaction {
+        did: did.clone(),
+        operation,
+        nonce,
+        vk: vk.to_did().map_err(|e| TransactionError::InvalidOp(e.to_string()))?,
+    }
+    .encode_to_bytes()
+    .map_err(|e| TransactionError::EncodingFailed(e.to_string()))
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, ToSchema)]
@@ -97,6 +170,25 @@ impl From<SignedPlcTransaction> for UnsignedPlcTransaction {
     }
 }
 
+/// Verifies the signature of a [`SignedPlcTransaction`] as received over the wire, without
+/// converting it into a [`Transaction`] first. Mirrors [`Transaction::verify_cbor_signature`].
+pub fn verify_did_transaction(tx: &SignedPlcTransaction) -> Result<(), TransactionError> {
+    tx.operation.unsigned.validate().map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+    let vk =
+        VerifyingKey::from_did(&tx.vk).map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+    let sig_bytes = Vec::<u8>::from_base64(&tx.signature)
+        .map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+    let signature = Signature::from_algorithm_and_bytes(vk.algorithm(), &sig_bytes)
+        .map_err(|e| TransactionError::InvalidOp(e.to_string()))?;
+
+    let message = UnsignedPlcTransaction::from(tx.clone())
+        .encode_to_bytes()
+        .map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+
+    vk.verify_signature(&message, &signature)
+        .map_err(|e| TransactionError::InvalidSignature(e.to_string()))
+}
+
 impl TryInto<SignedPlcTransaction> for Transaction {
     type Error = std::io::Error;
 
@@ -110,14 +202,21 @@ impl TryInto<SignedPlcTransaction> for Transaction {
                 atproto_pds,
                 signature,
             } => {
+                let to_did = |key: VerifyingKey| {
+                    key.to_did().map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                    })
+                };
                 let verification_methods: HashMap<String, String> = verification_methods
                     .into_iter()
-                    .map(|(a, b)| (a, b.to_did().unwrap()))
-                    .collect();
+                    .map(|(a, b)| Ok((a, to_did(b)?)))
+                    .collect::<Result<_, Self::Error>>()?;
                 let rotation_keys: Vec<String> =
-                    rotation_keys.into_iter().map(|a| a.to_did().unwrap()).collect();
+                    rotation_keys.into_iter().map(to_did).collect::<Result<_, _>>()?;
 
-                let plc_sig = signature.to_plc_signature();
+                let plc_sig = signature.to_plc_signature().map_err(|e| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                })?;
                 let operation = SignedPLCOp {
                     unsigned: UnsignedPLCOp::new_genesis(
                         rotation_keys,
@@ -125,14 +224,16 @@ impl TryInto<SignedPlcTransaction> for Transaction {
                         also_known_as,
                         atproto_pds,
                     ),
-                    sig: plc_sig.clone(),
+                    sig: plc_sig,
                 };
                 Ok(SignedPlcTransaction {
                     did,
                     operation,
                     nonce: self.nonce,
-                    signature: plc_sig.clone(),
-                    vk: self.vk.to_did().unwrap(),
+                    signature: self.signature.to_plc_signature().map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+                    })?,
+                    vk: to_did(self.vk)?,
                 })
             }
             _ => Err(std::io::Error::new(
@@ -155,18 +256,35 @@ impl TryInto<Transaction> for SignedPlcTransaction {
             vk,
         } = self;
 
-        let verification_methods: HashMap<String, VerifyingKey> = operation
+        // the transaction is received from clients, so malformed input must not panic
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+
+        operation.unsigned.validate().map_err(|e| invalid(e.to_string()))?;
+
+        let verification_methods = operation
             .unsigned
             .verification_methods
             .into_iter()
-            .map(|(a, b)| (a, VerifyingKey::from_did(&b).unwrap()))
-            .collect();
-        let rotation_keys: Vec<VerifyingKey> = operation
+            .map(|(a, b)| {
+                Ok((
+                    a,
+                    VerifyingKey::from_did(&b).map_err(|e| invalid(e.to_string()))?,
+                ))
+            })
+            .collect::<Result<HashMap<String, VerifyingKey>, Self::Error>>()?;
+        let rotation_keys = operation
             .unsigned
             .rotation_keys
-            .into_iter()
-            .map(|a| VerifyingKey::from_did(&a).unwrap())
-            .collect();
+            .iter()
+            .map(|a| VerifyingKey::from_did(a).map_err(|e| invalid(e.to_string())))
+            .collect::<Result<Vec<VerifyingKey>, Self::Error>>()?;
+        let atproto_pds = operation
+            .unsigned
+            .services
+            .get("atproto_pds")
+            .ok_or_else(|| invalid("missing atproto_pds service".to_string()))?
+            .endpoint
+            .clone();
 
         Ok(Transaction {
             id: did.clone(),
@@ -175,18 +293,14 @@ impl TryInto<Transaction> for SignedPlcTransaction {
                 verification_methods,
                 rotation_keys,
                 also_known_as: operation.unsigned.also_known_as,
-                atproto_pds: operation
-                    .unsigned
-                    .services
-                    .get("atproto_pds")
-                    .unwrap()
-                    .endpoint
-                    .clone(),
-                signature: Signature::try_from(operation.sig).unwrap(),
+                atproto_pds,
+                signature: Signature::try_from(operation.sig)
+                    .map_err(|e| invalid(e.to_string()))?,
             },
             nonce,
-            signature: Signature::try_from(signature).unwrap(),
-            vk: VerifyingKey::from_did(&vk).unwrap(),
+            prev: None,
+            signature: Signature::try_from(signature).map_err(|e| invalid(e.to_string()))?,
+            vk: VerifyingKey::from_did(&vk).map_err(|e| invalid(e.to_string()))?,
         })
     }
 }
@@ -200,6 +314,10 @@ pub struct Transaction {
     pub operation: Operation,
     /// The nonce of the account at the time of this transaction
     pub nonce: u64,
+    /// The head CID of the account at the time of this transaction, see
+    /// [`UnsignedTransaction::prev`].
+    #[serde(default)]
+    pub prev: Option<String>,
     /// The signature of the transaction, signed by [`self::vk`].
     pub signature: Signature,
     /// The verifying key of the signer of this transaction. This vk must be
@@ -209,6 +327,18 @@ pub struct Transaction {
 }
 
 impl Transaction {
+    /// Checks that the transaction is for the account its operation creates, see
+    /// [`Operation::id`]. This is a structural check, signatures are not verified.
+    pub fn validate_consistency(&self) -> Result<(), TransactionError> {
+        match self.operation.id() {
+            Some(id) if id != self.id => Err(TransactionError::IdMismatch(
+                self.id.clone(),
+                id.to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     /// Verifies the signature of the transaction
     pub fn verify_signature(&self) -> Result<(), TransactionError> {
         let message = self
@@ -218,21 +348,176 @@ impl Transaction {
 
         self.vk
             .verify_signature(&message, &self.signature)
-            .map_err(|e| TransactionError::InvalidOp(e.to_string()))
+            .map_err(|e| TransactionError::InvalidSignature(e.to_string()))
     }
 
     // Used for verifying CBOR-encoded transactions (for DID operations)
     pub fn verify_cbor_signature(&self) -> Result<(), TransactionError> {
-        let did_tx: SignedPlcTransaction = self.clone().try_into().unwrap();
-        let us_did_tx: UnsignedPlcTransaction = did_tx.into();
-
-        let message = us_did_tx
-            .encode_to_bytes()
-            .map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+        let message = did_signing_payload(&self.operation, self.nonce, &self.vk)?;
 
         self.vk
             .verify_signature(&message, &self.signature)
-            .map_err(|e| TransactionError::InvalidOp(e.to_string()))
+            .map_err(|e| TransactionError::InvalidSignature(e.to_string()))
+    }
+
+    /// Returns the hash of the DAG-CBOR encoded transaction, a unique handle for the transaction
+    /// as opposed to the id of the account it applies to. Fails for operations that can not be
+    /// encoded, i.e. [`Operation::Unknown`].
+    pub fn hash(&self) -> Result<Digest, TransactionError> {
+        let encoded =
+            self.encode_to_bytes().map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+        Ok(Digest::hash(encoded))
+    }
+
+    /// Returns the hex encoded [`Transaction::hash`].
+    pub fn tx_hash(&self) -> Result<String, TransactionError> {
+        Ok(self.hash()?.to_hex())
+    }
+
+    /// Serializes the transaction to pretty-printed JSON, the form the node's `/transaction`
+    /// endpoint accepts, e.g. to store a transaction crafted offline and submit it later.
+    pub fn to_json_pretty(&self) -> Result<String, TransactionError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| TransactionError::EncodingFailed(e.to_string()))
+    }
+
+    /// Parses a transaction from JSON, as written by [`Transaction::to_json_pretty`]. Like
+    /// [`Transaction::try_decode`], rejects transactions whose id does not match their operation.
+    pub fn from_json(json: &str) -> Result<Self, TransactionError> {
+        let transaction: Self = serde_json::from_str(json)
+            .map_err(|e| TransactionError::DecodingFailed(e.to_string()))?;
+        transaction.validate_consistency()?;
+        Ok(transaction)
+    }
+
+    /// Encodes the DAG-CBOR encoded transaction as base64, a compact form that survives being
+    /// copied around as text.
+    pub fn encode_to_base64(&self) -> Result<String, TransactionError> {
+        let encoded =
+            self.encode_to_bytes().map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+        Ok(encoded.to_base64())
+    }
+
+    /// Decodes a transaction encoded by [`Transaction::encode_to_base64`], see
+    /// [`Transaction::try_decode`].
+    pub fn decode_from_base64(base64: &str) -> Result<Self, TransactionError> {
+        let bytes = Vec::<u8>::from_base64(base64.trim())
+            .map_err(|e| TransactionError::DecodingFailed(e.to_string()))?;
+        Self::try_decode(&bytes)
+    }
+
+    /// Encodes the transaction for posting it as a blob: the [`TRANSACTION_BLOB_VERSION`]
+    /// followed by the DAG-CBOR encoded transaction.
+    pub fn encode_to_blob_bytes(&self) -> Result<Vec<u8>, TransactionError> {
+        let encoded =
+            self.encode_to_bytes().map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+        Ok([[TRANSACTION_BLOB_VERSION].as_slice(), &encoded].concat())
+    }
+
+    /// Decodes a DAG-CBOR encoded transaction from untrusted bytes, such as blob data.
+    ///
+    /// Never panics on malformed input, and rejects transactions whose id does not match their
+    /// operation (see [`Transaction::validate_consistency`]), so the result can be passed on to
+    /// conversions that expect a well-formed transaction.
+    pub fn try_decode(bytes: &[u8]) -> Result<Self, TransactionError> {
+        if bytes.is_empty() {
+            return Err(TransactionError::DecodingFailed("empty input".to_string()));
+        }
+        let transaction = Self::decode_from_bytes(bytes)
+            .map_err(|e| TransactionError::DecodingFailed(e.to_string()))?;
+        transaction.validate_consistency()?;
+        Ok(transaction)
+    }
+
+    /// Decodes a transaction encoded by [`Transaction::encode_to_blob_bytes`], rejecting
+    /// unknown versions.
+    ///
+    /// Blobs posted before the version byte was introduced are not supported: they were signed
+    /// without `prev`, so they could not be applied even if decoded.
+    pub fn decode_from_blob_bytes(bytes: &[u8]) -> Result<Self, TransactionError> {
+        match bytes.split_first() {
+            Some((&TRANSACTION_BLOB_VERSION, encoded)) => Self::try_decode(encoded),
+            Some((&version, _)) => Err(TransactionError::UnknownBlobVersion(version)),
+            None => Err(TransactionError::DecodingFailed("empty blob".to_string())),
+        }
+    }
+
+    /// Encodes several transactions into a single blob of version
+    /// [`TRANSACTION_BATCH_BLOB_VERSION`].
+    pub fn encode_batch_to_blob_bytes(transactions: &[Self]) -> Result<Vec<u8>, TransactionError> {
+        let mut bytes = vec![TRANSACTION_BATCH_BLOB_VERSION];
+        for transaction in transactions {
+            let encoded = transaction
+                .encode_to_bytes()
+                .map_err(|e| TransactionError::EncodingFailed(e.to_string()))?;
+            let len = u32::try_from(encoded.len()).map_err(|_| {
+                TransactionError::EncodingFailed(format!(
+                    "transaction of {} bytes exceeds the batch entry size",
+                    encoded.len()
+                ))
+            })?;
+            bytes.extend_from_slice(&len.to_be_bytes());
+            bytes.extend_from_slice(&encoded);
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes all transactions of a blob, written either by
+    /// [`Transaction::encode_to_blob_bytes`] or [`Transaction::encode_batch_to_blob_bytes`].
+    ///
+    /// Batch entries that are not valid transactions are skipped if `skip_malformed` is set, and
+    /// fail the whole blob otherwise. A blob whose length prefixes don't add up always fails, as
+    /// the following entries can't be located.
+    pub fn decode_all_from_blob_bytes(
+        bytes: &[u8],
+        skip_malformed: bool,
+    ) -> Result<Vec<Self>, TransactionError> {
+        let Some((&TRANSACTION_BATCH_BLOB_VERSION, mut remaining)) = bytes.split_first() else {
+            return Self::decode_from_blob_bytes(bytes).map(|transaction| vec![transaction]);
+        };
+
+        let mut transactions = Vec::new();
+        let mut index = 0;
+        while !remaining.is_empty() {
+            let Some((len, rest)) = remaining.split_first_chunk::<4>() else {
+                return Err(TransactionError::DecodingFailed(format!(
+                    "truncated length of batch entry {}",
+                    index
+                )));
+            };
+            let len = u32::from_be_bytes(*len) as usize;
+            if rest.len() < len {
+                return Err(TransactionError::DecodingFailed(format!(
+                    "batch entry {} of {} bytes exceeds the remaining {} bytes",
+                    index,
+                    len,
+                    rest.len()
+                )));
+            }
+            let (entry, rest) = rest.split_at(len);
+
+            match Self::try_decode(entry) {
+                Ok(transaction) => transactions.push(transaction),
+                Err(_) if skip_malformed => {}
+                Err(e) => {
+                    return Err(TransactionError::DecodingFailed(format!(
+                        "batch entry {}: {}",
+                        index, e
+                    )));
+                }
+            }
+            remaining = rest;
+            index += 1;
+        }
+        Ok(transactions)
+    }
+
+    /// Decodes all transactions of a blob, see [`Transaction::decode_all_from_blob_bytes`].
+    pub fn decode_all_from_blob(
+        blob: &Blob,
+        skip_malformed: bool,
+    ) -> Result<Vec<Self>, TransactionError> {
+        Self::decode_all_from_blob_bytes(&blob.data, skip_malformed)
     }
 
     /// Extracts the part of the transaction that was signed
@@ -241,6 +526,7 @@ impl Transaction {
             id: self.id.clone(),
             operation: self.operation.clone(),
             nonce: self.nonce,
+            prev: self.prev.clone(),
         }
     }
 }
@@ -249,6 +535,6 @@ impl TryFrom<&Blob> for Transaction {
     type Error = anyhow::Error;
 
     fn try_from(value: &Blob) -> Result<Self, Self::Error> {
-        Transaction::decode_from_bytes(&value.data).map_err(|e| e.into())
+        Transaction::decode_from_blob_bytes(&value.data).map_err(|e| e.into())
     }
 }
@@ -352,14 +352,14 @@ impl DataAvailabilityLayer for CelestiaConnection {
             .try_fetch_blobs(height, self.operation_namespace)
             .await?
             .iter()
-            .filter_map(|blob| match Transaction::try_from(blob) {
-                Ok(transaction) => Some(transaction),
+            .flat_map(|blob| match Transaction::decode_all_from_blob(blob, true) {
+                Ok(transactions) => transactions,
                 Err(e) => {
                     warn!(
-                        "Failed to parse blob from height {} to transaction: {:?}",
+                        "Failed to parse blob from height {} to transactions: {:?}",
                         height, e
                     );
-                    None
+                    Vec::new()
                 }
             })
             .collect();
@@ -372,7 +372,7 @@ impl DataAvailabilityLayer for CelestiaConnection {
             .iter()
             .map(|transaction| {
                 let data = transaction
-                    .encode_to_bytes()
+                    .encode_to_blob_bytes()
                     .context(format!("Failed to serialize transaction {:?}", transaction))
                     .map_err(|e| {
                         DataAvailabilityError::GeneralError(GeneralError::ParsingError(
@@ -156,6 +156,35 @@ pub enum OperationError {
     DataTooLarge(usize),
     #[error("operation not convertible to plc_operation")]
     InvalidPLCConversion,
+    #[error("signature not representable in plc_operation: {0}")]
+    UnsupportedPlcSignature(String),
+    #[error("key not representable in plc_operation: {0}")]
+    UnsupportedPlcKey(String),
+    #[error("service endpoint is not an absolute http(s) URL: {0}")]
+    InvalidServiceEndpoint(String),
+    #[error("service type cannot be empty")]
+    EmptyServiceType,
+    #[error("unsupported plc operation type: {0:?}")]
+    UnsupportedPlcOperationType(String),
+    #[error("unknown operation type: {0}")]
+    UnknownOperation(String),
+    #[error("rotation keys cannot be empty")]
+    EmptyRotationKeys,
+    #[error("rotation key {0} is a duplicate: {1}")]
+    DuplicateRotationKey(usize, String),
+    #[error("prev doesn't match expected CID: expected {expected}, got {actual:?}")]
+    PrevMismatch {
+        expected: String,
+        actual: Option<String>,
+    },
+    #[error("service challenge expired at {expires_at}, now is {now}")]
+    ChallengeExpired { expires_at: u64, now: u64 },
+    #[error("invalid did:key {0}: {1}")]
+    InvalidDidKey(String, String),
+    #[error("atproto_pds service must be of type AtprotoPersonalDataServer, got {0:?}")]
+    InvalidPdsServiceType(String),
+    #[error("operation {0} is not allowed on this node")]
+    OperationNotAllowed(String),
 }
 
 #[derive(Error, Clone, Debug)]
@@ -166,26 +195,92 @@ pub enum TransactionError {
     InvalidNonce(u64),
     #[error("missing account's public key")]
     MissingKey,
+    #[error("missing expiry of the service challenge")]
+    MissingChallengeExpiry,
+    #[error("transaction id doesn't match operation id: {0} != {1}")]
+    IdMismatch(String, String),
     #[error("encoding failed with: {0}")]
     EncodingFailed(String),
     #[error("signing failed")]
     SigningFailed,
     #[error("missing sender")]
     MissingSender,
+    #[error("unknown transaction blob version: {0}")]
+    UnknownBlobVersion(u8),
+    #[error("decoding failed with: {0}")]
+    DecodingFailed(String),
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("idempotency key {0} was already used for a different transaction")]
+    IdempotencyKeyReused(String),
 }
 
 #[derive(Error, Clone, Debug)]
 pub enum AccountError {
     #[error("nonce doesn't match: {0} != {1}")]
     NonceError(u64, u64),
+    #[error("nonce of account {0} would overflow")]
+    NonceOverflow(String),
+    #[error("prev doesn't match account head: {0:?} != {1:?}")]
+    PrevError(Option<String>, Option<String>),
     #[error("transaction id doesn't match operation id: {0} != {1}")]
     AccountIdError(String, String),
     #[error("transaction key doesn't match operation key")]
     AccountKeyError(String, String),
     #[error("transaction id doesn't match account id: {0} != {1}")]
     TransactionIdError(String, String),
+    #[error("key {0} has no authority to override the operations after prev")]
+    InsufficientAuthority(String),
+    #[error("account state after {0} to fork from is unknown")]
+    UnknownForkBase(String),
+    #[error("account {0} is deactivated")]
+    Deactivated(String),
     #[error("invalid key")]
     InvalidKey,
+    #[error("account {0} is not a service")]
+    NotAService(String),
+    #[error("invalid service challenge: {0}")]
+    InvalidChallenge(String),
     #[error("transaction error: {0}")]
     TransactionError(#[from] TransactionError),
 }
+
+#[derive(Error, Clone, Debug)]
+pub enum DidError {
+    #[error("operation log is empty")]
+    EmptyLog,
+    #[error("unsupported operation type: {0}")]
+    UnsupportedOperation(String),
+    #[error("invalid key {0}: {1}")]
+    InvalidKey(String, String),
+    #[error("operation {0} is not signed by a valid rotation key")]
+    InvalidSignature(usize),
+    #[error(
+        "operation {0} is not signed by a higher-authority key than the operations it overrides"
+    )]
+    InsufficientAuthority(usize),
+    #[error("operation {0} overrides operations outside of the recovery window")]
+    RecoveryWindowExpired(usize),
+    #[error(
+        "operation {index} does not chain to its predecessor: expected {expected:?}, got {actual:?}"
+    )]
+    BrokenChain {
+        index: usize,
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+    #[error("encoding failed with: {0}")]
+    EncodingFailed(String),
+    #[error("account {0} has been modified since its genesis operation")]
+    NotGenesis(String),
+    #[error("accounts of different DIDs can not be merged: {0} and {1}")]
+    DidMismatch(String, String),
+    #[error("account {did} has conflicting states at nonce {nonce}")]
+    ConflictingState { did: String, nonce: u64 },
+    #[error("DID {did} does not use the did:{expected} method")]
+    MethodMismatch { expected: String, did: String },
+    #[error("operation {0} is malformed: {1}")]
+    MalformedOperation(usize, String),
+    #[error("account {did} is not bound to its genesis operation, which derives {derived}")]
+    UnboundDid { did: String, derived: String },
+}
@@ -1,4 +1,7 @@
-use crate::{CryptoError, Result, errors::SignatureError};
+use crate::{
+    CryptoError, Result,
+    errors::{ParseError, SignatureError, VerificationError},
+};
 use pkcs8::{AlgorithmIdentifierRef, ObjectIdentifier};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -30,6 +33,62 @@ impl CryptoAlgorithm {
             Self::CosmosAdr36,
         ]
     }
+
+    /// Determines the algorithm of a `did:key:z...` string from the multicodec prefix of its
+    /// base58 decoded key.
+    pub fn from_did_key(did: &str) -> Result<Self> {
+        let decoded = decode_did_key(did)?;
+        Self::from_multicodec_prefix(&decoded)
+    }
+
+    /// Returns the varint encoded multicodec prefix of public keys of this algorithm in `did:key`s.
+    /// EIP-191 and ADR-36 keys have no registered multicodec, so they can not be `did:key`s.
+    pub const fn multicodec_prefix(&self) -> Option<&'static [u8]> {
+        match self {
+            CryptoAlgorithm::Ed25519 => Some(&ED25519_MULTICODEC),
+            CryptoAlgorithm::Secp256k1 => Some(&SECP256K1_MULTICODEC),
+            CryptoAlgorithm::Secp256r1 => Some(&SECP256R1_MULTICODEC),
+            CryptoAlgorithm::Eip191 | CryptoAlgorithm::CosmosAdr36 => None,
+        }
+    }
+
+    /// Determines the algorithm from the (varint encoded) multicodec prefix of `bytes`.
+    pub(crate) fn from_multicodec_prefix(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 2 {
+            return Err(CryptoError::ParseError(ParseError::GeneralError(
+                "Decoded data too short to contain codec".to_string(),
+            )));
+        }
+
+        Self::all()
+            .into_iter()
+            .find(|algorithm| {
+                algorithm.multicodec_prefix().is_some_and(|prefix| bytes.starts_with(prefix))
+            })
+            .ok_or_else(|| {
+                CryptoError::VerificationError(VerificationError::NotImplementedError(
+                    format!("Unsupported codec: {:02x}{:02x}", bytes[0], bytes[1]),
+                    "from_did".to_string(),
+                ))
+            })
+    }
+}
+
+/// Decodes the multibase (base58btc) part of a `did:key:z...` string, returning the
+/// multicodec-prefixed key bytes.
+pub(crate) fn decode_did_key(did: &str) -> Result<Vec<u8>> {
+    let prefix = "did:key:z";
+    let Some(encoded) = did.strip_prefix(prefix) else {
+        return Err(CryptoError::ParseError(ParseError::GeneralError(format!(
+            "Invalid DID format: expected '{prefix}...' but got '{did}'"
+        ))));
+    };
+
+    bs58::decode(encoded).into_vec().map_err(|e| {
+        CryptoError::ParseError(ParseError::GeneralError(format!(
+            "Failed to decode base58: {e}"
+        )))
+    })
 }
 
 impl std::str::FromStr for CryptoAlgorithm {
@@ -53,6 +112,11 @@ impl std::fmt::Display for CryptoAlgorithm {
     }
 }
 
+/// Varint encoded multicodec prefixes of the public key types supported in `did:key`s.
+pub const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+pub const SECP256K1_MULTICODEC: [u8; 2] = [0xe7, 0x01];
+pub const SECP256R1_MULTICODEC: [u8; 2] = [0x80, 0x24];
+
 pub const ED25519_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
 pub const ELLIPTIC_CURVE_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
 pub const ECDSA_SHA256_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
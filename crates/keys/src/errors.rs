@@ -22,6 +22,13 @@ pub enum SignatureError {
     #[error("Malformed signature")]
     MalformedSignError,
 
+    #[error("Invalid {algorithm} signature length: expected {expected} bytes, got {actual}")]
+    InvalidSignatureLength {
+        algorithm: String,
+        expected: usize,
+        actual: usize,
+    },
+
     #[error("Algorithm Error: {0}")]
     AlgorithmError(String),
 
@@ -33,6 +40,9 @@ pub enum SignatureError {
 
     #[error("Cosmos Error: {0}")]
     CosmosError(String),
+
+    #[error("{0} signatures can not be represented in PLC operations")]
+    UnsupportedForPlc(String),
 }
 
 #[derive(Error, Clone, Debug)]
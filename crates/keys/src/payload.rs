@@ -23,14 +23,11 @@ impl TryFrom<String> for CryptoPayload {
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         if value.starts_with("did:key:") {
-            let vk = VerifyingKey::from_did(&value)
-                .expect("Failed to parse VerifyingKey from CryptoPayload");
-
+            let vk = VerifyingKey::from_did(&value).map_err(|e| e.to_string())?;
             Ok(vk.into())
         } else {
             // TAG(DID)
-            let sig = Signature::from_plc_signature(&value)
-                .expect("Failed to parse Signature from CryptoPayload");
+            let sig = Signature::from_plc_signature(&value).map_err(|e| e.to_string())?;
             Ok(sig.into())
         }
     }
@@ -6,12 +6,13 @@ use pkcs8::{
     AlgorithmIdentifierRef, SecretDocument,
     der::{Decode, asn1::OctetStringRef, zeroize::Zeroize},
 };
-use prism_serde::base64::{FromBase64, ToBase64};
+use prism_serde::base64::{ToBase64, decode_base64url_padded_or_not};
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     fmt::{Display, Formatter},
 };
+use subtle::ConstantTimeEq;
 use utoipa::{
     PartialSchema, ToSchema,
     openapi::{RefOr, Schema},
@@ -22,7 +23,25 @@ use crate::{
     der::SignatureInfoRef, payload::CryptoPayload,
 };
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+/// Length of the raw signatures of all supported algorithms: r || s for ECDSA, R || s for
+/// Ed25519.
+const SIGNATURE_LENGTH: usize = 64;
+
+/// Rejects signature bytes of the wrong length up front, so that e.g. truncated signatures are
+/// reported as such instead of by a curve-specific parsing error.
+fn check_signature_length(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<()> {
+    if bytes.len() != SIGNATURE_LENGTH {
+        return Err(SignatureError::InvalidSignatureLength {
+            algorithm: algorithm.to_string(),
+            expected: SIGNATURE_LENGTH,
+            actual: bytes.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(try_from = "CryptoPayload", into = "CryptoPayload")]
 pub enum Signature {
     Secp256k1(Secp256k1Signature),
@@ -30,6 +49,17 @@ pub enum Signature {
     Secp256r1(Secp256r1Signature),
 }
 
+/// Compares the raw signature bytes in constant time, so that comparing a submitted signature
+/// against an expected one does not reveal how many leading bytes matched.
+impl PartialEq for Signature {
+    fn eq(&self, other: &Self) -> bool {
+        self.algorithm() == other.algorithm()
+            && bool::from(self.to_bytes().ct_eq(&other.to_bytes()))
+    }
+}
+
+impl Eq for Signature {}
+
 impl Signature {
     pub fn to_bytes(&self) -> Vec<u8> {
         match self {
@@ -39,29 +69,47 @@ impl Signature {
         }
     }
 
-    pub fn to_plc_signature(&self) -> String {
-        let sig_bytes = self.to_bytes();
-        sig_bytes.to_base64()
+    /// Encodes the signature as base64url, as used in PLC operations. Only ECDSA signatures
+    /// (secp256k1 and secp256r1) are supported by PLC.
+    pub fn to_plc_signature(&self) -> Result<String> {
+        match self {
+            Signature::Secp256k1(_) | Signature::Secp256r1(_) => Ok(self.to_bytes().to_base64()),
+            Signature::Ed25519(_) => {
+                Err(SignatureError::UnsupportedForPlc(self.algorithm().to_string()).into())
+            }
+        }
     }
 
+    /// Decodes a base64url signature of a PLC operation, padded or not, as some PLC tooling pads.
+    /// Only the decoded bytes are returned, the operation's CID is still derived from the
+    /// signature string as given.
     // TODO(DID): other blessed curve
     pub fn from_plc_signature(s: &str) -> Result<Self> {
-        // TODO(DID): unwrap
-        let bytes = Vec::from_base64(s).unwrap();
+        let bytes =
+            decode_base64url_padded_or_not(s).map_err(|_| SignatureError::MalformedSignError)?;
         Self::from_algorithm_and_bytes(CryptoAlgorithm::Secp256k1, &bytes)
     }
 
     pub fn from_algorithm_and_bytes(algorithm: CryptoAlgorithm, bytes: &[u8]) -> Result<Self> {
         match algorithm {
-            CryptoAlgorithm::Ed25519 => Ed25519Signature::try_from(bytes)
-                .map(Signature::Ed25519)
-                .map_err(|e| SignatureError::AlgorithmError(e.to_string()).into()),
-            CryptoAlgorithm::Secp256k1 => Secp256k1Signature::from_slice(bytes)
-                .map(Signature::Secp256k1)
-                .map_err(|e| SignatureError::AlgorithmError(e.to_string()).into()),
-            CryptoAlgorithm::Secp256r1 => Secp256r1Signature::from_slice(bytes)
-                .map(Signature::Secp256r1)
-                .map_err(|e| SignatureError::AlgorithmError(e.to_string()).into()),
+            CryptoAlgorithm::Ed25519 => {
+                check_signature_length(algorithm, bytes)?;
+                Ed25519Signature::try_from(bytes)
+                    .map(Signature::Ed25519)
+                    .map_err(|e| SignatureError::AlgorithmError(e.to_string()).into())
+            }
+            CryptoAlgorithm::Secp256k1 => {
+                check_signature_length(algorithm, bytes)?;
+                Secp256k1Signature::from_slice(bytes)
+                    .map(Signature::Secp256k1)
+                    .map_err(|e| SignatureError::AlgorithmError(e.to_string()).into())
+            }
+            CryptoAlgorithm::Secp256r1 => {
+                check_signature_length(algorithm, bytes)?;
+                Secp256r1Signature::from_slice(bytes)
+                    .map(Signature::Secp256r1)
+                    .map_err(|e| SignatureError::AlgorithmError(e.to_string()).into())
+            }
             CryptoAlgorithm::Eip191 => {
                 Err(SignatureError::UnsupportedFormatError("EIP-191".to_string()).into())
             }
@@ -14,6 +14,7 @@ use pkcs8::{
     der::{Decode, pem::PemLabel},
 };
 use std::path::Path;
+use subtle::ConstantTimeEq;
 
 use sha2::Digest as _;
 
@@ -247,10 +248,15 @@ impl SigningKey {
     }
 }
 
+/// Compares secret key material, which must never leak through timing. The ECDSA keys of `k256`
+/// and `p256` already compare in constant time, ed25519 keys are compared via
+/// [`ConstantTimeEq`].
 impl PartialEq for SigningKey {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (SigningKey::Ed25519(a), SigningKey::Ed25519(b)) => a.as_bytes() == b.as_bytes(),
+            (SigningKey::Ed25519(a), SigningKey::Ed25519(b)) => {
+                a.as_bytes().ct_eq(b.as_bytes()).into()
+            }
             (SigningKey::Secp256k1(a), SigningKey::Secp256k1(b)) => a == b,
             (SigningKey::Secp256r1(a), SigningKey::Secp256r1(b)) => a == b,
             (SigningKey::Eip191(a), SigningKey::Eip191(b)) => a == b,
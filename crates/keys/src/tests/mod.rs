@@ -1,10 +1,13 @@
 #[cfg(test)]
 mod key_tests {
-    use crate::errors::{CryptoError, VerificationError};
-    use base64::{Engine as _, alphabet, engine::general_purpose};
+    use crate::errors::{CryptoError, SignatureError, VerificationError};
     use ed25519_consensus::SigningKey as Ed25519SigningKey;
-    use prism_serde::base64::{FromBase64, ToBase64};
+    use prism_serde::{
+        base64::{FromBase64, ToBase64},
+        hex::ToHex,
+    };
     use rand::rngs::OsRng;
+    use std::collections::HashSet;
 
     use tempfile::tempdir;
 
@@ -13,16 +16,42 @@ mod key_tests {
     #[test]
     fn reparsed_plc_signature() {
         let signature = "F0_AgX0tghOjtCMPsMGxHP-8JL11GiR8ikgf68XofQAa1vgEZvEe9VBWFko8isAjT5pkcZOf0GBPAq1cujBNHw".to_string();
-        let sig_bytes =
-            general_purpose::GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::NO_PAD)
-                .decode(&signature)
-                .unwrap();
-        let parsed_signature =
-            Signature::from_algorithm_and_bytes(CryptoAlgorithm::Secp256k1, &sig_bytes).unwrap();
-        let reparsed_signature = parsed_signature.to_plc_signature();
+        let parsed_signature = Signature::from_plc_signature(&signature).unwrap();
+        let reparsed_signature = parsed_signature.to_plc_signature().unwrap();
         assert_eq!(signature, reparsed_signature);
     }
 
+    #[test]
+    fn test_plc_signature_tolerates_padding() {
+        let unpadded = "F0_AgX0tghOjtCMPsMGxHP-8JL11GiR8ikgf68XofQAa1vgEZvEe9VBWFko8isAjT5pkcZOf0GBPAq1cujBNHw";
+        let padded = format!("{}==", unpadded);
+        assert_eq!(
+            Signature::from_plc_signature(&padded).unwrap(),
+            Signature::from_plc_signature(unpadded).unwrap()
+        );
+        assert!(matches!(
+            Signature::from_plc_signature("not base64!"),
+            Err(CryptoError::SignatureError(
+                SignatureError::MalformedSignError
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_plc_signature_rejects_unsupported_algorithm() {
+        let signature = SigningKey::new_ed25519().sign(b"plc").unwrap();
+        let result = signature.to_plc_signature();
+        assert!(matches!(
+            result,
+            Err(CryptoError::SignatureError(
+                SignatureError::UnsupportedForPlc(_)
+            ))
+        ));
+
+        let signature = SigningKey::new_secp256r1().sign(b"plc").unwrap();
+        assert!(signature.to_plc_signature().is_ok());
+    }
+
     #[test]
     fn test_reparsed_verifying_keys_are_equal_to_original() {
         let verifying_key_ed25519 = SigningKey::new_ed25519().verifying_key();
@@ -228,6 +257,56 @@ mod key_tests {
         // EIP-191 and Cosmos ADR-36 are using SECP256K1 signatures and are omitted here
     }
 
+    #[test]
+    fn test_signature_length_is_validated_per_algorithm() {
+        let message = b"test message";
+
+        for algorithm in [
+            CryptoAlgorithm::Ed25519,
+            CryptoAlgorithm::Secp256k1,
+            CryptoAlgorithm::Secp256r1,
+        ] {
+            let signature =
+                SigningKey::new_with_algorithm(algorithm).unwrap().sign(message).unwrap();
+            let bytes = signature.to_bytes();
+            assert_eq!(bytes.len(), 64);
+            assert_eq!(
+                Signature::from_algorithm_and_bytes(algorithm, &bytes).unwrap(),
+                signature
+            );
+
+            let truncated = &bytes[..63];
+            let extended = [bytes.as_slice(), &[0]].concat();
+            for wrong in [&[][..], truncated, &extended] {
+                let result = Signature::from_algorithm_and_bytes(algorithm, wrong);
+                assert!(
+                    matches!(
+                        result,
+                        Err(CryptoError::SignatureError(
+                            SignatureError::InvalidSignatureLength {
+                                expected: 64,
+                                actual,
+                                ..
+                            }
+                        )) if actual == wrong.len()
+                    ),
+                    "{algorithm} signature of {} bytes was not rejected by length",
+                    wrong.len()
+                );
+            }
+        }
+
+        // truncated PLC signatures are reported as such
+        let truncated =
+            "F0_AgX0tghOjtCMPsMGxHP-8JL11GiR8ikgf68XofQAa1vgEZvEe9VBWFko8isAjT5pkcZOf0GBPAq1cujBN";
+        assert!(matches!(
+            Signature::from_plc_signature(truncated),
+            Err(CryptoError::SignatureError(
+                SignatureError::InvalidSignatureLength { .. }
+            ))
+        ));
+    }
+
     #[test]
     fn test_reparsed_der_signatures_are_equal_to_original() {
         let message = b"test message";
@@ -507,4 +586,200 @@ mod key_tests {
         let loaded_existing_key = result.unwrap();
         assert_eq!(loaded_existing_key, existing_key);
     }
+
+    #[test]
+    fn test_crypto_algorithm_from_did_key() {
+        let secp256k1_did = "did:key:zQ3shYxgqcVTCgB5z21jid9vfJy1GkFUySPMzLQDPUtdN5qPe";
+        assert_eq!(
+            CryptoAlgorithm::from_did_key(secp256k1_did).unwrap(),
+            CryptoAlgorithm::Secp256k1
+        );
+
+        let ed25519_did = SigningKey::new_ed25519().verifying_key().to_did().unwrap();
+        assert!(ed25519_did.starts_with("did:key:z6Mk"));
+        assert_eq!(
+            CryptoAlgorithm::from_did_key(&ed25519_did).unwrap(),
+            CryptoAlgorithm::Ed25519
+        );
+
+        let secp256r1_did = SigningKey::new_secp256r1().verifying_key().to_did().unwrap();
+        assert!(secp256r1_did.starts_with("did:key:zDn"));
+        assert_eq!(
+            CryptoAlgorithm::from_did_key(&secp256r1_did).unwrap(),
+            CryptoAlgorithm::Secp256r1
+        );
+    }
+
+    #[test]
+    fn test_crypto_algorithm_from_did_key_invalid() {
+        // bls12_381-g1-pub multicodec, which prism does not support
+        let unsupported = format!(
+            "did:key:z{}",
+            bs58::encode([0xea, 0x01, 0x01]).into_string()
+        );
+        assert!(matches!(
+            CryptoAlgorithm::from_did_key(&unsupported),
+            Err(CryptoError::VerificationError(
+                VerificationError::NotImplementedError(_, _)
+            ))
+        ));
+
+        assert!(CryptoAlgorithm::from_did_key("did:web:example.com").is_err());
+        assert!(CryptoAlgorithm::from_did_key("did:key:z").is_err());
+    }
+
+    #[test]
+    fn test_verifying_key_did_roundtrip() {
+        for algorithm in CryptoAlgorithm::all() {
+            let vk = SigningKey::new_with_algorithm(algorithm).unwrap().verifying_key();
+            if algorithm.multicodec_prefix().is_none() {
+                // wallet keys must never leave prism as did:keys
+                assert!(vk.to_did().is_err());
+                continue;
+            }
+            let did = vk.to_did().unwrap();
+
+            assert_eq!(CryptoAlgorithm::from_did_key(&did).unwrap(), algorithm);
+            assert_eq!(VerifyingKey::from_did(&did).unwrap(), vk);
+        }
+    }
+
+    #[test]
+    fn test_secp256r1_did_key_roundtrip() {
+        // P-256 test vectors of the did:key specification
+        for did in [
+            "did:key:zDnaerDaTF5BXEavCrfRZEk316dpbLsfPDZ3WJ5hRTPFU2169",
+            "did:key:zDnaerx9CtbPJ1q36T5Ln5wYt3MQYeGRG5ehnPAmxcf5mDZpv",
+        ] {
+            let vk = VerifyingKey::from_did(did).unwrap();
+            assert_eq!(vk.algorithm(), CryptoAlgorithm::Secp256r1);
+            assert_eq!(vk.to_did().unwrap(), did);
+
+            let compressed = vk.to_bytes();
+            assert_eq!(compressed.len(), 33);
+            assert!(matches!(compressed[0], 0x02 | 0x03));
+        }
+
+        // did:keys hold the compressed point only
+        let VerifyingKey::Secp256r1(vk) = SigningKey::new_secp256r1().verifying_key() else {
+            panic!("expected a secp256r1 key");
+        };
+        let uncompressed = [
+            CryptoAlgorithm::Secp256r1.multicodec_prefix().unwrap(),
+            vk.to_encoded_point(false).as_bytes(),
+        ]
+        .concat();
+        let did = format!("did:key:z{}", bs58::encode(uncompressed).into_string());
+        assert!(VerifyingKey::from_did(&did).is_err());
+    }
+
+    #[test]
+    fn test_verifying_key_from_did_rejects_wrong_key_length() {
+        for algorithm in CryptoAlgorithm::all() {
+            let Some(prefix) = algorithm.multicodec_prefix() else {
+                continue;
+            };
+            let vk = SigningKey::new_with_algorithm(algorithm).unwrap().verifying_key();
+            let truncated = [prefix, &vk.to_bytes()[1..]].concat();
+            let did = format!("did:key:z{}", bs58::encode(truncated).into_string());
+
+            assert!(VerifyingKey::from_did(&did).is_err());
+        }
+    }
+
+    #[test]
+    fn test_verifying_key_from_str() {
+        for algorithm in CryptoAlgorithm::all() {
+            let Some(prefix) = algorithm.multicodec_prefix() else {
+                continue;
+            };
+            let vk = SigningKey::new_with_algorithm(algorithm).unwrap().verifying_key();
+            let did = vk.to_did().unwrap();
+            let multibase = did.strip_prefix("did:key:").unwrap();
+            let multicodec_hex = [prefix, &vk.to_bytes()].concat().to_hex();
+
+            assert_eq!(did.parse::<VerifyingKey>().unwrap(), vk);
+            assert_eq!(multibase.parse::<VerifyingKey>().unwrap(), vk);
+            assert_eq!(multicodec_hex.parse::<VerifyingKey>().unwrap(), vk);
+        }
+
+        // raw 32-byte hex keys are Ed25519 keys
+        let ed25519_vk = SigningKey::new_ed25519().verifying_key();
+        assert_eq!(
+            ed25519_vk.to_bytes().to_hex().parse::<VerifyingKey>().unwrap(),
+            ed25519_vk
+        );
+
+        for unrecognized in ["", "not a key", "did:web:example.com", "0xabcd"] {
+            assert!(matches!(
+                unrecognized.parse::<VerifyingKey>(),
+                Err(CryptoError::ParseError(_))
+            ));
+        }
+        // recognized, but malformed
+        assert!("zInvalid0".parse::<VerifyingKey>().is_err());
+        assert!("abc".parse::<VerifyingKey>().is_err());
+    }
+
+    #[test]
+    fn test_point_encodings_of_the_same_key_are_equal() {
+        for sk in [SigningKey::new_secp256k1(), SigningKey::new_secp256r1()] {
+            let vk = sk.verifying_key();
+            let uncompressed = match &vk {
+                VerifyingKey::Secp256k1(vk) => vk.to_encoded_point(false).as_bytes().to_vec(),
+                VerifyingKey::Secp256r1(vk) => vk.to_encoded_point(false).as_bytes().to_vec(),
+                _ => unreachable!("created a secp256k1 or secp256r1 key"),
+            };
+            let compressed = vk.to_bytes();
+            assert_ne!(uncompressed, compressed);
+
+            let from_uncompressed =
+                VerifyingKey::from_algorithm_and_bytes(vk.algorithm(), &uncompressed).unwrap();
+            let from_compressed =
+                VerifyingKey::from_algorithm_and_bytes(vk.algorithm(), &compressed).unwrap();
+
+            assert_eq!(from_uncompressed, from_compressed);
+            assert_eq!(from_uncompressed.to_bytes(), compressed);
+            assert!([from_compressed].contains(&from_uncompressed));
+            assert!(HashSet::from([vk]).contains(&from_uncompressed));
+        }
+    }
+
+    #[test]
+    fn test_constant_time_equality() {
+        let message = b"test message";
+        let sk = SigningKey::new_secp256k1();
+        let vk = sk.verifying_key();
+
+        // equal bytes
+        let reparsed_vk =
+            VerifyingKey::from_algorithm_and_bytes(vk.algorithm(), &vk.to_bytes()).unwrap();
+        assert_eq!(reparsed_vk, vk);
+        // unequal bytes of the same length
+        assert_ne!(SigningKey::new_secp256k1().verifying_key(), vk);
+        // equal bytes, but a different algorithm
+        let eip191_vk =
+            VerifyingKey::from_algorithm_and_bytes(CryptoAlgorithm::Eip191, &vk.to_bytes())
+                .unwrap();
+        assert_ne!(eip191_vk, vk);
+
+        let signature = sk.sign(message).unwrap();
+        let mut sig_bytes = signature.to_bytes();
+        assert_eq!(
+            Signature::from_algorithm_and_bytes(signature.algorithm(), &sig_bytes).unwrap(),
+            signature
+        );
+        *sig_bytes.last_mut().unwrap() ^= 1;
+        assert_ne!(
+            Signature::from_algorithm_and_bytes(signature.algorithm(), &sig_bytes).unwrap(),
+            signature
+        );
+
+        let ed25519_sk = SigningKey::new_ed25519();
+        let reparsed_sk =
+            SigningKey::from_algorithm_and_bytes(ed25519_sk.algorithm(), &ed25519_sk.to_bytes())
+                .unwrap();
+        assert_eq!(reparsed_sk, ed25519_sk);
+        assert_ne!(SigningKey::new_ed25519(), ed25519_sk);
+    }
 }
@@ -25,19 +25,24 @@ use std::{
     borrow::Cow,
     hash::{Hash, Hasher},
     path::Path,
+    str::FromStr,
 };
+use subtle::ConstantTimeEq;
 use utoipa::{
     PartialSchema, ToSchema,
     openapi::{RefOr, Schema},
 };
 
 use crate::{
-    CryptoAlgorithm, Signature, SigningKey, cosmos::cosmos_adr36_hash_message,
-    payload::CryptoPayload,
+    CryptoAlgorithm, Signature, SigningKey, algorithm::decode_did_key,
+    cosmos::cosmos_adr36_hash_message, payload::CryptoPayload,
+};
+use prism_serde::{
+    base64::{FromBase64, ToBase64},
+    hex::FromHex,
 };
-use prism_serde::base64::{FromBase64, ToBase64};
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(try_from = "CryptoPayload", into = "CryptoPayload")]
 /// Represents a public key.
 pub enum VerifyingKey {
@@ -53,6 +58,35 @@ pub enum VerifyingKey {
     CosmosAdr36(Secp256k1VerifyingKey),
 }
 
+/// Compares the encoded keys in constant time. Public keys are not secret, but they are compared
+/// against the signer of a transaction to authorize it, so the comparison must not reveal how much
+/// of a key matched.
+///
+/// Points are compared in their fixed-size compressed encoding (see [`VerifyingKey::to_bytes`]),
+/// so a key parsed from its uncompressed encoding equals the same key parsed from its compressed
+/// one.
+impl PartialEq for VerifyingKey {
+    fn eq(&self, other: &Self) -> bool {
+        let equal = match (self, other) {
+            (VerifyingKey::Ed25519(vk), VerifyingKey::Ed25519(other)) => {
+                vk.as_bytes().as_slice().ct_eq(other.as_bytes().as_slice())
+            }
+            (VerifyingKey::Secp256k1(vk), VerifyingKey::Secp256k1(other))
+            | (VerifyingKey::Eip191(vk), VerifyingKey::Eip191(other))
+            | (VerifyingKey::CosmosAdr36(vk), VerifyingKey::CosmosAdr36(other)) => {
+                vk.to_encoded_point(true).as_bytes().ct_eq(other.to_encoded_point(true).as_bytes())
+            }
+            (VerifyingKey::Secp256r1(vk), VerifyingKey::Secp256r1(other)) => {
+                vk.to_encoded_point(true).as_bytes().ct_eq(other.to_encoded_point(true).as_bytes())
+            }
+            _ => return false,
+        };
+        bool::from(equal)
+    }
+}
+
+impl Eq for VerifyingKey {}
+
 impl Hash for VerifyingKey {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
@@ -190,120 +224,46 @@ impl VerifyingKey {
         }
     }
 
-    /// Converts the verifying key to a DID string, only supports Ed25519 and P256.
+    /// Converts the verifying key to a `did:key` string. Fails for EIP-191 and ADR-36 keys, see
+    /// [`CryptoAlgorithm::multicodec_prefix`].
     pub fn to_did(&self) -> Result<String> {
-        let prefix = String::from("did:key:");
-        match self {
-            VerifyingKey::Ed25519(vk) => {
-                let codec: &[u8] = &[0xed, 0x1];
-                let data = [codec, vk.as_bytes()].concat();
-                Ok(format!(
-                    "{prefix}z{}",
-                    bs58::encode(data).with_alphabet(Alphabet::BITCOIN).into_string()
-                ))
-            }
-            VerifyingKey::Secp256r1(vk) => {
-                let codec: &[u8] = &[0x80, 0x24];
-                let data = [codec, vk.to_encoded_point(true).as_ref()].concat();
-                Ok(format!(
-                    "{prefix}z{}",
-                    bs58::encode(data).with_alphabet(Alphabet::BITCOIN).into_string()
-                ))
-            }
-            VerifyingKey::Secp256k1(vk) => {
-                let codec: &[u8] = &[0xe7, 0x1];
-                let data = [codec, vk.to_encoded_point(true).as_ref()].concat();
-                Ok(format!(
-                    "{prefix}z{}",
-                    bs58::encode(data).with_alphabet(Alphabet::BITCOIN).into_string()
-                ))
-            }
-            _ => Err(CryptoError::VerificationError(
-                VerificationError::NotImplementedError(
-                    "Unsupported key type".to_string(),
-                    "to_did".to_string(),
-                ),
-            )),
-        }
+        let algorithm = self.algorithm();
+        let prefix = algorithm.multicodec_prefix().ok_or_else(|| {
+            VerificationError::NotImplementedError(
+                format!("{algorithm} keys have no did:key representation"),
+                "to_did".to_string(),
+            )
+        })?;
+        let data = [prefix, &self.to_bytes()].concat();
+        Ok(format!(
+            "did:key:z{}",
+            bs58::encode(data).with_alphabet(Alphabet::BITCOIN).into_string()
+        ))
     }
 
-    /// Parses a DID string into a verifying key, only supports Ed25519 and P256.
+    /// Parses a `did:key` string into a verifying key, the inverse of [`VerifyingKey::to_did`].
     pub fn from_did(did: &str) -> Result<Self> {
-        let prefix = "did:key:z";
-        if !did.starts_with(prefix) {
+        Self::from_multicodec_bytes(&decode_did_key(did)?)
+    }
+
+    /// Parses a key prefixed with the multicodec code of its algorithm, as encoded in `did:key`s.
+    fn from_multicodec_bytes(decoded: &[u8]) -> Result<Self> {
+        let algorithm = CryptoAlgorithm::from_multicodec_prefix(decoded)?;
+        let key_bytes = &decoded[algorithm.multicodec_prefix().unwrap_or_default().len()..];
+
+        // 32-byte key for Ed25519, 33-byte compressed key otherwise
+        let expected_len = match algorithm {
+            CryptoAlgorithm::Ed25519 => 32,
+            _ => 33,
+        };
+        if key_bytes.len() != expected_len {
             return Err(CryptoError::ParseError(ParseError::GeneralError(format!(
-                "Invalid DID format: expected '{prefix}...' but got '{did}'"
+                "Invalid {algorithm} key length: expected {expected_len} bytes, got {}",
+                key_bytes.len()
             ))));
         }
 
-        let encoded = &did[prefix.len()..];
-        let decoded = bs58::decode(encoded).into_vec().map_err(|e| {
-            CryptoError::ParseError(ParseError::GeneralError(format!(
-                "Failed to decode base58: {e}"
-            )))
-        })?;
-
-        if decoded.len() < 2 {
-            return Err(CryptoError::ParseError(ParseError::GeneralError(
-                "Decoded data too short to contain codec".to_string(),
-            )));
-        }
-
-        match &decoded[0..2] {
-            [0xed, 0x1] => {
-                // Ed25519
-                if decoded.len() != 34 {
-                    // 2-byte codec + 32-byte key
-                    return Err(CryptoError::ParseError(ParseError::GeneralError(format!(
-                        "Invalid Ed25519 key length: expected 34 bytes, got {}",
-                        decoded.len()
-                    ))));
-                }
-                let key_bytes = &decoded[2..];
-                let vk = Ed25519VerifyingKey::try_from(key_bytes).map_err(|e| {
-                    CryptoError::VerificationError(VerificationError::VerifyError(
-                        "ed25519".to_string(),
-                        e.to_string(),
-                    ))
-                })?;
-                Ok(VerifyingKey::Ed25519(vk))
-            }
-            [0x80, 0x24] => {
-                // Secp256r1
-                if decoded.len() != 35 {
-                    // 2-byte codec + 33-byte compressed key
-                    return Err(CryptoError::ParseError(ParseError::GeneralError(format!(
-                        "Invalid Secp256r1 key length: expected 35 bytes, got {}",
-                        decoded.len()
-                    ))));
-                }
-                let key_bytes = &decoded[2..];
-                let vk = Secp256r1VerifyingKey::from_sec1_bytes(key_bytes).map_err(|e| {
-                    CryptoError::VerificationError(VerificationError::VerifyError(
-                        "secp256r1".to_string(),
-                        e.to_string(),
-                    ))
-                })?;
-                Ok(VerifyingKey::Secp256r1(vk))
-            }
-            [0xe7, 0x1] => {
-                //TODO(DID): len check
-                let key_bytes = &decoded[2..];
-                let vk = Secp256k1VerifyingKey::from_sec1_bytes(key_bytes).map_err(|e| {
-                    CryptoError::VerificationError(VerificationError::VerifyError(
-                        "secp256k1".to_string(),
-                        e.to_string(),
-                    ))
-                })?;
-                Ok(VerifyingKey::Secp256k1(vk))
-            }
-            _ => Err(CryptoError::VerificationError(
-                VerificationError::NotImplementedError(
-                    format!("Unsupported codec: {:02x}{:02x}", decoded[0], decoded[1]),
-                    "from_did".to_string(),
-                ),
-            )),
-        }
+        Self::from_algorithm_and_bytes(algorithm, key_bytes)
     }
 
     fn to_spki_der_doc(&self) -> Result<Document> {
@@ -472,6 +432,36 @@ impl TryFrom<String> for VerifyingKey {
     }
 }
 
+/// Parses a `did:key`, a base58btc multibase key (`z` prefix) as in `did:key`s, or a hex key. Hex
+/// keys are either 32-byte Ed25519 keys or, like multibase keys, prefixed with their multicodec.
+impl FromStr for VerifyingKey {
+    type Err = CryptoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("did:key:") {
+            Self::from_did(s)
+        } else if let Some(encoded) = s.strip_prefix('z') {
+            let bytes = bs58::decode(encoded)
+                .into_vec()
+                .map_err(|e| ParseError::GeneralError(format!("Failed to decode base58: {e}")))?;
+            Self::from_multicodec_bytes(&bytes)
+        } else if !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            let bytes = Vec::<u8>::from_hex(s)
+                .map_err(|e| ParseError::GeneralError(format!("Failed to decode hex: {e}")))?;
+            if bytes.len() == 32 {
+                Self::from_algorithm_and_bytes(CryptoAlgorithm::Ed25519, &bytes)
+            } else {
+                Self::from_multicodec_bytes(&bytes)
+            }
+        } else {
+            Err(ParseError::GeneralError(format!(
+                "Unrecognized key format, expected did:key, multibase or hex: {s}"
+            ))
+            .into())
+        }
+    }
+}
+
 impl std::fmt::Display for VerifyingKey {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let encoded = self.to_bytes().to_base64();
@@ -1,4 +1,5 @@
 use anyhow::{Result, anyhow};
+use prism_common::operation::DidConfig;
 use prism_da::DataAvailabilityLayer;
 use prism_keys::{SigningKey, VerifyingKey};
 use prism_presets::{
@@ -7,6 +8,7 @@ use prism_presets::{
 use prism_storage::Database;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     env,
     path::{Path, PathBuf},
     sync::Arc,
@@ -37,6 +39,14 @@ pub struct FullNodeConfig {
 
     /// Web server configuration for REST API endpoints.
     pub webserver: WebServerConfig,
+
+    /// Method of the DIDs the node accepts and serves, e.g. `prismtest` for `did:prismtest:`.
+    /// Has to match the method of the prover the node follows.
+    pub did: DidConfig,
+
+    /// Types of the operations accepted from clients, e.g. `["create_did",
+    /// "set_rotation_keys"]` for a DID-only node. All operations are accepted if unset.
+    pub allowed_operations: Option<HashSet<String>>,
 }
 
 impl Default for FullNodeConfig {
@@ -49,6 +59,8 @@ impl Default for FullNodeConfig {
                 .to_string_lossy()
                 .into_owned(),
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         }
     }
 }
@@ -86,6 +98,13 @@ pub struct ProverConfig {
 
     /// Web server configuration for REST API endpoints.
     pub webserver: WebServerConfig,
+
+    /// Method of the DIDs the prover creates and serves, e.g. `prismtest` for `did:prismtest:`.
+    pub did: DidConfig,
+
+    /// Types of the operations accepted from clients, e.g. `["create_did",
+    /// "set_rotation_keys"]` for a DID-only node. All operations are accepted if unset.
+    pub allowed_operations: Option<HashSet<String>>,
 }
 
 impl Default for ProverConfig {
@@ -100,6 +119,8 @@ impl Default for ProverConfig {
             max_epochless_gap: DEFAULT_MAX_EPOCHLESS_GAP,
             recursive_proofs: true,
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         }
     }
 }
@@ -137,6 +158,7 @@ pub fn create_prover_as_full_node(
         sequencer: SequencerOptions {
             signing_key: None,
             batcher_enabled: true,
+            allowed_operations: config.allowed_operations.clone(),
         },
         prover_engine: ProverEngineOptions {
             recursive_proofs: true,
@@ -182,6 +204,7 @@ pub fn create_prover_as_prover(
         sequencer: SequencerOptions {
             signing_key: Some(signing_key),
             batcher_enabled: true,
+            allowed_operations: config.allowed_operations.clone(),
         },
         prover_engine: ProverEngineOptions { recursive_proofs },
         webserver: config.webserver.clone(),
@@ -203,13 +226,14 @@ fn create_ed25519_key_pair_pem_files(signing_key_path: impl AsRef<Path>) -> Resu
 #[cfg_attr(coverage_nightly, coverage(off))]
 #[cfg(test)]
 mod tests {
+    use prism_common::operation::DidConfig;
     use prism_da::{DataAvailabilityLayer, memory::InMemoryDataAvailabilityLayer};
     use prism_keys::SigningKey;
     use prism_presets::{
         ApplyPreset, FullNodePreset, PRESET_SPECTER_PUBLIC_KEY_BASE64, ProverPreset,
     };
     use prism_storage::{Database, inmemory::InMemoryDatabase};
-    use std::sync::Arc;
+    use std::{collections::HashSet, sync::Arc};
     use tempfile::TempDir;
     use tokio_util::sync::CancellationToken;
 
@@ -224,6 +248,7 @@ mod tests {
 
         assert!(config.verifying_key_str.contains(".prism/prover_key.spki"));
         assert_eq!(config.webserver, WebServerConfig::default());
+        assert_eq!(config.did, DidConfig::DEFAULT);
     }
 
     #[test]
@@ -278,6 +303,8 @@ mod tests {
         let config = FullNodeConfig {
             verifying_key_str: PRESET_SPECTER_PUBLIC_KEY_BASE64.to_string(),
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let db = Arc::new(Box::new(InMemoryDatabase::new()) as Box<dyn Database>);
@@ -289,11 +316,51 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_create_prover_with_unknown_allowed_operation() {
+        let config = FullNodeConfig {
+            verifying_key_str: PRESET_SPECTER_PUBLIC_KEY_BASE64.to_string(),
+            allowed_operations: Some(HashSet::from([
+                "create_did".to_string(),
+                "mint".to_string(),
+            ])),
+            ..FullNodeConfig::default()
+        };
+
+        let db = Arc::new(Box::new(InMemoryDatabase::new()) as Box<dyn Database>);
+        let da =
+            Arc::new(InMemoryDataAvailabilityLayer::default()) as Arc<dyn DataAvailabilityLayer>;
+        let cancellation_token = CancellationToken::new();
+
+        let result = create_prover_as_full_node(&config, db, da, cancellation_token);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_create_prover_with_did_method() {
+        let config = FullNodeConfig {
+            verifying_key_str: PRESET_SPECTER_PUBLIC_KEY_BASE64.to_string(),
+            webserver: WebServerConfig::default(),
+            did: DidConfig::new("prismtest"),
+            allowed_operations: None,
+        };
+
+        let db = Arc::new(Box::new(InMemoryDatabase::new()) as Box<dyn Database>);
+        let da =
+            Arc::new(InMemoryDataAvailabilityLayer::default()) as Arc<dyn DataAvailabilityLayer>;
+        let cancellation_token = CancellationToken::new();
+
+        let prover = create_prover_as_full_node(&config, db, da, cancellation_token).unwrap();
+        assert_eq!(prover.did_config().prefix(), "did:prismtest:");
+    }
+
     #[test]
     fn test_create_prover_as_full_node_with_invalid_key() {
         let config = FullNodeConfig {
             verifying_key_str: "invalid_key".to_string(),
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let db = Arc::new(Box::new(InMemoryDatabase::new()) as Box<dyn Database>);
@@ -319,6 +386,8 @@ mod tests {
             max_epochless_gap: DEFAULT_MAX_EPOCHLESS_GAP,
             recursive_proofs: true,
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let db = Arc::new(Box::new(InMemoryDatabase::new()) as Box<dyn Database>);
@@ -340,6 +409,8 @@ mod tests {
             max_epochless_gap: DEFAULT_MAX_EPOCHLESS_GAP,
             recursive_proofs: true,
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let db = Arc::new(Box::new(InMemoryDatabase::new()) as Box<dyn Database>);
@@ -360,6 +431,8 @@ mod tests {
         let config = FullNodeConfig {
             verifying_key_str: "test_key".to_string(),
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let cloned = config.clone();
@@ -373,6 +446,8 @@ mod tests {
             max_epochless_gap: 100,
             recursive_proofs: false,
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let cloned = config.clone();
@@ -386,6 +461,8 @@ mod tests {
         let config = FullNodeConfig {
             verifying_key_str: "test_key".to_string(),
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let debug_str = format!("{:?}", config);
@@ -399,6 +476,8 @@ mod tests {
             max_epochless_gap: 100,
             recursive_proofs: false,
             webserver: WebServerConfig::default(),
+            did: DidConfig::DEFAULT,
+            allowed_operations: None,
         };
 
         let debug_str = format!("{:?}", config);
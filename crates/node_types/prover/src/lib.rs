@@ -65,7 +65,9 @@
 //!             enabled: true,
 //!             host: "0.0.0.0".to_string(), // Bind to all interfaces
 //!             port: 41997,
+//!             ..WebServerConfig::default()
 //!         },
+//!         ..ProverConfig::default()
 //!     };
 //!
 //!     // Create and start the prover
@@ -1,20 +1,22 @@
-mod timer;
-
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use prism_common::{
     api::{
         PendingTransaction, PendingTransactionImpl, PrismApi, PrismApiError,
-        types::{AccountResponse, CommitmentResponse, HashedMerkleProof},
+        timer::TokioTimer,
+        types::{AccountResponse, CommitmentResponse, HashedMerkleProof, QueuedTransaction},
     },
+    operation::{DidConfig, SignedPLCOp},
     transaction::Transaction,
 };
 use prism_keys::{CryptoAlgorithm, SigningKey, VerifyingKey};
 use prism_storage::Database;
 use prism_tree::AccountResponse::{Found, NotFound};
-use std::sync::Arc;
-use timer::ProverTokioTimer;
-use tokio::{sync::RwLock, task::JoinSet};
+use std::{collections::HashSet, sync::Arc};
+use tokio::{
+    sync::{RwLock, broadcast},
+    task::JoinSet,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
@@ -49,6 +51,12 @@ pub struct SequencerOptions {
     /// Enables accepting incoming transactions from the webserver and posting batches to the DA
     /// layer.
     pub batcher_enabled: bool,
+    /// Types of the operations (see
+    /// [`prism_common::operation::Operation::operation_type`]) this node accepts from
+    /// clients, e.g. only `create_did` and `set_rotation_keys` for a DID-only node. `None`
+    /// accepts all of them. Transactions read from the DA layer are always applied, so that the
+    /// state stays in sync with other nodes. Unknown types are rejected at startup.
+    pub allowed_operations: Option<HashSet<String>>,
 }
 
 #[derive(Clone)]
@@ -63,6 +71,8 @@ pub struct ProverOptions {
     pub sequencer: SequencerOptions,
     pub prover_engine: ProverEngineOptions,
     pub webserver: WebServerConfig,
+    /// Method of the DIDs this node creates and serves
+    pub did_config: DidConfig,
 }
 
 impl Default for ProverOptions {
@@ -79,11 +89,13 @@ impl Default for ProverOptions {
             sequencer: SequencerOptions {
                 signing_key: Some(signing_key),
                 batcher_enabled: true,
+                allowed_operations: None,
             },
             prover_engine: ProverEngineOptions {
                 recursive_proofs: false,
             },
             webserver: WebServerConfig::default(),
+            did_config: DidConfig::DEFAULT,
         }
     }
 }
@@ -165,6 +177,7 @@ impl Prover {
             db.clone(),
             da.clone(),
             &opts.sequencer,
+            opts.did_config.clone(),
             latest_epoch_da_height.clone(),
         )?);
 
@@ -191,6 +204,11 @@ impl Prover {
         self.sequencer.get_db()
     }
 
+    /// Returns the method of the DIDs this node creates and serves.
+    pub const fn did_config(&self) -> &DidConfig {
+        &self.options.did_config
+    }
+
     pub async fn execute_block(
         &self,
         transactions: Vec<Transaction>,
@@ -214,14 +232,60 @@ impl Prover {
             .await
     }
 
-    pub async fn validate_and_queue_update(&self, transaction: Transaction) -> Result<()> {
+    /// Subscribes to the commitments of all epochs finalized by this prover from now on.
+    pub fn subscribe_to_commitments(&self) -> broadcast::Receiver<CommitmentResponse> {
+        self.sequencer.subscribe_to_commitments()
+    }
+
+    /// Subscribes to changes of the account with the given id, see
+    /// [`Sequencer::subscribe_to_account`].
+    pub async fn subscribe_to_account(&self, id: &str) -> broadcast::Receiver<()> {
+        self.sequencer.subscribe_to_account(id).await
+    }
+
+    /// Validates a transaction against the current state without queuing it.
+    pub async fn validate_transaction(&self, transaction: &Transaction) -> Result<()> {
+        self.sequencer.validate_transaction(transaction).await
+    }
+
+    pub async fn validate_and_queue_update(
+        &self,
+        transaction: Transaction,
+    ) -> Result<QueuedTransaction> {
         self.sequencer.validate_and_queue_update(transaction).await
     }
 
+    /// Validates and queues a transaction at most once per idempotency key, see
+    /// [`Sequencer::validate_and_queue_update_idempotent`].
+    pub async fn validate_and_queue_update_idempotent(
+        &self,
+        transaction: Transaction,
+        idempotency_key: Option<String>,
+    ) -> Result<QueuedTransaction> {
+        self.sequencer.validate_and_queue_update_idempotent(transaction, idempotency_key).await
+    }
+
     pub fn get_pending_transactions(&self) -> Arc<RwLock<Vec<Transaction>>> {
         self.sequencer.get_pending_transactions()
     }
 
+    /// Returns the PLC operation that created the given DID, see
+    /// [`Sequencer::get_genesis_operation`].
+    pub fn get_genesis_operation(&self, did: &str) -> Result<Option<SignedPLCOp>> {
+        self.sequencer.get_genesis_operation(did)
+    }
+
+    /// Returns the DID listing `handle` in its `alsoKnownAs`, see [`Sequencer::resolve_handle`].
+    pub fn resolve_handle(&self, handle: &str) -> Result<Option<String>> {
+        self.sequencer.resolve_handle(handle)
+    }
+
+    /// Returns the most recent transaction applied to the given DID, see
+    /// [`Sequencer::get_last_transaction`].
+    pub fn get_last_transaction(&self, did: &str) -> Result<Option<Transaction>> {
+        self.sequencer.get_last_transaction(did)
+    }
+
     pub async fn process_transaction(
         &self,
         transaction: Transaction,
@@ -301,7 +365,7 @@ impl Prover {
 
 #[async_trait]
 impl PrismApi for Prover {
-    type Timer = ProverTokioTimer;
+    type Timer = TokioTimer;
 
     async fn get_account(&self, id: &str) -> Result<AccountResponse, PrismApiError> {
         let acc_response = match self.sequencer.get_account(id).await? {
@@ -311,6 +375,8 @@ impl PrismApi for Prover {
                     account: Some(*account),
                     proof: HashedMerkleProof {
                         leaf: hashed_inclusion_proof.leaf,
+                        leaf_key_hash: hashed_inclusion_proof.leaf_key_hash,
+                        leaf_value_hash: hashed_inclusion_proof.leaf_value_hash,
                         siblings: hashed_inclusion_proof.siblings,
                     },
                 }
@@ -321,6 +387,8 @@ impl PrismApi for Prover {
                     account: None,
                     proof: HashedMerkleProof {
                         leaf: hashed_non_inclusion.leaf,
+                        leaf_key_hash: hashed_non_inclusion.leaf_key_hash,
+                        leaf_value_hash: hashed_non_inclusion.leaf_value_hash,
                         siblings: hashed_non_inclusion.siblings,
                     },
                 }
@@ -330,16 +398,31 @@ impl PrismApi for Prover {
     }
 
     async fn get_commitment(&self) -> Result<CommitmentResponse, PrismApiError> {
-        let commitment = self.sequencer.get_commitment().await?;
-        Ok(CommitmentResponse { commitment })
+        Ok(self.sequencer.get_commitment_response().await?)
+    }
+
+    async fn get_genesis_operation(&self, did: &str) -> Result<SignedPLCOp, PrismApiError> {
+        self.sequencer.get_genesis_operation(did)?.ok_or_else(|| {
+            PrismApiError::InvalidTarget(format!("no genesis operation known for {}", did))
+        })
+    }
+
+    async fn resolve_handle(&self, handle: &str) -> Result<String, PrismApiError> {
+        self.sequencer
+            .resolve_handle(handle)?
+            .ok_or_else(|| PrismApiError::InvalidTarget(format!("no DID claims handle {}", handle)))
     }
 
     async fn post_transaction(
         &self,
         transaction: Transaction,
+        idempotency_key: Option<String>,
     ) -> Result<impl PendingTransaction<Timer = Self::Timer>, PrismApiError> {
-        self.sequencer.validate_and_queue_update(transaction.clone()).await?;
-        Ok(PendingTransactionImpl::new(self, transaction))
+        let receipt = self
+            .sequencer
+            .validate_and_queue_update_idempotent(transaction.clone(), idempotency_key)
+            .await?;
+        Ok(PendingTransactionImpl::new(self, transaction).with_receipt(receipt))
     }
 }
 
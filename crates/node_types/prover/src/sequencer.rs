@@ -1,21 +1,84 @@
 use anyhow::{Context, Result, bail};
 use jmt::KeyHash;
 use prism_common::{
-    account::Account, digest::Digest, operation::Operation, transaction::Transaction,
+    account::Account,
+    api::types::{CommitmentResponse, QueuedTransaction},
+    digest::Digest,
+    operation::{DidConfig, OPERATION_TYPES, Operation, SignedPLCOp},
+    transaction::Transaction,
 };
 use prism_da::{DataAvailabilityLayer, FinalizedEpoch};
+use prism_errors::{DatabaseError, OperationError, TransactionError};
 use prism_keys::SigningKey;
 use prism_storage::Database;
 use prism_tree::{
-    AccountResponse::Found, hasher::TreeHasher, key_directory_tree::KeyDirectoryTree,
-    proofs::Proof, snarkable_tree::SnarkableTree,
+    AccountResponse::Found,
+    hasher::TreeHasher,
+    key_directory_tree::{ForkBaseStore, KeyDirectoryTree},
+    proofs::Proof,
+    snarkable_tree::SnarkableTree,
 };
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{OnceCell, RwLock, RwLockWriteGuard, broadcast};
 use tokio_util::sync::CancellationToken;
 
 use crate::prover_engine::engine::ProverEngine;
 
+/// Number of commitments buffered for subscribers that fall behind.
+const COMMITMENT_CHANNEL_CAPACITY: usize = 16;
+
+/// Number of account change notifications buffered for subscribers that fall behind.
+const ACCOUNT_CHANNEL_CAPACITY: usize = 16;
+
+/// How long the outcome of a transaction posted with an idempotency key is remembered.
+const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Maximum number of idempotency keys remembered at once. When full, the oldest key is forgotten.
+const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+/// A transaction posted with an idempotency key.
+struct IdempotencyEntry {
+    created_at: Instant,
+    /// Hash of the transaction the key was first used for
+    tx_hash: Digest,
+    /// Set once the first request with the key is done, retries wait for it meanwhile
+    outcome: Arc<OnceCell<Result<QueuedTransaction, CachedRejection>>>,
+}
+
+/// The rejection of a transaction posted with an idempotency key, returned to every retry. The
+/// original error is kept as its source, so that typed errors keep their error codes.
+#[derive(Debug, Clone)]
+struct CachedRejection(Arc<anyhow::Error>);
+
+impl std::fmt::Display for CachedRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for CachedRejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.0.as_ref().as_ref())
+    }
+}
+
+/// Keeps the account states that recovery forks are applied to in the database.
+struct DatabaseForkBases(Arc<Box<dyn Database>>);
+
+impl ForkBaseStore for DatabaseForkBases {
+    fn get_fork_base(&self, id: &str, cid: &str) -> Result<Option<Account>> {
+        self.0.get_fork_base(id, cid)
+    }
+
+    fn set_fork_base(&self, id: &str, cid: &str, account: &Account) -> Result<()> {
+        self.0.set_fork_base(id, cid, account)
+    }
+}
+
 #[derive(Clone)]
 pub struct Sequencer {
     db: Arc<Box<dyn Database>>,
@@ -25,6 +88,18 @@ pub struct Sequencer {
     signing_key: Option<SigningKey>,
     latest_epoch_da_height: Arc<RwLock<u64>>,
     batcher_enabled: bool,
+    commitment_tx: broadcast::Sender<CommitmentResponse>,
+    /// Notifies subscribers of an account, keyed by account id, whenever it changed.
+    account_watchers: Arc<RwLock<HashMap<String, broadcast::Sender<()>>>>,
+    /// Operation types accepted from clients, see [`SequencerOptions::allowed_operations`].
+    allowed_operations: Option<HashSet<String>>,
+    /// Recently posted transactions, keyed by their idempotency key.
+    idempotency_keys: Arc<RwLock<HashMap<String, IdempotencyEntry>>>,
+    /// Method of the DIDs created on this node
+    did_config: DidConfig,
+    /// The latest epoch of the tree and the Unix time it was committed at, reported along with
+    /// its commitment. Unknown for the epoch the tree was loaded at.
+    epoch_committed_at: Arc<RwLock<Option<(u64, u64)>>>,
 }
 
 impl Sequencer {
@@ -32,8 +107,18 @@ impl Sequencer {
         db: Arc<Box<dyn Database>>,
         da: Arc<dyn DataAvailabilityLayer>,
         config: &crate::prover::SequencerOptions,
+        did_config: DidConfig,
         latest_epoch_da_height: Arc<RwLock<u64>>,
     ) -> Result<Self> {
+        if let Some(unknown) = config
+            .allowed_operations
+            .iter()
+            .flatten()
+            .find(|operation_type| !OPERATION_TYPES.contains(&operation_type.as_str()))
+        {
+            bail!(OperationError::UnknownOperation(unknown.clone()));
+        }
+
         let saved_epoch = match db.get_latest_epoch_height() {
             Ok(height) => height + 1,
             Err(_) => {
@@ -42,7 +127,12 @@ impl Sequencer {
             }
         };
 
-        let tree = Arc::new(RwLock::new(KeyDirectoryTree::load(db.clone(), saved_epoch)));
+        let tree = Arc::new(RwLock::new(
+            KeyDirectoryTree::load(db.clone(), saved_epoch)
+                .with_did_config(did_config.clone())
+                .with_fork_bases(Arc::new(DatabaseForkBases(db.clone()))),
+        ));
+        let (commitment_tx, _) = broadcast::channel(COMMITMENT_CHANNEL_CAPACITY);
 
         Ok(Self {
             db,
@@ -52,6 +142,12 @@ impl Sequencer {
             signing_key: config.signing_key.clone(),
             latest_epoch_da_height,
             batcher_enabled: config.batcher_enabled,
+            allowed_operations: config.allowed_operations.clone(),
+            commitment_tx,
+            account_watchers: Arc::new(RwLock::new(HashMap::new())),
+            idempotency_keys: Arc::new(RwLock::new(HashMap::new())),
+            did_config,
+            epoch_committed_at: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -119,7 +215,11 @@ impl Sequencer {
     ) -> Result<u64> {
         let mut tree = self.tree.write().await;
         let batch = tree.process_batch(transactions)?;
+        let tree_epoch = tree.epoch();
+        let committed_at = self.stamp_epoch(tree_epoch).await?;
         batch.verify()?;
+        self.retain_operations(&batch.proofs)?;
+        self.notify_account_watchers(&batch.proofs).await;
 
         let (snark, stark) = prover_engine.prove_epoch(epoch_height, &batch, &self.db).await?;
 
@@ -151,6 +251,12 @@ impl Sequencer {
         self.db.add_epoch(&epoch_json)?;
 
         info!("finalized new epoch at height {}", epoch_height);
+        // sending only fails if nobody is subscribed
+        let _ = self.commitment_tx.send(CommitmentResponse {
+            commitment: epoch_json.current_commitment,
+            epoch: tree_epoch,
+            timestamp: Some(committed_at),
+        });
 
         Ok(da_height)
     }
@@ -161,43 +267,191 @@ impl Sequencer {
         let mut proofs = Vec::new();
 
         for transaction in transactions {
-            match self.process_transaction(transaction.clone()).await {
-                Ok(proof) => proofs.push(proof),
+            let mut tree = self.tree.write().await;
+            let proof = match tree.process_transaction(transaction.clone()) {
+                Ok(proof) => proof,
                 Err(e) => {
                     warn!(
                         "Failed to process transaction: {:?}. Error: {}",
                         transaction, e
                     );
+                    continue;
                 }
-            }
+            };
+            // the tree already holds the transaction, so its proof must not be dropped
+            self.record_applied(tree, &proof).await.with_context(|| {
+                format!("Failed to record applied transaction: {:?}", transaction)
+            })?;
+            proofs.push(proof);
         }
 
         Ok(proofs)
     }
 
-    pub async fn validate_and_queue_update(&self, transaction: Transaction) -> Result<()> {
-        if !self.batcher_enabled {
-            bail!("Batcher is disabled, cannot queue transactions");
-        }
+    /// Validates a transaction against the current account state, without queuing it or
+    /// modifying any state.
+    pub async fn validate_transaction(&self, transaction: &Transaction) -> Result<()> {
+        self.verify_operation_allowed(&transaction.operation)?;
+        transaction.validate_consistency()?;
+        verify_challenge_expiry(&transaction.operation)?;
 
         match transaction.operation {
-            Operation::CreateDID { .. } | Operation::CreateAccount { .. } => {
-                Account::default().process_transaction(&transaction)?;
+            Operation::CreateDID { ref did, .. } => {
+                // DIDs of another method would only be rejected by the tree
+                self.did_config.parse(did)?;
+                Account::default().process_transaction(transaction)?;
             }
-            Operation::AddKey { .. } | Operation::RevokeKey { .. } => {
+            // unknown operations are rejected by the account
+            Operation::CreateAccount { .. }
+            | Operation::RegisterService { .. }
+            | Operation::Unknown { .. } => {
+                Account::default().process_transaction(transaction)?;
+            }
+            Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::SetRotationKeys { .. }
+            | Operation::DeactivateDID {} => {
                 let account_response = self.get_account(&transaction.id).await?;
 
                 let Found(mut account, _) = account_response else {
                     bail!("Account not found for id: {}", transaction.id)
                 };
 
-                account.process_transaction(&transaction)?;
+                let fork_base = match account.fork_prev(transaction) {
+                    Some(prev) => self.db.get_fork_base(&transaction.id, prev)?,
+                    None => None,
+                };
+                account.process_transaction_with_fork_base(transaction, fork_base.as_ref())?;
+            }
+        };
+        Ok(())
+    }
+
+    /// Rejects operations whose type this node does not accept from clients.
+    fn verify_operation_allowed(&self, operation: &Operation) -> Result<(), OperationError> {
+        let operation_type = operation.operation_type();
+        match &self.allowed_operations {
+            Some(allowed) if !allowed.contains(operation_type) => Err(
+                OperationError::OperationNotAllowed(operation_type.to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates the transaction and queues it for the next batch, returning where it was queued.
+    pub async fn validate_and_queue_update(
+        &self,
+        transaction: Transaction,
+    ) -> Result<QueuedTransaction> {
+        if !self.batcher_enabled {
+            bail!("Batcher is disabled, cannot queue transactions");
+        }
+
+        self.validate_transaction(&transaction).await?;
+        let tx_hash = transaction.hash()?;
+        let target_epoch = match self.db.get_latest_epoch_height() {
+            Ok(height) => height + 1,
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<DatabaseError>(),
+                    Some(DatabaseError::NotFoundError(_))
+                ) =>
+            {
+                0
             }
+            Err(e) => return Err(e.context("Failed to get the latest epoch height")),
         };
 
         let mut pending = self.pending_transactions.write().await;
+        let queue_position = pending.len();
         pending.push(transaction);
-        Ok(())
+        Ok(QueuedTransaction {
+            tx_hash,
+            queue_position,
+            target_epoch,
+        })
+    }
+
+    /// Like [`Sequencer::validate_and_queue_update`], but if `idempotency_key` was already used
+    /// within [`IDEMPOTENCY_KEY_TTL`], the transaction is not queued again and the outcome of the
+    /// first call is returned instead. Reusing a key for a different transaction fails with
+    /// [`TransactionError::IdempotencyKeyReused`].
+    pub async fn validate_and_queue_update_idempotent(
+        &self,
+        transaction: Transaction,
+        idempotency_key: Option<String>,
+    ) -> Result<QueuedTransaction> {
+        let Some(idempotency_key) = idempotency_key else {
+            return self.validate_and_queue_update(transaction).await;
+        };
+        let tx_hash = transaction.hash()?;
+
+        let outcome = {
+            let mut keys = self.idempotency_keys.write().await;
+            keys.retain(|_, entry| entry.created_at.elapsed() < IDEMPOTENCY_KEY_TTL);
+
+            match keys.get(&idempotency_key) {
+                Some(entry) if entry.tx_hash != tx_hash => {
+                    return Err(TransactionError::IdempotencyKeyReused(idempotency_key).into());
+                }
+                Some(entry) => {
+                    debug!(
+                        "returning cached outcome for idempotency key {}",
+                        idempotency_key
+                    );
+                    entry.outcome.clone()
+                }
+                None => {
+                    if keys.len() >= MAX_IDEMPOTENCY_KEYS {
+                        let oldest = keys
+                            .iter()
+                            .min_by_key(|(_, entry)| entry.created_at)
+                            .map(|(key, _)| key.clone());
+                        if let Some(oldest) = oldest {
+                            keys.remove(&oldest);
+                        }
+                    }
+                    let outcome = Arc::new(OnceCell::new());
+                    keys.insert(
+                        idempotency_key,
+                        IdempotencyEntry {
+                            created_at: Instant::now(),
+                            tx_hash,
+                            outcome: outcome.clone(),
+                        },
+                    );
+                    outcome
+                }
+            }
+        };
+
+        // only the first request validates and queues, concurrent retries wait for its outcome
+        outcome
+            .get_or_init(|| async {
+                self.validate_and_queue_update(transaction)
+                    .await
+                    .map_err(|e| CachedRejection(Arc::new(e)))
+            })
+            .await
+            .clone()
+            .map_err(anyhow::Error::new)
+    }
+
+    /// Subscribes to the commitments of all epochs finalized from now on.
+    pub fn subscribe_to_commitments(&self) -> broadcast::Receiver<CommitmentResponse> {
+        self.commitment_tx.subscribe()
+    }
+
+    /// Subscribes to changes of the account with the given id. The account does not need to
+    /// exist yet. A notification is sent after every transaction successfully applied to it.
+    pub async fn subscribe_to_account(&self, id: &str) -> broadcast::Receiver<()> {
+        let mut watchers = self.account_watchers.write().await;
+        // drop channels of accounts whose subscribers have all disconnected
+        watchers.retain(|_, tx| tx.receiver_count() > 0);
+        watchers
+            .entry(id.to_string())
+            .or_insert_with(|| broadcast::channel(ACCOUNT_CHANNEL_CAPACITY).0)
+            .subscribe()
     }
 
     pub async fn get_commitment(&self) -> Result<Digest> {
@@ -205,6 +459,38 @@ impl Sequencer {
         tree.get_commitment().context("Failed to get commitment")
     }
 
+    /// Returns the current commitment along with the epoch of the tree it was taken at.
+    pub async fn get_commitment_response(&self) -> Result<CommitmentResponse> {
+        let tree = self.tree.read().await;
+        let epoch = tree.epoch();
+        let timestamp = self
+            .epoch_committed_at
+            .read()
+            .await
+            .filter(|(committed_epoch, _)| *committed_epoch == epoch)
+            .map(|(_, committed_at)| committed_at);
+        Ok(CommitmentResponse {
+            commitment: tree.get_commitment().context("Failed to get commitment")?,
+            epoch,
+            timestamp,
+        })
+    }
+
+    /// Records the time the tree reached `epoch`, unless it was already recorded, and returns
+    /// it. Has to be called with the tree locked for writing, so that readers of the tree never
+    /// see the time of another epoch.
+    async fn stamp_epoch(&self, epoch: u64) -> Result<u64> {
+        let mut committed_at = self.epoch_committed_at.write().await;
+        match *committed_at {
+            Some((committed_epoch, timestamp)) if committed_epoch == epoch => Ok(timestamp),
+            _ => {
+                let timestamp = unix_time()?;
+                *committed_at = Some((epoch, timestamp));
+                Ok(timestamp)
+            }
+        }
+    }
+
     pub async fn get_account(&self, id: &str) -> Result<prism_tree::AccountResponse> {
         let tree = self.tree.read().await;
         let key_hash = KeyHash::with::<TreeHasher>(id);
@@ -216,12 +502,450 @@ impl Sequencer {
         self.pending_transactions.clone()
     }
 
+    /// Returns the PLC operation that created the given DID, if it exists.
+    pub fn get_genesis_operation(&self, did: &str) -> Result<Option<SignedPLCOp>> {
+        self.db.get_genesis_operation(did)
+    }
+
+    /// Returns the DID listing `handle` in its `alsoKnownAs`. If several DIDs list it, the one
+    /// created first is returned.
+    pub fn resolve_handle(&self, handle: &str) -> Result<Option<String>> {
+        self.db.get_handle_did(handle)
+    }
+
+    /// Returns the most recent transaction applied to the given DID, if it exists. Its `prev` is
+    /// the CID of the operation it replaced.
+    pub fn get_last_transaction(&self, did: &str) -> Result<Option<Transaction>> {
+        self.db.get_last_transaction(did)
+    }
+
+    /// Stores the genesis operations of the DIDs created by `proofs` and the last transaction
+    /// applied to each account, as the account only holds the folded state, and indexes the
+    /// handles of new DIDs.
+    fn retain_operations<'a>(&self, proofs: impl IntoIterator<Item = &'a Proof>) -> Result<()> {
+        for proof in proofs {
+            let tx = match proof {
+                Proof::Insert(insert_proof) => &insert_proof.tx,
+                Proof::Update(update_proof) => &update_proof.tx,
+            };
+            if let Operation::CreateDID {
+                did, also_known_as, ..
+            } = &tx.operation
+            {
+                let operation = SignedPLCOp::try_from(&tx.operation)?;
+                self.db.set_genesis_operation(did, &operation)?;
+                self.index_handles(did, also_known_as)?;
+            }
+            self.db.set_last_transaction(&tx.id, tx)?;
+        }
+        Ok(())
+    }
+
+    /// Maps each handle to `did`, unless an earlier DID already claimed it.
+    fn index_handles(&self, did: &str, handles: &[String]) -> Result<()> {
+        for handle in handles {
+            match self.db.get_handle_did(handle)? {
+                Some(claimant) if claimant != did => warn!(
+                    "Handle {} of {} is already claimed by {}, keeping the earlier DID",
+                    handle, did, claimant
+                ),
+                Some(_) => {}
+                None => self.db.set_handle_did(handle, did)?,
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_db(&self) -> Arc<Box<dyn Database>> {
         self.db.clone()
     }
 
+    /// Notifies the subscribers of the accounts changed by `proofs`, see
+    /// [`Sequencer::subscribe_to_account`].
+    async fn notify_account_watchers<'a>(&self, proofs: impl IntoIterator<Item = &'a Proof>) {
+        let watchers = self.account_watchers.read().await;
+        for proof in proofs {
+            let id = match proof {
+                Proof::Insert(insert_proof) => &insert_proof.tx.id,
+                Proof::Update(update_proof) => &update_proof.tx.id,
+            };
+            if let Some(tx) = watchers.get(id) {
+                // sending only fails if nobody is subscribed
+                let _ = tx.send(());
+            }
+        }
+    }
+
     pub async fn process_transaction(&self, transaction: Transaction) -> Result<Proof> {
         let mut tree = self.tree.write().await;
-        tree.process_transaction(transaction)
+        let proof = tree.process_transaction(transaction)?;
+        self.record_applied(tree, &proof).await?;
+        Ok(proof)
+    }
+
+    /// Stamps the epoch, stores the operations and notifies the account subscribers of a
+    /// transaction `tree` was just updated with. Releases the tree once the epoch is stamped.
+    async fn record_applied(
+        &self,
+        tree: RwLockWriteGuard<'_, KeyDirectoryTree<Box<dyn Database>>>,
+        proof: &Proof,
+    ) -> Result<()> {
+        self.stamp_epoch(tree.epoch()).await?;
+        drop(tree);
+        self.retain_operations([proof])?;
+        self.notify_account_watchers([proof]).await;
+        Ok(())
+    }
+}
+
+/// Rejects account creations whose service challenge expired by the clock of this sequencer. The
+/// expiry is only checked when a transaction is admitted, not when it is applied: it is not part
+/// of the state transition, as provers and verifiers have no common clock, and transactions
+/// replayed from the DA layer have to apply as they did when they were posted.
+fn verify_challenge_expiry(operation: &Operation) -> Result<()> {
+    Ok(operation.verify_challenge_expiry(unix_time()?)?)
+}
+
+/// Returns the current Unix time in seconds.
+fn unix_time() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::SequencerOptions;
+    use prism_common::{test_utils::create_did_tx, transaction::UnsignedTransaction};
+    use prism_da::memory::InMemoryDataAvailabilityLayer;
+    use prism_errors::DidError;
+    use prism_storage::inmemory::InMemoryDatabase;
+    use std::time::Duration;
+
+    fn create_test_sequencer() -> Sequencer {
+        create_test_sequencer_with_did_config(DidConfig::DEFAULT)
+    }
+
+    fn create_test_sequencer_with_did_config(did_config: DidConfig) -> Sequencer {
+        let db: Arc<Box<dyn Database>> = Arc::new(Box::new(InMemoryDatabase::new()));
+        let (da, _, _) = InMemoryDataAvailabilityLayer::new(Duration::from_millis(50));
+        let options = SequencerOptions {
+            signing_key: None,
+            batcher_enabled: true,
+            allowed_operations: None,
+        };
+        Sequencer::new(
+            db,
+            Arc::new(da),
+            &options,
+            did_config,
+            Arc::new(RwLock::new(0)),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_validate_transaction_does_not_modify_state() {
+        let sequencer = create_test_sequencer();
+        let commitment = sequencer.get_commitment().await.unwrap();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let valid_tx = create_did_tx(&signing_key);
+        let mut invalid_tx = valid_tx.clone();
+        invalid_tx.nonce = 1;
+
+        sequencer.validate_transaction(&valid_tx).await.unwrap();
+        assert!(sequencer.validate_transaction(&invalid_tx).await.is_err());
+
+        assert_eq!(sequencer.get_commitment().await.unwrap(), commitment);
+        assert!(sequencer.get_pending_transactions().read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_did_only_node_rejects_other_operations() {
+        let db: Arc<Box<dyn Database>> = Arc::new(Box::new(InMemoryDatabase::new()));
+        let (da, _, _) = InMemoryDataAvailabilityLayer::new(Duration::from_millis(50));
+        let options = SequencerOptions {
+            signing_key: None,
+            batcher_enabled: true,
+            allowed_operations: Some(HashSet::from([
+                "create_did".to_string(),
+                "set_rotation_keys".to_string(),
+            ])),
+        };
+        let sequencer = Sequencer::new(
+            db,
+            Arc::new(da),
+            &options,
+            DidConfig::DEFAULT,
+            Arc::new(RwLock::new(0)),
+        )
+        .unwrap();
+
+        // services registered by other nodes are still applied
+        let service_key = SigningKey::new_ed25519();
+        let challenge_key = SigningKey::new_ed25519();
+        let register_tx = Account::builder()
+            .register_service()
+            .with_id("Service".to_string())
+            .with_key(service_key.verifying_key())
+            .requiring_signed_challenge(challenge_key.verifying_key())
+            .unwrap()
+            .sign(&service_key)
+            .unwrap()
+            .transaction();
+        sequencer.process_transaction(register_tx).await.unwrap();
+
+        let acc_key = SigningKey::new_ed25519();
+        let create_account_tx = Account::builder()
+            .create_account()
+            .with_id("Acc".to_string())
+            .for_service_with_id("Service".to_string())
+            .with_key(acc_key.verifying_key())
+            .with_challenge_expiry(u64::MAX)
+            .meeting_signed_challenge(&challenge_key)
+            .unwrap()
+            .sign(&acc_key)
+            .unwrap()
+            .transaction();
+        let err = sequencer.validate_and_queue_update(create_account_tx).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<OperationError>(),
+            Some(OperationError::OperationNotAllowed(operation)) if operation == "create_account"
+        ));
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_did_tx = create_did_tx(&signing_key);
+        sequencer.validate_and_queue_update(create_did_tx).await.unwrap();
+
+        assert_eq!(sequencer.get_pending_transactions().read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_create_account_requires_service_challenge() {
+        let sequencer = create_test_sequencer();
+        let service_key = SigningKey::new_ed25519();
+        let challenge_key = SigningKey::new_ed25519();
+        let acc_key = SigningKey::new_ed25519();
+
+        let create_account_tx = |id: &str, service_id: &str, challenge_signer: &SigningKey| {
+            Account::builder()
+                .create_account()
+                .with_id(id.to_string())
+                .for_service_with_id(service_id.to_string())
+                .with_key(acc_key.verifying_key())
+                .with_challenge_expiry(u64::MAX)
+                .meeting_signed_challenge(challenge_signer)
+                .unwrap()
+                .sign(&acc_key)
+                .unwrap()
+                .transaction()
+        };
+
+        // the service is not registered yet
+        let unregistered = create_account_tx("Acc", "Service", &challenge_key);
+        assert!(sequencer.process_transaction(unregistered).await.is_err());
+
+        let register_tx = Account::builder()
+            .register_service()
+            .with_id("Service".to_string())
+            .with_key(service_key.verifying_key())
+            .requiring_signed_challenge(challenge_key.verifying_key())
+            .unwrap()
+            .sign(&service_key)
+            .unwrap()
+            .transaction();
+        sequencer.process_transaction(register_tx).await.unwrap();
+
+        let wrong_signer = create_account_tx("Acc", "Service", &service_key);
+        assert!(sequencer.process_transaction(wrong_signer).await.is_err());
+
+        let valid = create_account_tx("Acc", "Service", &challenge_key);
+        let Proof::Insert(insert_proof) = sequencer.process_transaction(valid).await.unwrap()
+        else {
+            panic!("Creating an account did not return an insert proof");
+        };
+        assert!(insert_proof.service_proof.is_some());
+        insert_proof.verify().unwrap();
+
+        let Found(account, _) = sequencer.get_account("Acc").await.unwrap() else {
+            panic!("Account was not created");
+        };
+        assert_eq!(account.valid_keys(), [acc_key.verifying_key()]);
+    }
+
+    #[tokio::test]
+    async fn test_process_transaction_applies_recovery_fork() {
+        let sequencer = create_test_sequencer();
+        let recovery = SigningKey::new_secp256k1();
+        let signer = SigningKey::new_secp256k1();
+
+        let create_tx = Account::builder()
+            .create_did()
+            .with_rotation_keys(vec![recovery.verifying_key(), signer.verifying_key()])
+            .with_atproto_pds("http://localhost:2583".to_string())
+            .build(&recovery)
+            .unwrap()
+            .sign(&recovery)
+            .unwrap()
+            .transaction();
+        let mut account = Account::default();
+        account.process_transaction(&create_tx).unwrap();
+        let genesis_cid = account.head_cid().map(str::to_string);
+        sequencer.process_transaction(create_tx).await.unwrap();
+
+        // the lower-authority key takes over, the recovery key overrides it
+        let takeover_tx = account
+            .modify()
+            .rotate_to(vec![signer.verifying_key()])
+            .unwrap()
+            .sign(&signer)
+            .unwrap()
+            .transaction();
+        account.process_transaction(&takeover_tx).unwrap();
+        sequencer.process_transaction(takeover_tx).await.unwrap();
+
+        let fork_tx = UnsignedTransaction {
+            id: account.id().to_string(),
+            operation: Operation::SetRotationKeys {
+                keys: vec![recovery.verifying_key()],
+            },
+            nonce: account.nonce(),
+            prev: genesis_cid,
+        }
+        .sign(&recovery)
+        .unwrap();
+        sequencer.validate_transaction(&fork_tx).await.unwrap();
+        let Proof::Update(update_proof) = sequencer.process_transaction(fork_tx).await.unwrap()
+        else {
+            panic!("Forking an account did not return an update proof");
+        };
+        assert!(update_proof.fork_base.is_some());
+        update_proof.verify().unwrap();
+
+        let Found(account, _) = sequencer.get_account(account.id()).await.unwrap() else {
+            panic!("Account was not found");
+        };
+        assert_eq!(account.valid_keys(), [recovery.verifying_key()]);
+        assert_eq!(account.nonce(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_create_account_rejects_expired_challenge() {
+        let sequencer = create_test_sequencer();
+        let service_key = SigningKey::new_ed25519();
+        let challenge_key = SigningKey::new_ed25519();
+        let acc_key = SigningKey::new_ed25519();
+
+        let register_tx = Account::builder()
+            .register_service()
+            .with_id("Service".to_string())
+            .with_key(service_key.verifying_key())
+            .requiring_signed_challenge(challenge_key.verifying_key())
+            .unwrap()
+            .sign(&service_key)
+            .unwrap()
+            .transaction();
+        sequencer.process_transaction(register_tx).await.unwrap();
+
+        let create_account_tx = |expires_at: u64| {
+            Account::builder()
+                .create_account()
+                .with_id("Acc".to_string())
+                .for_service_with_id("Service".to_string())
+                .with_key(acc_key.verifying_key())
+                .with_challenge_expiry(expires_at)
+                .meeting_signed_challenge(&challenge_key)
+                .unwrap()
+                .sign(&acc_key)
+                .unwrap()
+                .transaction()
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let expired = create_account_tx(now - 1);
+        assert!(sequencer.validate_transaction(&expired).await.is_err());
+        assert!(sequencer.validate_and_queue_update(expired.clone()).await.is_err());
+
+        let fresh = create_account_tx(now + 600);
+        sequencer.validate_transaction(&fresh).await.unwrap();
+
+        // a transaction admitted before its challenge expired still applies when replayed later
+        assert_eq!(
+            sequencer.execute_block(vec![expired]).await.unwrap().len(),
+            1
+        );
+        assert!(matches!(
+            sequencer.get_account("Acc").await.unwrap(),
+            Found(..)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_custom_did_method() {
+        let testnet = DidConfig::new("prismtest");
+        let sequencer = create_test_sequencer_with_did_config(testnet.clone());
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_did_tx = |config: DidConfig| {
+            Account::builder()
+                .create_did()
+                .with_did_config(config)
+                .with_rotation_keys(vec![signing_key.verifying_key()])
+                .with_atproto_pds("http://localhost:2583".to_string())
+                .build(&signing_key)
+                .unwrap()
+                .sign(&signing_key)
+                .unwrap()
+                .transaction()
+        };
+
+        // DIDs of the default method are neither admitted nor applied
+        let default_tx = create_did_tx(DidConfig::DEFAULT);
+        let err = sequencer.validate_transaction(&default_tx).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DidError>(),
+            Some(DidError::MethodMismatch { .. })
+        ));
+        assert!(sequencer.execute_block(vec![default_tx.clone()]).await.unwrap().is_empty());
+        assert!(!matches!(
+            sequencer.get_account(&default_tx.id).await.unwrap(),
+            Found(..)
+        ));
+
+        let testnet_tx = create_did_tx(testnet);
+        assert!(testnet_tx.id.starts_with("did:prismtest:"));
+        sequencer.validate_transaction(&testnet_tx).await.unwrap();
+        assert_eq!(
+            sequencer.execute_block(vec![testnet_tx.clone()]).await.unwrap().len(),
+            1
+        );
+        assert!(matches!(
+            sequencer.get_account(&testnet_tx.id).await.unwrap(),
+            Found(..)
+        ));
+    }
+
+    #[test]
+    fn test_unknown_allowed_operations_are_rejected() {
+        let db: Arc<Box<dyn Database>> = Arc::new(Box::new(InMemoryDatabase::new()));
+        let (da, _, _) = InMemoryDataAvailabilityLayer::new(Duration::from_millis(50));
+        let options = SequencerOptions {
+            signing_key: None,
+            batcher_enabled: true,
+            allowed_operations: Some(HashSet::from(["create_dids".to_string()])),
+        };
+        let Err(err) = Sequencer::new(
+            db,
+            Arc::new(da),
+            &options,
+            DidConfig::DEFAULT,
+            Arc::new(RwLock::new(0)),
+        ) else {
+            panic!("expected unknown operation types to be rejected");
+        };
+        assert!(matches!(
+            err.downcast_ref::<OperationError>(),
+            Some(OperationError::UnknownOperation(operation_type)) if operation_type == "create_dids"
+        ));
     }
 }
@@ -1,28 +1,49 @@
 use crate::Prover;
 use anyhow::{Result, bail};
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json, Router,
+    extract::{
+        DefaultBodyLimit, Path, Request, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    serve::ListenerExt,
+};
 use prism_common::{
+    account::Account,
     api::{
-        PrismApi,
+        IDEMPOTENCY_KEY_HEADER, PrismApi, REQUEST_ID_HEADER,
         types::{
-            AccountDidResponse, AccountRequest, AccountResponse, CommitmentResponse, DidDocument,
+            AccountDidResponse, AccountRequest, AccountResponse, CommitmentResponse,
+            DID_DOCUMENT_CBOR_CONTENT_TYPE, DID_DOCUMENT_CONTENT_TYPE, DidDocument,
+            DidResolutionResult, LastOperationResponse, QueuedTransaction, ResolveHandleRequest,
+            ResolveHandleResponse, TransactionErrorResponse, TransactionResult, ValidationResponse,
         },
     },
+    operation::SignedPLCOp,
     transaction::{SignedPlcTransaction, Transaction},
 };
+use prism_errors::TransactionError;
 use serde::{Deserialize, Serialize};
 use sp1_sdk::network::proto::types::ClaimGpuRequest;
-use std::{net::SocketAddr, sync::Arc};
-use tokio::net::TcpListener;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    net::TcpSocket,
+    sync::broadcast::{self, error::RecvError},
+};
 use tokio_util::sync::CancellationToken;
 use tower_http::cors::CorsLayer;
-use tracing::{error, info, warn};
+use tracing::{Instrument, Span, debug, error, field, info, info_span, warn};
 use utoipa::{
     OpenApi,
     openapi::{Info, OpenApiBuilder},
 };
 use utoipa_axum::{router::OpenApiRouter, routes};
 use utoipa_swagger_ui::SwaggerUi;
+use uuid::Uuid;
 
 /// Configuration for the embedded web server in Prism nodes.
 ///
@@ -41,14 +62,43 @@ pub struct WebServerConfig {
     /// Port number for the web server.
     /// Should be unique per node instance.
     pub port: u16,
+
+    /// Maximum size of request bodies in bytes.
+    /// Larger requests are rejected with 413 Payload Too Large.
+    pub max_body_bytes: usize,
+
+    /// Maximum number of pending connections the OS queues before refusing new ones.
+    /// Must be non-zero, defaults to [`DEFAULT_TCP_BACKLOG`].
+    pub tcp_backlog: u32,
+
+    /// Whether to disable Nagle's algorithm on accepted connections, trading throughput for
+    /// lower latency on small responses. Disabled by default.
+    pub tcp_nodelay: bool,
+
+    /// How long to wait on shutdown for in-flight requests, e.g. transactions being validated
+    /// and queued, before dropping them. No new requests are accepted in the meantime.
+    pub shutdown_drain_timeout: Duration,
 }
 
+/// Default maximum request body size, well above the size of any valid transaction.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Default listen backlog, the same tokio uses for [`tokio::net::TcpListener::bind`].
+pub const DEFAULT_TCP_BACKLOG: u32 = 1024;
+
+/// Default time in-flight requests are given to finish on shutdown.
+pub const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
 impl Default for WebServerConfig {
     fn default() -> Self {
         Self {
             enabled: true,
             host: "127.0.0.1".to_string(),
             port: 41997,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            tcp_backlog: DEFAULT_TCP_BACKLOG,
+            tcp_nodelay: false,
+            shutdown_drain_timeout: DEFAULT_SHUTDOWN_DRAIN_TIMEOUT,
         }
     }
 }
@@ -71,25 +121,29 @@ impl WebServer {
             bail!("Webserver is disabled")
         }
 
-        let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
-            .routes(routes!(get_account))
-            .routes(routes!(get_did_document))
-            .routes(routes!(post_transaction))
-            .routes(routes!(post_transaction2))
-            .routes(routes!(get_commitment))
-            .layer(CorsLayer::permissive())
-            .with_state(self.session.clone())
-            .split_for_parts();
-
-        let api = OpenApiBuilder::from(api).info(Info::new("Prism Full Node API", "0.1.0")).build();
+        if self.cfg.tcp_backlog == 0 {
+            bail!("Webserver TCP backlog must be non-zero")
+        }
 
-        let router = router.merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api));
+        let router = self.router();
 
         let addr = SocketAddr::new(
             self.cfg.host.parse().expect("IP address can be parsed"),
             self.cfg.port,
         );
-        let listener = TcpListener::bind(addr).await.expect("Binding to address works");
+        let socket = if addr.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        socket.set_reuseaddr(true)?;
+        socket.bind(addr)?;
+        let tcp_nodelay = self.cfg.tcp_nodelay;
+        let listener = socket.listen(self.cfg.tcp_backlog)?.tap_io(move |stream| {
+            if let Err(e) = stream.set_nodelay(tcp_nodelay) {
+                warn!("Failed to set TCP_NODELAY on connection: {}", e);
+            }
+        });
         let server = axum::serve(listener, router.into_make_service());
 
         let socket_addr = server.local_addr()?;
@@ -99,85 +153,295 @@ impl WebServer {
             socket_addr.port()
         );
 
-        let cancellation_token = cancellation_token.clone();
-        server
+        let shutdown_token = cancellation_token.clone();
+        let server = server
             .with_graceful_shutdown(async move {
-                cancellation_token.cancelled().await;
+                shutdown_token.cancelled().await;
                 info!("Webserver shutting down gracefully");
             })
-            .await?;
+            .into_future();
+        tokio::pin!(server);
+
+        tokio::select! {
+            result = &mut server => result?,
+            _ = cancellation_token.cancelled() => {
+                // the server stopped accepting connections, but requests that were already
+                // received get to finish, so that accepted transactions are not dropped
+                let drain_timeout = self.cfg.shutdown_drain_timeout;
+                match tokio::time::timeout(drain_timeout, server).await {
+                    Ok(result) => result?,
+                    Err(_) => warn!(
+                        "Webserver dropped in-flight requests after draining for {:?}",
+                        drain_timeout
+                    ),
+                }
+            }
+        }
 
         Ok(())
     }
+
+    fn router(&self) -> Router {
+        let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
+            .routes(routes!(get_account))
+            .routes(routes!(get_did_document))
+            .routes(routes!(post_transaction))
+            .routes(routes!(post_transactions))
+            .routes(routes!(post_did_transaction))
+            .routes(routes!(validate_transaction))
+            .routes(routes!(get_commitment))
+            .routes(routes!(get_genesis_operation))
+            .routes(routes!(get_last_operation))
+            .routes(routes!(resolve_handle))
+            .routes(routes!(resolve_did))
+            .routes(routes!(get_plc_data))
+            // WebSocket endpoints can not be described by OpenAPI
+            .route("/ws/commitments", get(stream_commitments))
+            .route("/ws/account/{did}", get(stream_account))
+            .layer(middleware::from_fn(require_json_content_type))
+            .layer(DefaultBodyLimit::max(self.cfg.max_body_bytes))
+            .layer(CorsLayer::permissive())
+            .with_state(self.session.clone())
+            .split_for_parts();
+
+        let api = OpenApiBuilder::from(api).info(Info::new("Prism Full Node API", "0.1.0")).build();
+
+        router
+            .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api))
+            .layer(middleware::from_fn(propagate_request_id))
+    }
+}
+
+/// Runs the request in a span carrying its [`REQUEST_ID_HEADER`], generating a UUID if the header
+/// is absent, and echoes the id in the response.
+async fn propagate_request_id(request: Request, next: Next) -> Response {
+    let request_id = request.headers().get(REQUEST_ID_HEADER).cloned().unwrap_or_else(|| {
+        HeaderValue::try_from(Uuid::new_v4().to_string()).expect("UUID is a valid header value")
+    });
+    let span = info_span!(
+        "request",
+        request_id = %String::from_utf8_lossy(request_id.as_bytes()),
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = next.run(request).instrument(span).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, request_id);
+    response
+}
+
+/// Rejects requests with a body that is not declared as JSON, which all POST endpoints expect,
+/// with a descriptive 415 instead of the extractor's plain-text rejection.
+async fn require_json_content_type(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+
+    let content_type = request.headers().get(header::CONTENT_TYPE);
+    let is_json = content_type
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .is_some_and(|mime| {
+            mime == "application/json"
+                || (mime.starts_with("application/") && mime.ends_with("+json"))
+        });
+    if is_json {
+        return next.run(request).await;
+    }
+
+    let received = content_type.map_or_else(
+        || "no Content-Type".to_string(),
+        |value| {
+            format!(
+                "Content-Type {:?}",
+                String::from_utf8_lossy(value.as_bytes())
+            )
+        },
+    );
+    (
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        Json(TransactionErrorResponse {
+            error_code: "unsupported_content_type".to_string(),
+            message: format!("expected Content-Type application/json, got {}", received),
+            field: None,
+        }),
+    )
+        .into_response()
 }
 
 /// Updates or inserts a transaction in the transparency dictionary, pending inclusion in the next
-/// epoch.
+/// epoch. The response tells where the transaction was queued and which epoch can include it.
 #[utoipa::path(
     post,
     path = "/transaction",
     request_body = Transaction,
+    params(
+        ("Idempotency-Key" = Option<String>, Header,
+            description = "Retries with the same key return the first outcome instead of queuing again")
+    ),
     responses(
-        (status = 200, description = "Entry update queued for insertion into next epoch"),
-        (status = 400, description = "Bad request"),
+        (status = 200, description = "Entry update queued for insertion into next epoch", body = QueuedTransaction),
+        (status = 400, description = "Transaction rejected", body = TransactionErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse),
+        (status = 422, description = "Idempotency key was already used for a different transaction", body = TransactionErrorResponse),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn post_transaction(
     State(session): State<Arc<Prover>>,
+    headers: HeaderMap,
     Json(transaction): Json<Transaction>,
 ) -> impl IntoResponse {
-    match session.validate_and_queue_update(transaction).await {
-        Ok(_) => (
-            StatusCode::OK,
-            "Entry update queued for insertion into next epoch",
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            format!("Could not update entry: {}", e),
-        )
-            .into_response(),
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let span = transaction_span(&transaction);
+    let result = session
+        .validate_and_queue_update_idempotent(transaction, idempotency_key)
+        .instrument(span.clone())
+        .await;
+    record_outcome(&span, &result);
+
+    match result {
+        Ok(queued) => (StatusCode::OK, Json(queued)).into_response(),
+        Err(e) => {
+            let status = match e.downcast_ref::<TransactionError>() {
+                Some(TransactionError::IdempotencyKeyReused(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            (status, Json(TransactionErrorResponse::from(&e))).into_response()
+        }
     }
 }
 
-/// Updates or inserts a transaction in the transparency dictionary, pending inclusion in the next
-/// epoch.
+/// Queues multiple transactions, e.g. when migrating many DIDs. Each transaction is validated
+/// independently, so a rejected transaction does not fail the batch.
 #[utoipa::path(
     post,
-    path = "/transaction_2",
-    request_body = SignedPlcTransaction,
+    path = "/transactions",
+    request_body = Vec<Transaction>,
     responses(
-        (status = 200, description = "Entry update queued for insertion into next epoch"),
+        (status = 200, description = "Outcome of each transaction", body = Vec<TransactionResult>),
         (status = 400, description = "Bad request"),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse)
+    )
+)]
+async fn post_transactions(
+    State(session): State<Arc<Prover>>,
+    Json(transactions): Json<Vec<Transaction>>,
+) -> impl IntoResponse {
+    let mut results = Vec::with_capacity(transactions.len());
+    for (index, transaction) in transactions.into_iter().enumerate() {
+        let span = transaction_span(&transaction);
+        let result = session.validate_and_queue_update(transaction).instrument(span.clone()).await;
+        record_outcome(&span, &result);
+
+        results.push(match result {
+            Ok(_) => TransactionResult::queued(index),
+            Err(e) => TransactionResult::rejected(index, e.to_string()),
+        });
+    }
+    (StatusCode::OK, Json(results)).into_response()
+}
+
+/// Queues a PLC-shaped DID transaction, as produced by atproto tooling, pending inclusion in the
+/// next epoch.
+#[utoipa::path(
+    post,
+    path = "/did-transaction",
+    request_body = SignedPlcTransaction,
+    responses(
+        (status = 200, description = "Entry update queued for insertion into next epoch", body = QueuedTransaction),
+        (status = 400, description = "Malformed or rejected transaction", body = TransactionErrorResponse),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse),
         (status = 500, description = "Internal server error")
     )
 )]
-async fn post_transaction2(
+async fn post_did_transaction(
     State(session): State<Arc<Prover>>,
-    Json(transaction): Json<SignedPlcTransaction>,
+    Json(did_transaction): Json<SignedPlcTransaction>,
 ) -> impl IntoResponse {
-    let transaction = transaction.try_into().map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            format!("Failed to convert DidTransaction to Transaction: {}", e),
-        )
-            .into_response()
-    });
-    match session.validate_and_queue_update(transaction.unwrap()).await {
-        Ok(_) => (
-            StatusCode::OK,
-            "Entry update queued for insertion into next epoch",
-        )
-            .into_response(),
+    let transaction: Transaction = match did_transaction.try_into() {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            let e = anyhow::Error::from(e);
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(TransactionErrorResponse::from(&e)),
+            )
+                .into_response();
+        }
+    };
+    let span = transaction_span(&transaction);
+    let result = session.validate_and_queue_update(transaction).instrument(span.clone()).await;
+    record_outcome(&span, &result);
+
+    match result {
+        Ok(queued) => (StatusCode::OK, Json(queued)).into_response(),
         Err(e) => (
             StatusCode::BAD_REQUEST,
-            format!("Could not update entry: {}", e),
+            Json(TransactionErrorResponse::from(&e)),
         )
             .into_response(),
     }
 }
 
+/// Checks whether a transaction would be accepted by the current state, without queuing it.
+#[utoipa::path(
+    post,
+    path = "/validate-transaction",
+    request_body = Transaction,
+    responses(
+        (status = 200, description = "Transaction validated", body = ValidationResponse),
+        (status = 400, description = "Bad request"),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse)
+    )
+)]
+async fn validate_transaction(
+    State(session): State<Arc<Prover>>,
+    Json(transaction): Json<Transaction>,
+) -> impl IntoResponse {
+    let response = match session.validate_transaction(&transaction).await {
+        Ok(_) => ValidationResponse {
+            valid: true,
+            error: None,
+        },
+        Err(e) => ValidationResponse {
+            valid: false,
+            error: Some(e.to_string()),
+        },
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Creates the span a submitted transaction is validated in, so that all logs of its validation can
+/// be correlated. The `outcome` field is filled in by [`record_outcome`].
+fn transaction_span(transaction: &Transaction) -> Span {
+    info_span!(
+        "transaction",
+        account_id = %transaction.id,
+        operation = transaction.operation.operation_type(),
+        outcome = field::Empty,
+    )
+}
+
+/// Records whether the transaction of the given span was accepted or rejected.
+fn record_outcome<T>(span: &Span, result: &Result<T>) {
+    let _entered = span.enter();
+    match result {
+        Ok(_) => {
+            span.record("outcome", "accepted");
+            info!("Transaction accepted");
+        }
+        Err(e) => {
+            span.record("outcome", "rejected");
+            warn!("Transaction rejected: {}", e);
+        }
+    }
+}
+
 /// The /get-account endpoint returns all added keys for a given user id.
 ///
 /// If the ID is not found in the database, the endpoint will return a 400 response with the message
@@ -188,7 +452,8 @@ async fn post_transaction2(
     request_body = AccountRequest,
     responses(
         (status = 200, description = "Successfully retrieved valid keys", body = AccountResponse),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse)
     )
 )]
 async fn get_account(
@@ -210,11 +475,142 @@ async fn get_account(
     (StatusCode::OK, Json(account_response)).into_response()
 }
 
+/// Returns the signed PLC operation that created the given DID, e.g. for explorers. Deriving the
+/// DID from it yields the requested DID.
+#[utoipa::path(
+    get,
+    path = "/{did}/genesis",
+    params(("did" = String, Path, description = "The DID, e.g. did:prism:...")),
+    responses(
+        (status = 200, description = "Genesis operation of the DID", body = SignedPLCOp),
+        (status = 404, description = "Unknown DID"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_genesis_operation(
+    State(session): State<Arc<Prover>>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    match session.get_genesis_operation(&did) {
+        Ok(Some(operation)) => (StatusCode::OK, Json(operation)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            format!("No genesis operation known for {}", did),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to retrieve genesis operation: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Returns the most recent operation applied to the given DID along with its CID, the current
+/// head of the DID. The `prev` of the returned transaction is the CID of the operation before it.
+#[utoipa::path(
+    get,
+    path = "/{did}/last-op",
+    params(("did" = String, Path, description = "The DID, e.g. did:prism:...")),
+    responses(
+        (status = 200, description = "Last operation of the DID", body = LastOperationResponse),
+        (status = 404, description = "Unknown DID"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_last_operation(
+    State(session): State<Arc<Prover>>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    let transaction = match session.get_last_transaction(&did) {
+        Ok(Some(transaction)) => transaction,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("No operation known for {}", did),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve last operation: {}", e),
+            )
+                .into_response();
+        }
+    };
+
+    match transaction.operation.cid() {
+        Ok(cid) => (
+            StatusCode::OK,
+            Json(LastOperationResponse { cid, transaction }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to compute CID of last operation: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// The /resolve-handle endpoint returns the DID listing the given handle in its `alsoKnownAs`. If
+/// several DIDs list the handle, the one created first is returned.
+#[utoipa::path(
+    post,
+    path = "/resolve-handle",
+    request_body = ResolveHandleRequest,
+    responses(
+        (status = 200, description = "DID claiming the handle", body = ResolveHandleResponse),
+        (status = 404, description = "No DID claims the handle"),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn resolve_handle(
+    State(session): State<Arc<Prover>>,
+    Json(request): Json<ResolveHandleRequest>,
+) -> impl IntoResponse {
+    match session.resolve_handle(&request.handle) {
+        Ok(Some(did)) => (StatusCode::OK, Json(ResolveHandleResponse { did })).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            format!("No DID claims handle {}", request.handle),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to resolve handle: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Returns the DID document media type requested by the `Accept` header, if any. The first
+/// supported media type in the header wins.
+fn requested_did_document_type(headers: &HeaderMap) -> Option<&'static str> {
+    let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+    accept
+        .split(',')
+        .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+        .find_map(|media_type| match media_type {
+            DID_DOCUMENT_CONTENT_TYPE => Some(DID_DOCUMENT_CONTENT_TYPE),
+            DID_DOCUMENT_CBOR_CONTENT_TYPE => Some(DID_DOCUMENT_CBOR_CONTENT_TYPE),
+            _ => None,
+        })
+}
+
 /// The /get-did-document endpoint returns account information along with its corresponding DID
 /// document.
 ///
 /// If the ID is not found in the database, the endpoint will return a 400 response with the message
 /// "Could not calculate values". The DID document is only generated if an account exists.
+/// Deactivated DIDs are still returned with a 200 response, their DID document is flagged with
+/// `deactivated`.
+///
+/// DID resolvers requesting `application/did+ld+json` or `application/did+cbor` via the `Accept`
+/// header receive only the DID document, as JSON or DAG-CBOR respectively, and a 404 response if
+/// the account does not exist. Any other `Accept` value yields the JSON response above.
 #[utoipa::path(
     post,
     path = "/get-did-document",
@@ -222,23 +618,36 @@ async fn get_account(
     responses(
         (status = 200, description = "Successfully retrieved account and DID document", body = AccountDidResponse),
         (status = 400, description = "Bad request"),
+        (status = 404, description = "DID document requested by media type, but account not found"),
+        (status = 415, description = "Content-Type is not application/json", body = TransactionErrorResponse),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn get_did_document(
     State(session): State<Arc<Prover>>,
+    headers: HeaderMap,
     Json(request): Json<AccountRequest>,
 ) -> impl IntoResponse {
-    info!("Retrieving DID document for account ID: {}", request.id);
-    let full_did = if request.id.starts_with("did:prism:") {
+    let prefix = session.did_config().prefix();
+    let full_did = if request.id.starts_with(&prefix) {
         request.id.clone()
     } else {
-        format!("did:prism:{}", request.id)
+        format!("{}{}", prefix, request.id)
     };
+    let span = info_span!(
+        "get_did_document",
+        account_id = %full_did,
+        outcome = field::Empty,
+    );
+    span.in_scope(|| info!("Retrieving DID document for account ID: {}", request.id));
 
-    let account_response = match session.get_account(&full_did).await {
+    let account_result = session.get_account(&full_did).instrument(span.clone()).await;
+    // no awaits below, so the guard is never held across one
+    let _entered = span.enter();
+    let account_response = match account_result {
         Ok(response) => response,
         Err(e) => {
+            span.record("outcome", "error");
             error!("Failed to retrieve account for DID document: {}", e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -249,9 +658,20 @@ async fn get_did_document(
     };
 
     let did_document = if let Some(ref account) = account_response.account {
+        if let Err(e) = verify_did_binding(&session, account) {
+            span.record("outcome", "error");
+            error!("Refusing to serve DID document of {}: {}", account.id(), e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to verify DID binding: {}", e),
+            )
+                .into_response();
+        }
+        span.record("outcome", "found");
         info!("Generating DID document for account: {}", account.id());
         Some(DidDocument::from(account))
     } else {
+        span.record("outcome", "not_found");
         warn!(
             "No account found for ID {}, returning None for DID document",
             request.id
@@ -261,6 +681,30 @@ async fn get_did_document(
 
     println!("DID Document: {:?}", did_document);
 
+    if let Some(content_type) = requested_did_document_type(&headers) {
+        let Some(did_document) = did_document else {
+            return (StatusCode::NOT_FOUND, "DID not found").into_response();
+        };
+        let body = if content_type == DID_DOCUMENT_CBOR_CONTENT_TYPE {
+            did_document.to_dag_cbor()
+        } else {
+            serde_json::to_vec(&did_document).map_err(Into::into)
+        };
+        return match body {
+            Ok(body) => {
+                (StatusCode::OK, [(header::CONTENT_TYPE, content_type)], body).into_response()
+            }
+            Err(e) => {
+                error!("Failed to encode DID document as {}: {}", content_type, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to encode DID document: {}", e),
+                )
+                    .into_response()
+            }
+        };
+    }
+
     let response = AccountDidResponse {
         account: account_response.account,
         proof: account_response.proof,
@@ -274,6 +718,118 @@ async fn get_did_document(
     (StatusCode::OK, Json(response)).into_response()
 }
 
+/// Resolves a DID according to W3C DID resolution, returning the DID document together with its
+/// metadata. Deactivated DIDs are still resolved, flagged as `deactivated` in the document
+/// metadata.
+#[utoipa::path(
+    get,
+    path = "/resolve/{did}",
+    params(("did" = String, Path, description = "The DID, e.g. did:prism:...")),
+    responses(
+        (status = 200, description = "Resolved DID", body = DidResolutionResult),
+        (status = 400, description = "Invalid DID", body = DidResolutionResult),
+        (status = 404, description = "Unknown DID", body = DidResolutionResult),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn resolve_did(
+    State(session): State<Arc<Prover>>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    if session.did_config().parse(&did).is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(DidResolutionResult::error("invalidDid")),
+        )
+            .into_response();
+    }
+
+    match session.get_account(&did).await {
+        Ok(AccountResponse {
+            account: Some(account),
+            ..
+        }) => match verify_did_binding(&session, &account) {
+            Ok(()) => (StatusCode::OK, Json(DidResolutionResult::from(&account))).into_response(),
+            Err(e) => {
+                error!("Refusing to resolve {}: {}", did, e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to verify DID binding: {}", e),
+                )
+                    .into_response()
+            }
+        },
+        Ok(_) => (
+            StatusCode::NOT_FOUND,
+            Json(DidResolutionResult::error("notFound")),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to resolve DID: {}", e),
+        )
+            .into_response(),
+    }
+}
+
+/// Checks that an account is bound to the genesis operation that created its DID, so that no DID
+/// document is served for an account whose id does not match its content. Accounts without a
+/// retained genesis operation, i.e. non-DID accounts, are not checked. DIDs are derived under the
+/// node's configured method.
+fn verify_did_binding(session: &Prover, account: &Account) -> Result<()> {
+    if let Some(genesis_op) = session.get_genesis_operation(account.id())? {
+        account.verify_did_binding_with(&genesis_op, session.did_config())?;
+    }
+    Ok(())
+}
+
+/// Returns the current operation fields of a DID in the shape of plc.directory's `/{did}/data`
+/// endpoint, see [`prism_common::account::Account::to_plc_data`]. Like plc.directory, deactivated
+/// DIDs are answered with 410.
+#[utoipa::path(
+    get,
+    path = "/{did}/data",
+    params(("did" = String, Path, description = "The DID, e.g. did:prism:...")),
+    responses(
+        (status = 200, description = "Current operation fields of the DID", body = Object),
+        (status = 400, description = "Invalid DID"),
+        (status = 404, description = "Unknown DID"),
+        (status = 410, description = "Deactivated DID"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_plc_data(
+    State(session): State<Arc<Prover>>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    if session.did_config().parse(&did).is_err() {
+        return (StatusCode::BAD_REQUEST, format!("Invalid DID: {}", did)).into_response();
+    }
+
+    match session.get_account(&did).await {
+        Ok(AccountResponse {
+            account: Some(account),
+            ..
+        }) => {
+            if account.is_deactivated() {
+                (StatusCode::GONE, format!("DID not available: {}", did)).into_response()
+            } else {
+                (StatusCode::OK, Json(account.to_plc_data())).into_response()
+            }
+        }
+        Ok(_) => (
+            StatusCode::NOT_FOUND,
+            format!("DID not registered: {}", did),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to retrieve DID data: {}", e),
+        )
+            .into_response(),
+    }
+}
+
 /// Returns the commitment (tree root) of the `IndexedMerkleTree` initialized from the database.
 #[utoipa::path(
     get,
@@ -289,3 +845,1100 @@ async fn get_commitment(State(session): State<Arc<Prover>>) -> impl IntoResponse
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
+
+/// Streams the commitment of every newly finalized epoch as a [`CommitmentResponse`] JSON
+/// message, until the client disconnects.
+async fn stream_commitments(
+    ws: WebSocketUpgrade,
+    State(session): State<Arc<Prover>>,
+) -> impl IntoResponse {
+    // subscribe before upgrading, so no epoch finalized during the handshake is missed
+    let commitments = session.subscribe_to_commitments();
+    ws.on_upgrade(move |socket| send_commitments(socket, commitments))
+}
+
+async fn send_commitments(
+    mut socket: WebSocket,
+    mut commitments: broadcast::Receiver<CommitmentResponse>,
+) {
+    loop {
+        tokio::select! {
+            commitment = commitments.recv() => {
+                let commitment = match commitment {
+                    Ok(commitment) => commitment,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Commitment subscriber lagged behind, skipped {} commitments", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let message = match serde_json::to_string(&commitment) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        error!("Failed to serialize commitment: {}", e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(message.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => if client_disconnected(message) {
+                break;
+            }
+        }
+    }
+    debug!("Commitment stream closed");
+}
+
+/// Streams the [`AccountResponse`] of the given account every time a transaction has been applied
+/// to it, until the client disconnects. The account does not need to exist yet.
+async fn stream_account(
+    ws: WebSocketUpgrade,
+    State(session): State<Arc<Prover>>,
+    Path(did): Path<String>,
+) -> impl IntoResponse {
+    // subscribe before upgrading, so no change applied during the handshake is missed
+    let changes = session.subscribe_to_account(&did).await;
+    ws.on_upgrade(move |socket| send_account_changes(socket, session, did, changes))
+}
+
+async fn send_account_changes(
+    mut socket: WebSocket,
+    session: Arc<Prover>,
+    did: String,
+    mut changes: broadcast::Receiver<()>,
+) {
+    loop {
+        tokio::select! {
+            change = changes.recv() => {
+                // lagging only skips intermediate states, the latest one is read below anyway
+                if let Err(RecvError::Closed) = change {
+                    break;
+                }
+                let response = match session.get_account(&did).await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        error!("Failed to retrieve changed account {}: {}", did, e);
+                        continue;
+                    }
+                };
+                let message = match serde_json::to_string(&response) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        error!("Failed to serialize account {}: {}", did, e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(message.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => if client_disconnected(message) {
+                break;
+            }
+        }
+    }
+    debug!("Account stream for {} closed", did);
+}
+
+/// Returns whether a message received on a server-push WebSocket means that the client is gone.
+/// All other incoming messages are ignored.
+fn client_disconnected(message: Option<Result<Message, axum::Error>>) -> bool {
+    matches!(message, Some(Ok(Message::Close(_)) | Err(_)) | None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProverOptions, prover_engine::engine::MockProverEngine};
+    use anyhow::anyhow;
+    use futures::StreamExt;
+    use prism_common::{
+        account::Account,
+        api::{
+            PendingTransaction, PrismApiError,
+            types::{AccountLookup, TransactionStatus},
+        },
+        operation::{DidConfig, Operation},
+        test_utils::create_did_tx,
+        transaction::UnsignedTransaction,
+    };
+    use prism_da::{SuccinctProof, memory::InMemoryDataAvailabilityLayer};
+    use prism_keys::SigningKey;
+    use prism_storage::{Database, inmemory::InMemoryDatabase};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        task::JoinHandle,
+    };
+    use tokio_tungstenite::tungstenite;
+    use tracing::{
+        Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+    };
+    use tracing_subscriber::{Registry, layer::Context, prelude::*};
+
+    /// Collects the fields of all spans, keyed by field name.
+    #[derive(Clone, Default)]
+    struct SpanFields(Arc<Mutex<HashMap<String, String>>>);
+
+    impl Visit for SpanFields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.lock().unwrap().insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.lock().unwrap().insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl<S: Subscriber> tracing_subscriber::Layer<S> for SpanFields {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    fn add_key_transaction() -> Transaction {
+        let signing_key = SigningKey::new_ed25519();
+        UnsignedTransaction {
+            id: "did:prism:alice".to_string(),
+            operation: Operation::AddKey {
+                key: SigningKey::new_ed25519().verifying_key(),
+            },
+            nonce: 1,
+            prev: None,
+        }
+        .sign(&signing_key)
+        .unwrap()
+    }
+
+    fn recorded_fields(result: Result<()>) -> HashMap<String, String> {
+        let fields = SpanFields::default();
+        let subscriber = Registry::default().with(fields.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = transaction_span(&add_key_transaction());
+            record_outcome(&span, &result);
+        });
+
+        fields.0.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn test_transaction_span_records_accepted_outcome() {
+        let fields = recorded_fields(Ok(()));
+
+        assert_eq!(fields["account_id"], "did:prism:alice");
+        assert_eq!(fields["operation"], "add_key");
+        assert_eq!(fields["outcome"], "accepted");
+    }
+
+    #[test]
+    fn test_transaction_span_records_rejected_outcome() {
+        let fields = recorded_fields(Err(anyhow!("invalid signature")));
+
+        assert_eq!(fields["operation"], "add_key");
+        assert_eq!(fields["outcome"], "rejected");
+    }
+
+    fn create_test_prover() -> Arc<Prover> {
+        create_test_prover_with_options(&ProverOptions::default())
+    }
+
+    fn create_test_prover_with_options(options: &ProverOptions) -> Arc<Prover> {
+        let db: Arc<Box<dyn Database>> = Arc::new(Box::new(InMemoryDatabase::new()));
+        let (da, _, _) = InMemoryDataAvailabilityLayer::new(Duration::from_millis(50));
+        let mut engine = MockProverEngine::new();
+        engine
+            .expect_prove_epoch()
+            .returning(|_, _, _| Ok((SuccinctProof::default(), SuccinctProof::default())));
+
+        let prover = Prover::new_with_engine(
+            db,
+            Arc::new(da),
+            Arc::new(engine),
+            options,
+            CancellationToken::new(),
+        )
+        .unwrap();
+        Arc::new(prover)
+    }
+
+    /// Serves the web server of `prover` on a random local port and returns its address.
+    async fn serve(prover: Arc<Prover>) -> SocketAddr {
+        let router = WebServer::new(WebServerConfig::default(), prover).router();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_stream_commitments_sends_finalized_commitment() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/ws/commitments", addr))
+                .await
+                .unwrap();
+
+        prover.finalize_new_epoch(0, Vec::new(), 0).await.unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("commitment should arrive")
+            .unwrap()
+            .unwrap();
+        let tungstenite::Message::Text(text) = message else {
+            panic!("expected a text message, got {:?}", message);
+        };
+        let response: CommitmentResponse = serde_json::from_str(&text).unwrap();
+        let current = prover.get_commitment().await.unwrap();
+        assert_eq!(response.commitment, current.commitment);
+        assert_eq!(response.epoch, current.epoch);
+    }
+
+    #[tokio::test]
+    async fn test_commitment_epoch_grows_with_state_changes() {
+        let prover = create_test_prover();
+        let before = prover.get_commitment().await.unwrap();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let after = prover.get_commitment().await.unwrap();
+        assert_ne!(after.commitment, before.commitment);
+        assert!(after.epoch > before.epoch);
+        assert!(after > before);
+
+        // the timestamp is the time the epoch was committed at, not the time of the request
+        assert!(after.timestamp.is_some());
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(prover.get_commitment().await.unwrap(), after);
+    }
+
+    #[tokio::test]
+    async fn test_stream_account_sends_changed_account() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+
+        // subscribing works before the account exists
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/ws/account/{}", addr, did))
+                .await
+                .unwrap();
+
+        prover.process_transaction(create_tx).await.unwrap();
+        let created = receive_account(&mut socket).await;
+        assert_eq!(created.nonce(), 1);
+
+        let new_key = SigningKey::new_secp256k1().verifying_key();
+        let add_key_tx = created
+            .modify()
+            .add_key(new_key.clone())
+            .unwrap()
+            .sign(&signing_key)
+            .unwrap()
+            .transaction();
+        prover.process_transaction(add_key_tx).await.unwrap();
+
+        let updated = receive_account(&mut socket).await;
+        assert_eq!(updated.nonce(), 2);
+        assert!(updated.valid_keys().contains(&new_key));
+    }
+
+    #[tokio::test]
+    async fn test_stream_account_sends_account_changed_by_epoch() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{}/ws/account/{}", addr, did))
+                .await
+                .unwrap();
+
+        // the prover applies queued transactions in batches when finalizing an epoch
+        prover.finalize_new_epoch(0, vec![create_tx], 0).await.unwrap();
+        let created = receive_account(&mut socket).await;
+        assert_eq!(created.id(), did);
+        assert_eq!(created.nonce(), 1);
+    }
+
+    async fn receive_account<S>(socket: &mut S) -> Account
+    where
+        S: StreamExt<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("account change should arrive")
+            .unwrap()
+            .unwrap();
+        let tungstenite::Message::Text(text) = message else {
+            panic!("expected a text message, got {:?}", message);
+        };
+        let response: AccountResponse = serde_json::from_str(&text).unwrap();
+        response.account.expect("changed account should exist")
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed_or_generated() {
+        let addr = serve(create_test_prover()).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/get-current-commitment", addr);
+
+        let response =
+            client.get(&url).header(REQUEST_ID_HEADER, "gateway-1234").send().await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "gateway-1234");
+
+        let response = client.get(&url).send().await.unwrap();
+        let generated = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(Uuid::parse_str(generated).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_request_body_is_rejected() {
+        let addr = serve(create_test_prover()).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/transaction", addr);
+
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let response = client.post(&url).json(&transaction).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let oversized = vec![b' '; DEFAULT_MAX_BODY_BYTES + 1];
+        let response = client
+            .post(&url)
+            .header("content-type", "application/json")
+            .body(oversized)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_content_type_is_rejected_with_descriptive_error() {
+        let addr = serve(create_test_prover()).await;
+        let client = reqwest::Client::new();
+
+        for path in ["transaction", "get-account"] {
+            let response = client
+                .post(format!("http://{}/{}", addr, path))
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body("id=did%3Aprism%3Aalice")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE
+            );
+            let error: TransactionErrorResponse = response.json().await.unwrap();
+            assert_eq!(error.error_code, "unsupported_content_type");
+            assert!(error.message.contains("application/x-www-form-urlencoded"));
+        }
+
+        // parameters of the media type are fine
+        let response = client
+            .post(format!("http://{}/get-account", addr))
+            .header("content-type", "application/json; charset=utf-8")
+            .body(r#"{"id":"did:prism:alice"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_retried_transaction_with_idempotency_key_is_queued_once() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/transaction", addr);
+
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+
+        let mut receipts = Vec::new();
+        for _ in 0..2 {
+            let response = client
+                .post(&url)
+                .header(IDEMPOTENCY_KEY_HEADER, "retry-1")
+                .json(&transaction)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            receipts.push(response.json::<QueuedTransaction>().await.unwrap());
+        }
+
+        assert_eq!(receipts[0], receipts[1]);
+        assert_eq!(prover.get_pending_transactions().read().await.len(), 1);
+
+        // the key is bound to the transaction it was first used for
+        let other_key = SigningKey::new_secp256k1();
+        let other = create_did_tx(&other_key);
+        let response = client
+            .post(&url)
+            .header(IDEMPOTENCY_KEY_HEADER, "retry-1")
+            .json(&other)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let error: TransactionErrorResponse = response.json().await.unwrap();
+        assert_eq!(error.error_code, "idempotency_key_reused");
+        assert_eq!(prover.get_pending_transactions().read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retried_rejection_keeps_its_error_code() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/transaction", addr);
+
+        let signing_key = SigningKey::new_secp256k1();
+        let mut transaction = create_did_tx(&signing_key);
+        transaction.nonce = 1;
+
+        for _ in 0..2 {
+            let response = client
+                .post(&url)
+                .header(IDEMPOTENCY_KEY_HEADER, "retry-2")
+                .json(&transaction)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+            let error: TransactionErrorResponse = response.json().await.unwrap();
+            assert_eq!(error.error_code, "nonce_mismatch");
+        }
+        assert!(prover.get_pending_transactions().read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_queued_transaction_targets_next_epoch() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = &reqwest::Client::new();
+        let url = &format!("http://{}/transaction", addr);
+
+        let post_create_did = || async move {
+            let signing_key = SigningKey::new_secp256k1();
+            let transaction = create_did_tx(&signing_key);
+            let response = client.post(url).json(&transaction).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            let queued: QueuedTransaction = response.json().await.unwrap();
+            assert_eq!(queued.tx_hash, transaction.hash().unwrap());
+            queued
+        };
+
+        let first = post_create_did().await;
+        assert_eq!(first.queue_position, 0);
+        assert_eq!(first.target_epoch, 0);
+
+        prover.finalize_new_epoch(0, Vec::new(), 0).await.unwrap();
+        let second = post_create_did().await;
+        assert_eq!(second.queue_position, 1);
+        assert_eq!(second.target_epoch, 1);
+
+        prover.finalize_new_epoch(1, Vec::new(), 0).await.unwrap();
+        let third = post_create_did().await;
+        assert_eq!(third.target_epoch, 2);
+
+        // transactions posted through the API carry the same receipt
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let tx_hash = transaction.hash().unwrap();
+        let pending = PrismApi::post_transaction(prover.as_ref(), transaction, None).await.unwrap();
+        let receipt = pending.receipt().unwrap();
+        assert_eq!(receipt.tx_hash, tx_hash);
+        assert_eq!(receipt.queue_position, 3);
+        assert_eq!(receipt.target_epoch, 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_transaction_reports_error_code() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/transaction", addr);
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let mut account = Account::default();
+        account.process_transaction(&create_tx).unwrap();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let add_key_tx = account
+            .modify()
+            .add_key(SigningKey::new_secp256k1().verifying_key())
+            .unwrap()
+            .sign(&signing_key)
+            .unwrap()
+            .transaction();
+
+        let mut wrong_nonce = add_key_tx.clone();
+        wrong_nonce.nonce += 1;
+        let mut wrong_signature = add_key_tx;
+        wrong_signature.signature = signing_key.sign(b"something else").unwrap();
+
+        for (transaction, error_code, field) in [
+            (wrong_nonce, "nonce_mismatch", "nonce"),
+            (wrong_signature, "invalid_signature", "signature"),
+        ] {
+            let response = client.post(&url).json(&transaction).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+            let error: TransactionErrorResponse = response.json().await.unwrap();
+            assert_eq!(error.error_code, error_code);
+            assert_eq!(error.field.as_deref(), Some(field));
+        }
+        assert!(prover.get_pending_transactions().read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_did_transaction_is_queued() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let did_transaction: SignedPlcTransaction = transaction.clone().try_into().unwrap();
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/did-transaction", addr))
+            .json(&did_transaction)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let pending = prover.get_pending_transactions();
+        assert_eq!(*pending.read().await, vec![transaction]);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_did_transaction_is_rejected() {
+        let addr = serve(create_test_prover()).await;
+
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let mut did_transaction: SignedPlcTransaction = transaction.try_into().unwrap();
+        did_transaction.vk = "did:key:zinvalid".to_string();
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/did-transaction", addr))
+            .json(&did_transaction)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_post_transactions_reports_outcome_per_item() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+
+        let create_did = || {
+            let signing_key = SigningKey::new_secp256k1();
+            create_did_tx(&signing_key)
+        };
+        let mut invalid = create_did();
+        invalid.nonce = 5;
+        let transactions = vec![create_did(), invalid, create_did()];
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/transactions", addr))
+            .json(&transactions)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        let results: Vec<TransactionResult> = response.json().await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], TransactionResult::queued(0));
+        assert_eq!(results[1].index, 1);
+        assert_eq!(results[1].status, TransactionStatus::Rejected);
+        assert!(results[1].error.is_some());
+        assert_eq!(results[2], TransactionResult::queued(2));
+
+        assert_eq!(prover.get_pending_transactions().read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_genesis_operation_derives_requested_did() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let response = client.get(format!("http://{}/{}/genesis", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let operation: SignedPLCOp = response.json().await.unwrap();
+        assert_eq!(operation.derive_did(), did);
+
+        let response =
+            client.get(format!("http://{}/did:prism:unknown/genesis", addr)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_last_operation_returns_latest_operation() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+        let mut account = Account::default();
+        account.process_transaction(&create_tx).unwrap();
+        let create_cid = create_tx.operation.cid().unwrap();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let add_key_tx = account
+            .modify()
+            .add_key(SigningKey::new_secp256k1().verifying_key())
+            .unwrap()
+            .sign(&signing_key)
+            .unwrap()
+            .transaction();
+        prover.process_transaction(add_key_tx.clone()).await.unwrap();
+
+        let response = client.get(format!("http://{}/{}/last-op", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let last_op: LastOperationResponse = response.json().await.unwrap();
+        assert_eq!(last_op.transaction, add_key_tx);
+        assert_eq!(last_op.transaction.prev, Some(create_cid));
+        assert_eq!(last_op.cid, add_key_tx.operation.cid().unwrap());
+
+        let response =
+            client.get(format!("http://{}/did:prism:unknown/last-op", addr)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    /// Creates a DID listing `handle` in its `alsoKnownAs` and returns the DID.
+    async fn create_did_with_handle(prover: &Prover, handle: &str) -> String {
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = Account::builder()
+            .create_did()
+            .with_rotation_keys(vec![signing_key.verifying_key()])
+            .with_atproto_pds("http://localhost:2583".to_string())
+            .with_also_known_as(handle.to_string())
+            .build(&signing_key)
+            .unwrap()
+            .sign(&signing_key)
+            .unwrap()
+            .transaction();
+        let did = create_tx.id.clone();
+        prover.process_transaction(create_tx).await.unwrap();
+        did
+    }
+
+    async fn post_resolve_handle(addr: SocketAddr, handle: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://{}/resolve-handle", addr))
+            .json(&ResolveHandleRequest {
+                handle: handle.to_string(),
+            })
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resolve_handle_returns_claiming_did() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let did = create_did_with_handle(&prover, "at://alice.test").await;
+
+        let response = post_resolve_handle(addr, "at://alice.test").await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let resolved: ResolveHandleResponse = response.json().await.unwrap();
+        assert_eq!(resolved.did, did);
+        assert_eq!(prover.resolve_handle("at://alice.test").unwrap(), Some(did));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_handle_of_unclaimed_handle_is_not_found() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        create_did_with_handle(&prover, "at://alice.test").await;
+
+        let response = post_resolve_handle(addr, "at://bob.test").await;
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        assert!(matches!(
+            PrismApi::resolve_handle(prover.as_ref(), "at://bob.test").await,
+            Err(PrismApiError::InvalidTarget(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_handle_collision_returns_earliest_did() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let first = create_did_with_handle(&prover, "at://alice.test").await;
+        let second = create_did_with_handle(&prover, "at://alice.test").await;
+        assert_ne!(first, second);
+
+        let response = post_resolve_handle(addr, "at://alice.test").await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let resolved: ResolveHandleResponse = response.json().await.unwrap();
+        assert_eq!(resolved.did, first);
+    }
+
+    #[tokio::test]
+    async fn test_custom_did_method_is_served() {
+        let prover = create_test_prover_with_options(&ProverOptions {
+            did_config: DidConfig::new("prismtest"),
+            ..ProverOptions::default()
+        });
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = Account::builder()
+            .create_did()
+            .with_did_config(DidConfig::new("prismtest"))
+            .with_rotation_keys(vec![signing_key.verifying_key()])
+            .with_atproto_pds("http://localhost:2583".to_string())
+            .build(&signing_key)
+            .unwrap()
+            .sign(&signing_key)
+            .unwrap()
+            .transaction();
+        let did = create_tx.id.clone();
+        prover.validate_and_queue_update(create_tx.clone()).await.unwrap();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let response = client.get(format!("http://{}/{}/data", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        // the DID binding is checked under the configured method
+        let response = client.get(format!("http://{}/resolve/{}", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let result: DidResolutionResult = response.json().await.unwrap();
+        assert_eq!(result.did_document.unwrap().id, did);
+
+        let response =
+            client.get(format!("http://{}/resolve/did:prism:unknown", addr)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_did_returns_resolution_metadata() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let response = client.get(format!("http://{}/resolve/{}", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let result: DidResolutionResult = response.json().await.unwrap();
+        assert_eq!(result.did_document.unwrap().id, did);
+        assert!(!result.did_document_metadata.deactivated);
+        assert!(result.did_document_metadata.version_id.is_some());
+
+        let response =
+            client.get(format!("http://{}/resolve/did:prism:unknown", addr)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        let result: DidResolutionResult = response.json().await.unwrap();
+        assert_eq!(
+            result.did_resolution_metadata.error.as_deref(),
+            Some("notFound")
+        );
+
+        let response = client
+            .get(format!("http://{}/resolve/did:web:example.com", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_get_plc_data() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+        prover.process_transaction(create_tx).await.unwrap();
+        let account = prover.get_account(&did).await.unwrap().account.unwrap();
+
+        let response = client.get(format!("http://{}/{}/data", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let data: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(data, account.to_plc_data());
+
+        let response =
+            client.get(format!("http://{}/did:prism:unknown/data", addr)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+        let deactivate_tx =
+            account.modify().deactivate().unwrap().sign(&signing_key).unwrap().transaction();
+        prover.process_transaction(deactivate_tx).await.unwrap();
+        let response = client.get(format!("http://{}/{}/data", addr, did)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::GONE);
+    }
+
+    #[tokio::test]
+    async fn test_did_document_content_types_round_trip_to_same_document() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let signing_key = SigningKey::new_secp256k1();
+        let create_tx = create_did_tx(&signing_key);
+        let did = create_tx.id.clone();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let request_document = |accept: &'static str| {
+            client
+                .post(format!("http://{}/get-did-document", addr))
+                .header(reqwest::header::ACCEPT, accept)
+                .json(&AccountRequest { id: did.clone() })
+                .send()
+        };
+
+        let response = request_document(DID_DOCUMENT_CONTENT_TYPE).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            DID_DOCUMENT_CONTENT_TYPE
+        );
+        let json_document: DidDocument = response.json().await.unwrap();
+
+        let response = request_document(DID_DOCUMENT_CBOR_CONTENT_TYPE).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(
+            response.headers()[reqwest::header::CONTENT_TYPE],
+            DID_DOCUMENT_CBOR_CONTENT_TYPE
+        );
+        let cbor_document = DidDocument::from_dag_cbor(&response.bytes().await.unwrap()).unwrap();
+
+        assert_eq!(json_document.id, did);
+        assert_eq!(json_document, cbor_document);
+
+        // unknown media types fall back to the full JSON response
+        let response = request_document("text/html").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let response: AccountDidResponse = response.json().await.unwrap();
+        assert_eq!(response.did_document, Some(json_document));
+    }
+
+    #[tokio::test]
+    async fn test_did_document_of_unbound_account_is_not_served() {
+        let prover = create_test_prover();
+        let addr = serve(prover.clone()).await;
+        let client = reqwest::Client::new();
+
+        let create_did = || {
+            let signing_key = SigningKey::new_secp256k1();
+            create_did_tx(&signing_key)
+        };
+        let create_tx = create_did();
+        let did = create_tx.id.clone();
+        prover.process_transaction(create_tx).await.unwrap();
+
+        let get_did_document = || {
+            client
+                .post(format!("http://{}/get-did-document", addr))
+                .json(&AccountRequest { id: did.clone() })
+                .send()
+        };
+        let resolve_did = || client.get(format!("http://{}/resolve/{}", addr, did)).send();
+
+        assert_eq!(
+            get_did_document().await.unwrap().status(),
+            reqwest::StatusCode::OK
+        );
+        assert_eq!(
+            resolve_did().await.unwrap().status(),
+            reqwest::StatusCode::OK
+        );
+
+        // corrupt the binding by retaining the genesis operation of another DID
+        let other_genesis = SignedPLCOp::try_from(&create_did().operation).unwrap();
+        prover.get_db().set_genesis_operation(&did, &other_genesis).unwrap();
+
+        assert_eq!(
+            get_did_document().await.unwrap().status(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            resolve_did().await.unwrap().status(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    /// Runs [`WebServer::start`] on a free port until it serves requests, returning the port, the
+    /// token shutting it down and the server task.
+    async fn start_server(
+        cfg: WebServerConfig,
+        prover: Arc<Prover>,
+    ) -> (u16, CancellationToken, JoinHandle<Result<()>>) {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let server = WebServer::new(WebServerConfig { port, ..cfg }, prover);
+        let cancellation_token = CancellationToken::new();
+        let token = cancellation_token.clone();
+        let handle = tokio::spawn(async move { server.start(token).await });
+
+        let url = format!("http://127.0.0.1:{}/get-current-commitment", port);
+        let mut response = reqwest::get(&url).await;
+        for _ in 0..50 {
+            if response.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            response = reqwest::get(&url).await;
+        }
+        assert!(response.is_ok());
+
+        (port, cancellation_token, handle)
+    }
+
+    /// Sends the head of a `POST /transaction` announcing a body of `content_length` bytes.
+    async fn send_transaction_head(port: u16, content_length: usize) -> TcpStream {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let head = format!(
+            "POST /transaction HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            content_length
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream
+    }
+
+    #[tokio::test]
+    async fn test_start_with_custom_tcp_settings() {
+        let cfg = WebServerConfig {
+            tcp_backlog: 16,
+            tcp_nodelay: true,
+            ..WebServerConfig::default()
+        };
+        let (_, cancellation_token, handle) = start_server(cfg, create_test_prover()).await;
+
+        cancellation_token.cancel();
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_transactions() {
+        let prover = create_test_prover();
+        let (port, cancellation_token, handle) =
+            start_server(WebServerConfig::default(), prover.clone()).await;
+
+        let signing_key = SigningKey::new_secp256k1();
+        let transaction = create_did_tx(&signing_key);
+        let body = serde_json::to_vec(&transaction).unwrap();
+        let (body_start, body_end) = body.split_at(body.len() / 2);
+
+        // the request is in flight while shutting down, as only half of its body was sent
+        let mut stream = send_transaction_head(port, body.len()).await;
+        stream.write_all(body_start).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancellation_token.cancel();
+        stream.write_all(body_end).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        handle.await.unwrap().unwrap();
+        assert_eq!(
+            *prover.get_pending_transactions().read().await,
+            vec![transaction]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_draining_after_timeout() {
+        let cfg = WebServerConfig {
+            shutdown_drain_timeout: Duration::from_millis(100),
+            ..WebServerConfig::default()
+        };
+        let (port, cancellation_token, handle) = start_server(cfg, create_test_prover()).await;
+
+        // a request whose body never arrives
+        let _stream = send_transaction_head(port, 1024).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancellation_token.cancel();
+
+        tokio::time::timeout(Duration::from_secs(5), handle)
+            .await
+            .expect("server should stop after the drain timeout")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_rejects_zero_tcp_backlog() {
+        let cfg = WebServerConfig {
+            tcp_backlog: 0,
+            ..WebServerConfig::default()
+        };
+        let server = WebServer::new(cfg, create_test_prover());
+
+        assert!(server.start(CancellationToken::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_account_responses_verify_against_commitment() {
+        let prover = create_test_prover();
+        let mut dids = Vec::new();
+        for _ in 0..3 {
+            let signing_key = SigningKey::new_secp256k1();
+            let create_tx = create_did_tx(&signing_key);
+            dids.push(create_tx.id.clone());
+            prover.process_transaction(create_tx).await.unwrap();
+        }
+        let commitment = prover.get_commitment().await.unwrap().commitment;
+
+        for did in &dids {
+            let response = prover.get_account(did).await.unwrap();
+            assert!(response.account.is_some());
+            response.verify(did, &commitment).unwrap();
+        }
+
+        let missing = prover.get_account("did:prism:unknown").await.unwrap();
+        assert!(missing.account.is_none());
+        missing.verify("did:prism:unknown", &commitment).unwrap();
+
+        let lookup = prover.lookup_account("did:prism:unknown").await.unwrap();
+        assert!(matches!(lookup, AccountLookup::Absent(_)));
+        lookup.verify("did:prism:unknown", &commitment).unwrap();
+    }
+}
@@ -1,5 +1,12 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+};
+
 use base32::{Alphabet, decode, encode};
 
+const PADDING: u8 = b'=';
+
 pub trait ToBase32 {
     fn to_base32(&self) -> String;
 }
@@ -13,17 +20,70 @@ where
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Base32Error {
+    /// The input is not valid UTF-8
+    InvalidUtf8,
+    /// The input contains a character outside of the RFC 4648 base32 alphabet
+    InvalidCharacter { character: char, index: usize },
+    /// The (unpadded) input length can not be produced by base32 encoding
+    InvalidLength(usize),
+}
+
+impl Display for Base32Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUtf8 => write!(f, "base32 input is not valid UTF-8"),
+            Self::InvalidCharacter { character, index } => {
+                write!(
+                    f,
+                    "invalid base32 character {:?} at index {}",
+                    character, index
+                )
+            }
+            Self::InvalidLength(len) => write!(f, "invalid base32 length: {}", len),
+        }
+    }
+}
+
+impl Error for Base32Error {}
+
 pub trait FromBase32: Sized {
-    fn from_base32<T: AsRef<[u8]>>(base64: T) -> Option<Self>;
+    /// Decodes RFC 4648 base32 (case-insensitive, optional `=` padding).
+    fn from_base32_strict<T: AsRef<[u8]>>(base32: T) -> Result<Self, Base32Error>;
+
+    /// Like [`FromBase32::from_base32_strict`], but discards the error.
+    fn from_base32<T: AsRef<[u8]>>(base32: T) -> Option<Self> {
+        Self::from_base32_strict(base32).ok()
+    }
 }
 
 impl FromBase32 for Vec<u8> {
-    // TODO(DID): This base32 library SUCKS compared to b64
-    fn from_base32<T: AsRef<[u8]>>(base32: T) -> Option<Self> {
-        let bytes = str::from_utf8(base32.as_ref());
-        if bytes.is_err() {
-            return None;
+    fn from_base32_strict<T: AsRef<[u8]>>(base32: T) -> Result<Self, Base32Error> {
+        let input = str::from_utf8(base32.as_ref()).map_err(|_| Base32Error::InvalidUtf8)?;
+
+        let unpadded = input.trim_end_matches(PADDING as char);
+        let padding_len = input.len() - unpadded.len();
+        // Padding is optional, but when present it has to complete the last 8-char block
+        if padding_len > 0 && (padding_len > 6 || input.len() % 8 != 0) {
+            return Err(Base32Error::InvalidLength(input.len()));
         }
-        decode(Alphabet::Rfc4648Lower { padding: false }, bytes.unwrap())
+
+        if let Some((index, character)) =
+            unpadded.char_indices().find(|(_, c)| !matches!(c, 'a'..='z' | 'A'..='Z' | '2'..='7'))
+        {
+            return Err(Base32Error::InvalidCharacter { character, index });
+        }
+
+        // Lengths that leave 1, 3 or 6 trailing chars can't be produced by an encoder
+        if matches!(unpadded.len() % 8, 1 | 3 | 6) {
+            return Err(Base32Error::InvalidLength(unpadded.len()));
+        }
+
+        decode(
+            Alphabet::Rfc4648Lower { padding: false },
+            &unpadded.to_ascii_lowercase(),
+        )
+        .ok_or(Base32Error::InvalidLength(unpadded.len()))
     }
 }
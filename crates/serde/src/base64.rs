@@ -1,8 +1,25 @@
 use base64::{
-    DecodeError, DecodeSliceError, Engine, decoded_len_estimate,
-    engine::general_purpose::URL_SAFE_NO_PAD as BASE64,
+    DecodeError, DecodeSliceError, Engine, alphabet, decoded_len_estimate,
+    engine::{
+        DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig,
+        general_purpose::URL_SAFE_NO_PAD as BASE64,
+    },
 };
 
+/// base64url decoding that accepts both padded and unpadded input.
+const BASE64_PAD_INDIFFERENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Decodes base64url, padded or not. [`FromBase64`] only accepts the unpadded form, this is for
+/// input produced by external tooling that may pad, such as PLC signatures.
+pub fn decode_base64url_padded_or_not<T: AsRef<[u8]>>(
+    base64url: T,
+) -> Result<Vec<u8>, DecodeError> {
+    BASE64_PAD_INDIFFERENT.decode(base64url)
+}
+
 pub trait ToBase64 {
     fn to_base64(&self) -> String;
 }
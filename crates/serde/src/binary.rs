@@ -1,6 +1,17 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+/// Error of encoding a value with [`ToBinary`].
+pub type EncodeError = serde_ipld_dagcbor::error::EncodeError<std::collections::TryReserveError>;
+
+/// Error of decoding a value with [`FromBinary`].
+pub type DecodeError = serde_ipld_dagcbor::error::DecodeError<std::convert::Infallible>;
+
+/// Encodes values as DAG-CBOR.
+///
+/// The encoding is canonical: map keys are always emitted sorted by length and then bytewise,
+/// regardless of the iteration order of the underlying map type. Equal values therefore always
+/// encode to identical bytes, which DID derivation relies on.
 pub trait ToBinary {
     type Error;
 
@@ -11,7 +22,7 @@ impl<T> ToBinary for T
 where
     T: Serialize,
 {
-    type Error = serde_ipld_dagcbor::error::EncodeError<std::collections::TryReserveError>;
+    type Error = EncodeError;
 
     fn encode_to_bytes(&self) -> Result<Vec<u8>, Self::Error> {
         serde_ipld_dagcbor::to_vec(self)
@@ -28,7 +39,7 @@ impl<T> FromBinary for T
 where
     T: for<'de> Deserialize<'de>,
 {
-    type Error = serde_ipld_dagcbor::error::DecodeError<std::convert::Infallible>;
+    type Error = DecodeError;
     fn decode_from_bytes<B: AsRef<[u8]>>(bytes: B) -> Result<Self, Self::Error> {
         serde_ipld_dagcbor::from_slice(bytes.as_ref())
     }
@@ -73,3 +73,6 @@ pub mod raw_or_b64 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,128 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    base32::{Base32Error, FromBase32, ToBase32},
+    base64::{FromBase64, ToBase64, decode_base64url_padded_or_not},
+    binary::ToBinary,
+};
+
+#[test]
+fn test_base32_roundtrip() {
+    let data = b"prism did".to_vec();
+    let encoded = data.to_base32();
+    assert_eq!(encoded, "obzgs43nebsgsza");
+    assert_eq!(Vec::<u8>::from_base32_strict(&encoded).unwrap(), data);
+}
+
+#[test]
+fn test_base32_tolerates_padding() {
+    let padded = Vec::<u8>::from_base32_strict("obzgs43nebsgsza=").unwrap();
+    let unpadded = Vec::<u8>::from_base32_strict("obzgs43nebsgsza").unwrap();
+    assert_eq!(padded, unpadded);
+    assert_eq!(Vec::<u8>::from_base32_strict("mzxw6===").unwrap(), b"foo");
+
+    // padding has to complete the last block
+    assert_eq!(
+        Vec::<u8>::from_base32_strict("mzxw6=="),
+        Err(Base32Error::InvalidLength(7))
+    );
+}
+
+#[test]
+fn test_base64_rejects_padding() {
+    let data = b"prism".to_vec();
+    assert_eq!(data.to_base64(), "cHJpc20");
+    assert_eq!(Vec::<u8>::from_base64("cHJpc20").unwrap(), data);
+    assert!(Vec::<u8>::from_base64("cHJpc20=").is_err());
+}
+
+#[test]
+fn test_decode_base64url_padded_or_not() {
+    let data = b"prism".to_vec();
+    assert_eq!(decode_base64url_padded_or_not("cHJpc20").unwrap(), data);
+    assert_eq!(decode_base64url_padded_or_not("cHJpc20=").unwrap(), data);
+    assert_eq!(
+        decode_base64url_padded_or_not("-_-_").unwrap(),
+        [0xfb, 0xff, 0xbf]
+    );
+    assert!(decode_base64url_padded_or_not("+/+/").is_err());
+}
+
+#[test]
+fn test_base32_is_case_insensitive() {
+    let lower = Vec::<u8>::from_base32_strict("obzgs43nebsgsza").unwrap();
+    let upper = Vec::<u8>::from_base32_strict("OBZGS43NEBSGSZA").unwrap();
+    assert_eq!(lower, upper);
+}
+
+#[test]
+fn test_base32_invalid_input() {
+    assert_eq!(
+        Vec::<u8>::from_base32_strict("obzgs1"),
+        Err(Base32Error::InvalidCharacter {
+            character: '1',
+            index: 5
+        })
+    );
+    assert_eq!(
+        Vec::<u8>::from_base32_strict("mz=xw6=="),
+        Err(Base32Error::InvalidCharacter {
+            character: '=',
+            index: 2
+        })
+    );
+    assert_eq!(
+        Vec::<u8>::from_base32_strict("obz"),
+        Err(Base32Error::InvalidLength(3))
+    );
+    assert_eq!(
+        Vec::<u8>::from_base32_strict([0xff, 0xfe]),
+        Err(Base32Error::InvalidUtf8)
+    );
+    assert_eq!(Vec::<u8>::from_base32("obzgs1"), None);
+}
+
+#[test]
+fn test_binary_encoding_is_independent_of_map_insertion_order() {
+    #[derive(Serialize)]
+    struct WithMap {
+        entries: HashMap<String, String>,
+        id: String,
+    }
+
+    let keys = [
+        "atproto",
+        "atproto_pds",
+        "a",
+        "zz",
+        "signing",
+        "b",
+        "rotation",
+        "ab",
+        "handle",
+        "key_1",
+    ];
+
+    let encode = |order: &[&str]| {
+        let entries: HashMap<String, String> =
+            order.iter().map(|k| (k.to_string(), format!("value-{k}"))).collect();
+        WithMap {
+            entries,
+            id: "did:prism:test".to_string(),
+        }
+        .encode_to_bytes()
+        .unwrap()
+    };
+
+    let expected = encode(&keys);
+    let mut order = keys.to_vec();
+    for i in 0..100 {
+        order.rotate_left(3);
+        if i % 2 == 0 {
+            order.reverse();
+        }
+        order.swap(i % keys.len(), (i * 7) % keys.len());
+        assert_eq!(encode(&order), expected);
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use auto_impl::auto_impl;
 use jmt::storage::{TreeReader, TreeWriter};
-use prism_common::digest::Digest;
+use prism_common::{
+    account::Account, digest::Digest, operation::SignedPLCOp, transaction::Transaction,
+};
 use prism_da::FinalizedEpoch;
 
 #[auto_impl(&, Box, Arc)]
@@ -15,6 +17,23 @@ pub trait Database: Send + Sync + TreeReader + TreeWriter {
     fn get_latest_epoch_height(&self) -> Result<u64>;
     fn get_latest_epoch(&self) -> Result<FinalizedEpoch>;
 
+    /// Returns the PLC operation that created the DID, if the DID exists.
+    fn get_genesis_operation(&self, did: &str) -> Result<Option<SignedPLCOp>>;
+    fn set_genesis_operation(&self, did: &str, operation: &SignedPLCOp) -> Result<()>;
+
+    /// Returns the DID listing the handle (e.g. `at://alice.test`) in its `alsoKnownAs`, if any.
+    fn get_handle_did(&self, handle: &str) -> Result<Option<String>>;
+    fn set_handle_did(&self, handle: &str, did: &str) -> Result<()>;
+
+    /// Returns the most recent transaction applied to the DID, if the DID exists.
+    fn get_last_transaction(&self, did: &str) -> Result<Option<Transaction>>;
+    fn set_last_transaction(&self, did: &str, transaction: &Transaction) -> Result<()>;
+
+    /// Returns the state of the account after the operation with the given CID, if it was kept
+    /// for recovery forks to be applied to.
+    fn get_fork_base(&self, id: &str, cid: &str) -> Result<Option<Account>>;
+    fn set_fork_base(&self, id: &str, cid: &str, account: &Account) -> Result<()>;
+
     fn get_last_synced_height(&self) -> Result<u64>;
     fn set_last_synced_height(&self, height: &u64) -> Result<()>;
 
@@ -3,7 +3,9 @@ use jmt::{
     KeyHash, OwnedValue, Version,
     storage::{LeafNode, Node, NodeBatch, NodeKey, TreeReader, TreeWriter},
 };
-use prism_common::digest::Digest;
+use prism_common::{
+    account::Account, digest::Digest, operation::SignedPLCOp, transaction::Transaction,
+};
 use prism_da::FinalizedEpoch;
 use prism_errors::DatabaseError;
 use std::{
@@ -23,6 +25,10 @@ pub struct InMemoryDatabase {
     values: Arc<Mutex<HashMap<(Version, KeyHash), OwnedValue>>>,
     commitments: Arc<Mutex<HashMap<u64, Digest>>>,
     current_epochs: Arc<Mutex<Vec<FinalizedEpoch>>>,
+    genesis_operations: Arc<Mutex<HashMap<String, SignedPLCOp>>>,
+    handle_dids: Arc<Mutex<HashMap<String, String>>>,
+    last_transactions: Arc<Mutex<HashMap<String, Transaction>>>,
+    fork_bases: Arc<Mutex<HashMap<(String, String), Account>>>,
     sync_height: Arc<AtomicU64>,
 }
 
@@ -33,6 +39,10 @@ impl InMemoryDatabase {
             values: Arc::new(Mutex::new(HashMap::new())),
             commitments: Arc::new(Mutex::new(HashMap::new())),
             current_epochs: Arc::new(Mutex::new(Vec::new())),
+            genesis_operations: Arc::new(Mutex::new(HashMap::new())),
+            handle_dids: Arc::new(Mutex::new(HashMap::new())),
+            last_transactions: Arc::new(Mutex::new(HashMap::new())),
+            fork_bases: Arc::new(Mutex::new(HashMap::new())),
             sync_height: Arc::new(AtomicU64::new(UNINITIALIZED_SYNC_HEIGHT)),
         }
     }
@@ -131,6 +141,42 @@ impl Database for InMemoryDatabase {
         self.get_epoch(&height)
     }
 
+    fn get_genesis_operation(&self, did: &str) -> Result<Option<SignedPLCOp>> {
+        Ok(self.genesis_operations.lock().unwrap().get(did).cloned())
+    }
+
+    fn set_genesis_operation(&self, did: &str, operation: &SignedPLCOp) -> Result<()> {
+        self.genesis_operations.lock().unwrap().insert(did.to_string(), operation.clone());
+        Ok(())
+    }
+
+    fn get_handle_did(&self, handle: &str) -> Result<Option<String>> {
+        Ok(self.handle_dids.lock().unwrap().get(handle).cloned())
+    }
+
+    fn set_handle_did(&self, handle: &str, did: &str) -> Result<()> {
+        self.handle_dids.lock().unwrap().insert(handle.to_string(), did.to_string());
+        Ok(())
+    }
+
+    fn get_last_transaction(&self, did: &str) -> Result<Option<Transaction>> {
+        Ok(self.last_transactions.lock().unwrap().get(did).cloned())
+    }
+
+    fn set_last_transaction(&self, did: &str, transaction: &Transaction) -> Result<()> {
+        self.last_transactions.lock().unwrap().insert(did.to_string(), transaction.clone());
+        Ok(())
+    }
+
+    fn get_fork_base(&self, id: &str, cid: &str) -> Result<Option<Account>> {
+        Ok(self.fork_bases.lock().unwrap().get(&(id.to_string(), cid.to_string())).cloned())
+    }
+
+    fn set_fork_base(&self, id: &str, cid: &str, account: &Account) -> Result<()> {
+        self.fork_bases.lock().unwrap().insert((id.to_string(), cid.to_string()), account.clone());
+        Ok(())
+    }
+
     fn get_last_synced_height(&self) -> Result<u64> {
         // Acquire ordering so that readers see all prior writes up to the first store(Release).
         let h = self.sync_height.load(Ordering::Acquire);
@@ -152,6 +198,10 @@ impl Database for InMemoryDatabase {
         self.values.lock().unwrap().clear();
         self.commitments.lock().unwrap().clear();
         self.current_epochs.lock().unwrap().clear();
+        self.genesis_operations.lock().unwrap().clear();
+        self.handle_dids.lock().unwrap().clear();
+        self.last_transactions.lock().unwrap().clear();
+        self.fork_bases.lock().unwrap().clear();
         self.sync_height.store(UNINITIALIZED_SYNC_HEIGHT, Ordering::Release);
         Ok(())
     }
@@ -3,7 +3,9 @@ use jmt::{
     KeyHash, OwnedValue, Version,
     storage::{LeafNode, Node, NodeBatch, NodeKey, TreeReader, TreeWriter},
 };
-use prism_common::digest::Digest;
+use prism_common::{
+    account::Account, digest::Digest, operation::SignedPLCOp, transaction::Transaction,
+};
 use prism_errors::DatabaseError;
 use prism_serde::binary::{FromBinary, ToBinary};
 use rocksdb::{DB, DBWithThreadMode, MultiThreaded, Options};
@@ -94,6 +96,10 @@ enum Key {
     Node,
     ValueHistory,
     Epoch,
+    GenesisOperation,
+    HandleDid,
+    LastTransaction,
+    ForkBase,
 }
 
 fn create_final_key(prefix: Vec<u8>, suffix: impl AsRef<[u8]>) -> Vec<u8> {
@@ -119,6 +125,10 @@ impl Key {
             Self::Node => 1,
             Self::ValueHistory => 2,
             Self::Epoch => 3,
+            Self::GenesisOperation => 4,
+            Self::HandleDid => 5,
+            Self::LastTransaction => 6,
+            Self::ForkBase => 7,
         }
     }
 }
@@ -143,6 +153,97 @@ impl Database for RocksDBConnection {
         )?)
     }
 
+    fn get_genesis_operation(&self, did: &str) -> Result<Option<SignedPLCOp>> {
+        let Some(data) = self.connection.get(Key::GenesisOperation.with(did))? else {
+            return Ok(None);
+        };
+
+        let operation = SignedPLCOp::decode_from_bytes(&data).map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to decode genesis operation of {}: {}",
+                did, e
+            )))
+        })?;
+        Ok(Some(operation))
+    }
+
+    fn set_genesis_operation(&self, did: &str, operation: &SignedPLCOp) -> Result<()> {
+        let data = operation.encode_to_bytes().map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to encode genesis operation of {}: {}",
+                did, e
+            )))
+        })?;
+        Ok(self.connection.put(Key::GenesisOperation.with(did), data)?)
+    }
+
+    fn get_handle_did(&self, handle: &str) -> Result<Option<String>> {
+        let Some(data) = self.connection.get(Key::HandleDid.with(handle))? else {
+            return Ok(None);
+        };
+
+        let did = String::from_utf8(data).map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to decode DID of handle {}: {}",
+                handle, e
+            )))
+        })?;
+        Ok(Some(did))
+    }
+
+    fn set_handle_did(&self, handle: &str, did: &str) -> Result<()> {
+        Ok(self.connection.put(Key::HandleDid.with(handle), did)?)
+    }
+
+    fn get_last_transaction(&self, did: &str) -> Result<Option<Transaction>> {
+        let Some(data) = self.connection.get(Key::LastTransaction.with(did))? else {
+            return Ok(None);
+        };
+
+        let transaction = Transaction::decode_from_bytes(&data).map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to decode last transaction of {}: {}",
+                did, e
+            )))
+        })?;
+        Ok(Some(transaction))
+    }
+
+    fn set_last_transaction(&self, did: &str, transaction: &Transaction) -> Result<()> {
+        let data = transaction.encode_to_bytes().map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to encode last transaction of {}: {}",
+                did, e
+            )))
+        })?;
+        Ok(self.connection.put(Key::LastTransaction.with(did), data)?)
+    }
+
+    fn get_fork_base(&self, id: &str, cid: &str) -> Result<Option<Account>> {
+        let key = create_final_key(Key::ForkBase.with(id), cid);
+        let Some(data) = self.connection.get(key)? else {
+            return Ok(None);
+        };
+
+        let account = Account::decode_from_bytes(&data).map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to decode state of {} after {}: {}",
+                id, cid, e
+            )))
+        })?;
+        Ok(Some(account))
+    }
+
+    fn set_fork_base(&self, id: &str, cid: &str, account: &Account) -> Result<()> {
+        let data = account.encode_to_bytes().map_err(|e| {
+            anyhow!(DatabaseError::ParsingError(format!(
+                "Failed to encode state of {} after {}: {}",
+                id, cid, e
+            )))
+        })?;
+        Ok(self.connection.put(create_final_key(Key::ForkBase.with(id), cid), data)?)
+    }
+
     fn get_last_synced_height(&self) -> Result<u64> {
         let res = self
             .connection
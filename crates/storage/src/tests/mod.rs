@@ -6,7 +6,13 @@ use jmt::{
     KeyHash, OwnedValue, Version,
     storage::{NodeBatch, TreeReader, TreeWriter},
 };
-use prism_common::digest::Digest;
+use prism_common::{
+    account::Account,
+    digest::Digest,
+    operation::{SignedPLCOp, UnsignedPLCOp},
+};
+use prism_keys::SigningKey;
+use std::collections::HashMap;
 
 fn setup_db() -> (TempDir, RocksDBConnection) {
     let temp_dir = TempDir::new().unwrap();
@@ -28,6 +34,92 @@ fn test_rw_commitment() {
     assert_eq!(read_commitment, commitment);
 }
 
+#[test]
+fn test_rw_genesis_operation() {
+    let (_temp_dir, db) = setup_db();
+
+    let did = "did:prism:test";
+    assert_eq!(db.get_genesis_operation(did).unwrap(), None);
+
+    let operation = SignedPLCOp {
+        unsigned: UnsignedPLCOp::new_genesis(
+            vec!["did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme".to_string()],
+            HashMap::new(),
+            vec!["at://alice.test".to_string()],
+            "https://pds.test".to_string(),
+        ),
+        sig: "sig".to_string(),
+    };
+    db.set_genesis_operation(did, &operation).unwrap();
+
+    assert_eq!(db.get_genesis_operation(did).unwrap(), Some(operation));
+}
+
+#[test]
+fn test_rw_handle_did() {
+    let (_temp_dir, db) = setup_db();
+
+    let handle = "at://alice.test";
+    assert_eq!(db.get_handle_did(handle).unwrap(), None);
+
+    db.set_handle_did(handle, "did:prism:test").unwrap();
+
+    assert_eq!(
+        db.get_handle_did(handle).unwrap(),
+        Some("did:prism:test".to_string())
+    );
+}
+
+#[test]
+fn test_rw_last_transaction() {
+    let (_temp_dir, db) = setup_db();
+
+    let signing_key = SigningKey::new_secp256k1();
+    let transaction = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("https://pds.test".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let did = transaction.id.clone();
+    assert_eq!(db.get_last_transaction(&did).unwrap(), None);
+
+    db.set_last_transaction(&did, &transaction).unwrap();
+
+    assert_eq!(db.get_last_transaction(&did).unwrap(), Some(transaction));
+}
+
+#[test]
+fn test_rw_fork_base() {
+    let (_temp_dir, db) = setup_db();
+
+    let signing_key = SigningKey::new_secp256k1();
+    let transaction = Account::builder()
+        .create_did()
+        .with_rotation_keys(vec![signing_key.verifying_key()])
+        .with_atproto_pds("https://pds.test".to_string())
+        .build(&signing_key)
+        .unwrap()
+        .sign(&signing_key)
+        .unwrap()
+        .transaction();
+    let mut account = Account::default();
+    account.process_transaction(&transaction).unwrap();
+    let cid = account.head_cid().unwrap();
+    assert_eq!(db.get_fork_base(account.id(), cid).unwrap(), None);
+
+    db.set_fork_base(account.id(), cid, &account).unwrap();
+
+    assert_eq!(
+        db.get_fork_base(account.id(), cid).unwrap(),
+        Some(account.clone())
+    );
+    assert_eq!(db.get_fork_base("did:prism:other", cid).unwrap(), None);
+}
+
 #[test]
 fn test_write_and_read_value() {
     let (_temp_dir, db) = setup_db();
@@ -5,6 +5,7 @@ extern crate log;
 
 use anyhow::Result;
 // use prism_common::test_transaction_builder::TestTransactionBuilder;
+use prism_common::operation::DidConfig;
 use prism_da::{DataAvailabilityLayer, memory::InMemoryDataAvailabilityLayer};
 use prism_keys::{CryptoAlgorithm, SigningKey};
 use prism_prover::{
@@ -83,11 +84,13 @@ async fn test_light_client_prover_talking() -> Result<()> {
         sequencer: SequencerOptions {
             signing_key: Some(signing_key),
             batcher_enabled: true,
+            allowed_operations: None,
         },
         prover_engine: ProverEngineOptions {
             recursive_proofs: false,
         },
         webserver: WebServerConfig::default(),
+        did_config: DidConfig::DEFAULT,
     };
 
     let prover = Arc::new(Prover::new(
@@ -3,13 +3,23 @@ use jmt::{
     self, JellyfishMerkleTree, KeyHash, RootHash,
     storage::{NodeBatch, TreeReader, TreeUpdateBatch, TreeWriter},
 };
-use prism_common::digest::Digest;
+use prism_common::{
+    account::Account, digest::Digest, operation::DidConfig, transaction::Transaction,
+};
 use std::sync::Arc;
 
 use crate::hasher::TreeHasher;
 
 pub const SPARSE_MERKLE_PLACEHOLDER_HASH: KeyHash = KeyHash(*b"SPARSE_MERKLE_PLACEHOLDER_HASH__");
 
+/// Account states that recovery forks can be applied to, keyed by the account id and the CID of
+/// the operation that produced them. Accounts only commit to the digests of their former states,
+/// see [`Account::process_transaction_with_fork_base`].
+pub trait ForkBaseStore: Send + Sync {
+    fn get_fork_base(&self, id: &str, cid: &str) -> Result<Option<Account>>;
+    fn set_fork_base(&self, id: &str, cid: &str, account: &Account) -> Result<()>;
+}
+
 /// Wraps a [`JellyfishMerkleTree`] to provide a key-value store for [`Account`]s with batched
 /// insertions. This is prism's primary data structure for storing and retrieving [`Account`]s.
 pub struct KeyDirectoryTree<S>
@@ -20,6 +30,10 @@ where
     pub(crate) epoch: u64,
     pending_batch: Option<NodeBatch>,
     db: Arc<S>,
+    /// Method of the DIDs created in the tree
+    pub(crate) did_config: DidConfig,
+    /// Former account states, without which recovery forks are rejected
+    fork_bases: Option<Arc<dyn ForkBaseStore>>,
 }
 
 impl<S> KeyDirectoryTree<S>
@@ -32,6 +46,8 @@ where
             jmt: JellyfishMerkleTree::<Arc<S>, TreeHasher>::new(store),
             pending_batch: None,
             epoch: 0,
+            did_config: DidConfig::DEFAULT,
+            fork_bases: None,
         };
         let (_, batch) = tree
             .jmt
@@ -50,9 +66,49 @@ where
             jmt: JellyfishMerkleTree::<Arc<S>, TreeHasher>::new(store),
             pending_batch: None,
             epoch,
+            did_config: DidConfig::DEFAULT,
+            fork_bases: None,
         }
     }
 
+    /// Accepts DIDs of the method configured in `config` instead of `did:prism:`.
+    pub fn with_did_config(mut self, config: DidConfig) -> Self {
+        self.did_config = config;
+        self
+    }
+
+    /// Keeps the account states produced by the tree in `store`, so that recovery forks can be
+    /// applied to them.
+    pub fn with_fork_bases(mut self, store: Arc<dyn ForkBaseStore>) -> Self {
+        self.fork_bases = Some(store);
+        self
+    }
+
+    /// Returns the state `transaction` is applied to if it is a fork of `account`, if known.
+    pub(crate) fn fork_base(
+        &self,
+        account: &Account,
+        transaction: &Transaction,
+    ) -> Result<Option<Account>> {
+        match (account.fork_prev(transaction), &self.fork_bases) {
+            (Some(prev), Some(store)) => store.get_fork_base(&transaction.id, prev),
+            _ => Ok(None),
+        }
+    }
+
+    /// Keeps `account` as a state later forks can be applied to.
+    pub(crate) fn retain_fork_base(&self, account: &Account) -> Result<()> {
+        match (account.head_cid(), &self.fork_bases) {
+            (Some(cid), Some(store)) => store.set_fork_base(account.id(), cid, account),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the version of the tree, which grows with every written batch.
+    pub const fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
     pub fn get_commitment(&self) -> Result<Digest> {
         let root = self.get_current_root()?;
         Ok(Digest(root.0))
@@ -2,7 +2,9 @@ use jmt::{
     KeyHash, RootHash,
     proof::{SparseMerkleNode, SparseMerkleProof, UpdateMerkleProof},
 };
-use prism_common::{account::Account, digest::Digest, transaction::Transaction};
+use prism_common::{
+    account::Account, digest::Digest, operation::Operation, transaction::Transaction,
+};
 use prism_errors::ProofError;
 use prism_serde::binary::ToBinary;
 use serde::{Deserialize, Serialize};
@@ -36,7 +38,6 @@ impl Batch {
             match proof {
                 Proof::Insert(insert_proof) => {
                     // TODO(DID): Hash Verification here or one level lower?
-                    // When verifying account creation, ensure service challenge is verified as well
                     insert_proof.verify()?;
                     root = insert_proof.new_root;
                 }
@@ -77,6 +78,10 @@ pub struct InsertProof {
 
     /// The new account that was inserted.
     pub tx: Transaction,
+
+    /// Proof of the service an account is created for, whose challenge the
+    /// [`Operation::CreateAccount`] has to meet. `None` for all other operations.
+    pub service_proof: Option<ServiceProof>,
 }
 
 impl InsertProof {
@@ -85,6 +90,18 @@ impl InsertProof {
         self.non_membership_proof
             .verify_nonexistence()
             .map_err(|e| ProofError::NonexistenceError(e.to_string()))?;
+
+        if let Operation::CreateAccount { service_id, .. } = &self.tx.operation {
+            let Some(service_proof) = &self.service_proof else {
+                return Err(ProofError::MissingServiceProof(self.tx.id.clone()));
+            };
+            service_proof.verify(
+                self.non_membership_proof.root,
+                service_id,
+                &self.tx.operation,
+            )?;
+        }
+
         let mut account = Account::default();
         account
             .process_transaction(&self.tx)
@@ -108,6 +125,43 @@ impl InsertProof {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Proves the state of the service an account is created for.
+pub struct ServiceProof {
+    /// The service account
+    pub service: Account,
+    /// Inclusion proof of [`ServiceProof::service`] at the root the account is inserted into
+    pub membership_proof: MerkleProof,
+}
+
+impl ServiceProof {
+    /// The method called in circuit to verify that `operation` meets the challenge of the service
+    /// `service_id` at `root`.
+    pub fn verify(
+        &self,
+        root: Digest,
+        service_id: &str,
+        operation: &Operation,
+    ) -> Result<(), ProofError> {
+        if self.membership_proof.root != root
+            || self.membership_proof.key != KeyHash::with::<TreeHasher>(service_id)
+        {
+            return Err(ProofError::VerificationError(format!(
+                "service proof does not prove service {}",
+                service_id
+            )));
+        }
+        self.membership_proof.verify_existence(&self.service)?;
+
+        if self.service.challenge_key().is_none() {
+            return Err(ProofError::MissingServiceChallenge(service_id.to_string()));
+        }
+        self.service
+            .verify_service_challenge(operation)
+            .map_err(|e| ProofError::VerificationError(e.to_string()))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents an update proof for an existing [`Account`].
 pub struct UpdateProof {
@@ -118,6 +172,10 @@ pub struct UpdateProof {
 
     pub old_account: Account,
     pub tx: Transaction,
+    /// The state [`UpdateProof::tx`] is applied to if it is a recovery fork, which
+    /// [`UpdateProof::old_account`] commits to by its digest
+    #[serde(default)]
+    pub fork_base: Option<Account>,
 
     /// Inclusion proof of [`UpdateProof::old_account`]
     pub inclusion_proof: SparseMerkleProof<TreeHasher>,
@@ -140,7 +198,7 @@ impl UpdateProof {
 
         let mut new_account = self.old_account.clone();
         new_account
-            .process_transaction(&self.tx)
+            .process_transaction_with_fork_base(&self.tx, self.fork_base.as_ref())
             .map_err(|e| ProofError::TransactionError(e.to_string()))?;
 
         // Ensure the update proof corresponds to the new account value
@@ -184,7 +242,8 @@ impl MerkleProof {
 
 impl MerkleProof {
     pub fn hashed(self) -> HashedMerkleProof {
-        let leaf_hash = self.proof.leaf().map(|node| node.hash::<TreeHasher>()).map(Digest::new);
+        let leaf = self.proof.leaf();
+        let leaf_hash = leaf.map(|node| node.hash::<TreeHasher>()).map(Digest::new);
         let sibling_hashes = self
             .proof
             .siblings()
@@ -194,6 +253,8 @@ impl MerkleProof {
             .collect();
         HashedMerkleProof {
             leaf: leaf_hash,
+            leaf_key_hash: leaf.map(|node| Digest::new(node.key_hash().0)),
+            leaf_value_hash: leaf.map(|node| Digest::new(node.value_hash().0)),
             siblings: sibling_hashes,
         }
     }
@@ -202,5 +263,7 @@ impl MerkleProof {
 #[derive(Debug, Clone)]
 pub struct HashedMerkleProof {
     pub leaf: Option<Digest>,
+    pub leaf_key_hash: Option<Digest>,
+    pub leaf_value_hash: Option<Digest>,
     pub siblings: Vec<Digest>,
 }
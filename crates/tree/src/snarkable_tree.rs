@@ -3,7 +3,7 @@ use jmt::{
     KeyHash,
     storage::{TreeReader, TreeWriter},
 };
-use prism_errors::DatabaseError;
+use prism_errors::{DatabaseError, OperationError};
 use prism_serde::binary::{FromBinary, ToBinary};
 use tracing::{debug, warn};
 
@@ -18,7 +18,7 @@ use crate::{
     AccountResponse::{self, *},
     hasher::TreeHasher,
     key_directory_tree::KeyDirectoryTree,
-    proofs::{Batch, InsertProof, MerkleProof, Proof, UpdateProof},
+    proofs::{Batch, InsertProof, MerkleProof, Proof, ServiceProof, UpdateProof},
 };
 
 /// Represents a tree that can be used to verifiably store and retrieve [`Account`]s.
@@ -66,7 +66,10 @@ where
 
     fn process_transaction(&mut self, transaction: Transaction) -> Result<Proof> {
         match &transaction.operation {
-            Operation::AddKey { .. } | Operation::RevokeKey { .. } => {
+            Operation::AddKey { .. }
+            | Operation::RevokeKey { .. }
+            | Operation::SetRotationKeys { .. }
+            | Operation::DeactivateDID {} => {
                 let key_hash = KeyHash::with::<TreeHasher>(&transaction.id);
 
                 debug!("updating account for user id {}", transaction.id);
@@ -97,11 +100,14 @@ where
                     )));
                 }
 
-                let unsigned_op = SignedPLCOp::try_from(op)?;
-                let derived_did = unsigned_op.derive_did();
-
-                // TODO(did): error instead of assert which panics
-                assert_eq!(did, &derived_did);
+                let genesis_op = SignedPLCOp::try_from(op)?;
+                let derived_did = genesis_op.derive_did_with(&self.did_config);
+                ensure!(
+                    did == &derived_did,
+                    "DID {} does not match the DID derived from its genesis operation: {}",
+                    did,
+                    derived_did
+                );
 
                 transaction.verify_cbor_signature()?;
 
@@ -110,7 +116,7 @@ where
                 let insert_proof = self.insert(account_key_hash, transaction)?;
                 Ok(Proof::Insert(Box::new(insert_proof)))
             }
-            Operation::CreateAccount { id, .. } => {
+            op @ Operation::CreateAccount { id, service_id, .. } => {
                 ensure!(
                     transaction.id == id.as_str(),
                     "Id of transaction needs to be equal to operation id"
@@ -126,28 +132,47 @@ where
                     )));
                 }
 
-                // let service_key_hash = KeyHash::with::<TreeHasher>(service_id);
+                let service_key_hash = KeyHash::with::<TreeHasher>(service_id);
 
-                // let Found(service_account, _) = self.get(service_key_hash)? else {
-                //     bail!("Failed to get account for service ID {}", service_id);
-                // };
+                let Found(service_account, service_membership_proof) =
+                    self.get(service_key_hash)?
+                else {
+                    bail!("Failed to get account for service ID {}", service_id);
+                };
 
-                // let Some(service_challenge) = service_account.service_challenge() else {
-                //     bail!("Service account does not contain a service challenge");
-                // };
+                service_account.verify_service_challenge(op)?;
+
+                debug!("creating new account for user ID {}", id);
+
+                let mut insert_proof = self.insert(account_key_hash, transaction)?;
+                insert_proof.service_proof = Some(ServiceProof {
+                    service: *service_account,
+                    membership_proof: service_membership_proof,
+                });
+                Ok(Proof::Insert(Box::new(insert_proof)))
+            }
+            Operation::RegisterService { id, .. } => {
+                ensure!(
+                    transaction.id == id.as_str(),
+                    "Id of transaction needs to be equal to operation id"
+                );
 
-                // Hash and sign credentials that have been signed by the external service
-                // let hash =
-                //     Digest::hash_items(&[id.as_bytes(), service_id.as_bytes(), &key.to_bytes()]);
+                let service_key_hash = KeyHash::with::<TreeHasher>(id);
 
-                // let ServiceChallenge::Signed(service_pubkey) = service_challenge;
-                // let ServiceChallengeInput::Signed(challenge_signature) = &challenge;
+                // Verify that the service doesn't already exist
+                if matches!(self.get(service_key_hash)?, Found(_, _)) {
+                    bail!(DatabaseError::NotFoundError(format!(
+                        "Service already exists for ID {}",
+                        id
+                    )));
+                }
 
-                debug!("creating new account for user ID {}", id);
+                debug!("registering new service with ID {}", id);
 
-                let insert_proof = self.insert(account_key_hash, transaction)?;
+                let insert_proof = self.insert(service_key_hash, transaction)?;
                 Ok(Proof::Insert(Box::new(insert_proof)))
             }
+            Operation::Unknown { name } => bail!(OperationError::UnknownOperation(name.clone())),
         }
     }
 
@@ -165,6 +190,7 @@ where
 
         let mut account = Account::default();
         account.process_transaction(&transaction)?;
+        self.retain_fork_base(&account)?;
         let serialized_account = account.encode_to_bytes()?;
 
         // the update proof just contains another nm proof
@@ -181,6 +207,7 @@ where
             tx: transaction,
             non_membership_proof,
             membership_proof,
+            service_proof: None,
         })
     }
 
@@ -194,8 +221,10 @@ where
 
         let old_account = Account::decode_from_bytes(&old_serialized_account)?;
 
+        let fork_base = self.fork_base(&old_account, &transaction)?;
         let mut new_account = old_account.clone();
-        new_account.process_transaction(&transaction)?;
+        new_account.process_transaction_with_fork_base(&transaction, fork_base.as_ref())?;
+        self.retain_fork_base(&new_account)?;
 
         let serialized_value = new_account.encode_to_bytes()?;
 
@@ -214,6 +243,7 @@ where
             key,
             update_proof,
             tx: transaction,
+            fork_base,
         })
     }
 
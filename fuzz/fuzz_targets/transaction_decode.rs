@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use prism_common::transaction::Transaction;
+
+fuzz_target!(|data: &[u8]| {
+    // Blob data is untrusted, none of the decoders may panic on it.
+    let _ = Transaction::try_decode(data);
+    let _ = Transaction::decode_from_blob_bytes(data);
+    let _ = Transaction::decode_all_from_blob_bytes(data, true);
+});